@@ -1,14 +1,19 @@
-use std::fs;
-use std::fs::OpenOptions;
-use std::io::Write;
-use std::ops::Deref;
-use std::path::PathBuf;
-use std::thread::sleep;
-use std::time::Duration;
+#[cfg(target_os = "macos")]
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    ops::Deref,
+    path::PathBuf,
+    thread::sleep,
+    time::Duration,
+};
 
+#[cfg(target_os = "macos")]
 use objc_id::Id;
 
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::os_types::base::BOOL;
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::{
     cm_sample_buffer_ref::CMSampleBufferRef, content_filter::UnsafeContentFilter,
     content_filter::UnsafeInitParams, shareable_content::UnsafeSCShareableContent,
@@ -16,16 +21,20 @@ use screencapturekit_sys::{
     stream_error_handler::UnsafeSCStreamError, stream_output_handler::UnsafeSCStreamOutput,
 };
 
+#[cfg(target_os = "macos")]
 struct StoreAudioHandler {}
 
+#[cfg(target_os = "macos")]
 struct ErrorHandler;
 
+#[cfg(target_os = "macos")]
 impl UnsafeSCStreamError for ErrorHandler {
     fn handle_error(&self) {
         eprintln!("ERROR!");
     }
 }
 
+#[cfg(target_os = "macos")]
 impl UnsafeSCStreamOutput for StoreAudioHandler {
     fn did_output_sample_buffer(&self, sample: Id<CMSampleBufferRef>, _of_type: u8) {
         println!("Got sample buffer");
@@ -67,6 +76,7 @@ impl UnsafeSCStreamOutput for StoreAudioHandler {
     }
 }
 
+#[cfg(target_os = "macos")]
 fn main() {
     let display = UnsafeSCShareableContent::get()
         .unwrap()
@@ -93,7 +103,7 @@ fn main() {
         }
     }
 
-    let stream = UnsafeSCStream::init(filter, config.into(), ErrorHandler);
+    let stream = UnsafeSCStream::init(filter, config.try_into().unwrap(), ErrorHandler);
     stream.add_stream_output(StoreAudioHandler {}, 1);
     stream.start_capture().ok();
 
@@ -105,4 +115,11 @@ fn main() {
     //
     // Here's an example of how one can do this:
     // sox -t raw -r 48000 -e floating-point -b 32 -c 1 --endian little /tmp/audio-0.raw /tmp/output-0.wav
+    //
+    // The `record_audio` example of the `screencapturekit` crate writes a WAV file directly.
+}
+
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    eprintln!("This example requires macOS");
 }
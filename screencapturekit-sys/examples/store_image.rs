@@ -1,3 +1,4 @@
+#[cfg(target_os = "macos")]
 use std::{
     fs::File,
     io::Write,
@@ -5,8 +6,11 @@ use std::{
     sync::mpsc::{sync_channel, SyncSender},
 };
 
+#[cfg(target_os = "macos")]
 use objc_foundation::INSData;
+#[cfg(target_os = "macos")]
 use objc_id::Id;
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::{
     cm_sample_buffer_ref::CMSampleBufferRef, content_filter::UnsafeContentFilter,
     content_filter::UnsafeInitParams, sc_stream_frame_info::SCFrameStatus,
@@ -15,18 +19,22 @@ use screencapturekit_sys::{
     stream_output_handler::UnsafeSCStreamOutput,
 };
 
+#[cfg(target_os = "macos")]
 struct StoreImageHandler {
     tx: SyncSender<Id<CMSampleBufferRef>>,
 }
 
+#[cfg(target_os = "macos")]
 struct ErrorHandler;
 
+#[cfg(target_os = "macos")]
 impl UnsafeSCStreamError for ErrorHandler {
     fn handle_error(&self) {
         eprintln!("ERROR!");
     }
 }
 
+#[cfg(target_os = "macos")]
 impl UnsafeSCStreamOutput for StoreImageHandler {
     fn did_output_sample_buffer(&self, sample: Id<CMSampleBufferRef>, _of_type: u8) {
         sample.get_frame_info();
@@ -35,6 +43,7 @@ impl UnsafeSCStreamOutput for StoreImageHandler {
         }
     }
 }
+#[cfg(target_os = "macos")]
 fn main() {
     let display = UnsafeSCShareableContent::get()
         .unwrap()
@@ -53,7 +62,7 @@ fn main() {
         ..Default::default()
     };
 
-    let stream = UnsafeSCStream::init(filter, config.into(), ErrorHandler);
+    let stream = UnsafeSCStream::init(filter, config.try_into().unwrap(), ErrorHandler);
     stream.add_stream_output(StoreImageHandler { tx }, 0);
     stream.start_capture().ok();
 
@@ -69,3 +78,8 @@ fn main() {
         .output()
         .expect("failedto execute process");
 }
+
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    eprintln!("This example requires macOS");
+}
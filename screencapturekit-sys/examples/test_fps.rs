@@ -1,8 +1,12 @@
+#[cfg(target_os = "macos")]
 use objc_id::Id;
+#[cfg(target_os = "macos")]
 use once_cell::sync::Lazy;
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::{
     cm_sample_buffer_ref::CMSampleBufferRef,
     content_filter::{UnsafeContentFilter, UnsafeInitParams::Display},
+    os_types::base::CMTime,
     sc_stream_frame_info::SCFrameStatus,
     shareable_content::UnsafeSCShareableContent,
     stream::UnsafeSCStream,
@@ -10,33 +14,38 @@ use screencapturekit_sys::{
     stream_error_handler::UnsafeSCStreamError,
     stream_output_handler::UnsafeSCStreamOutput,
 };
+#[cfg(target_os = "macos")]
 use std::{
     sync::atomic::{AtomicI64, Ordering},
     thread,
     time::Duration,
 };
 
+#[cfg(target_os = "macos")]
 #[repr(C)]
 struct TestHandler {}
+#[cfg(target_os = "macos")]
 impl UnsafeSCStreamError for TestHandler {
     fn handle_error(&self) {
         eprintln!("ERROR!");
     }
 }
+#[cfg(target_os = "macos")]
 static PREV_TIMESTAMP: Lazy<AtomicI64> = Lazy::new(|| AtomicI64::new(0));
 
+#[cfg(target_os = "macos")]
 impl UnsafeSCStreamOutput for TestHandler {
     fn did_output_sample_buffer(&self, sample: Id<CMSampleBufferRef>, _of_type: u8) {
         if let SCFrameStatus::Complete = sample.get_frame_info().unwrap().status() {
-            let timescale_ms = 1000000;
             let prev_timestamp = PREV_TIMESTAMP.load(Ordering::Relaxed);
-            let new_timestamp = sample.get_presentation_timestamp().value / timescale_ms;
+            let new_timestamp = (sample.get_presentation_timestamp().get_seconds() * 1000.0) as i64;
             let frame_ms = new_timestamp - prev_timestamp;
             println!("{} MS for frame", frame_ms);
             PREV_TIMESTAMP.store(new_timestamp, Ordering::Relaxed);
         }
     }
 }
+#[cfg(target_os = "macos")]
 fn main() {
     let display = UnsafeSCShareableContent::get()
         .unwrap()
@@ -54,12 +63,18 @@ fn main() {
     let config = UnsafeStreamConfiguration {
         width,
         height,
+        minimum_frame_interval: CMTime::from_fps(60),
         ..Default::default()
     };
 
-    let stream = UnsafeSCStream::init(filter, config.into(), TestHandler {});
+    let stream = UnsafeSCStream::init(filter, config.try_into().unwrap(), TestHandler {});
     stream.add_stream_output(TestHandler {}, 0);
     stream.start_capture().ok();
 
     thread::sleep(Duration::from_millis(10_000));
 }
+
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    eprintln!("This example requires macOS");
+}
@@ -1,3 +1,4 @@
+#[cfg(target_os = "macos")]
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
 pub(crate) struct AudioBuffer {
@@ -6,8 +7,10 @@ pub(crate) struct AudioBuffer {
     pub data: *mut u8,
 }
 
+#[cfg(target_os = "macos")]
 const MAX_AUDIO_BUFFERS: usize = 8;
 
+#[cfg(target_os = "macos")]
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
 pub(crate) struct AudioBufferList {
@@ -15,6 +18,7 @@ pub(crate) struct AudioBufferList {
     pub buffers: [AudioBuffer; MAX_AUDIO_BUFFERS],
 }
 
+#[derive(Debug, Clone)]
 pub struct CopiedAudioBuffer {
     pub number_channels: u32,
     pub data: Vec<u8>,
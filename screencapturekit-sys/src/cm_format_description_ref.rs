@@ -1,8 +1,11 @@
 #![allow(non_upper_case_globals)]
 
+#[cfg(target_os = "macos")]
 use crate::macros::declare_ref_type;
+#[cfg(target_os = "macos")]
 declare_ref_type!(CMFormatDescriptionRef);
 
+#[cfg(target_os = "macos")]
 impl CMFormatDescriptionRef {
     pub fn audio_format_description_get_stream_basic_description(
         &self,
@@ -177,6 +180,7 @@ pub const kAudioFormatEnhancedAC3: ::std::os::raw::c_uint = 1700998451;
 pub const kAudioFormatFLAC: ::std::os::raw::c_uint = 1718378851;
 pub const kAudioFormatOpus: ::std::os::raw::c_uint = 1869641075;
 
+#[cfg(target_os = "macos")]
 extern "C" {
     pub fn CMAudioFormatDescriptionGetStreamBasicDescription(
         desc: *const CMFormatDescriptionRef,
@@ -95,12 +95,11 @@ impl CMSampleBufferRef {
         audio_buffer_list_ptr: *mut AudioBufferList,
     ) -> Vec<CopiedAudioBuffer> {
         let audio_buffer_list = unsafe { *audio_buffer_list_ptr };
-        let number_buffers = audio_buffer_list.number_buffers;
         let mut buffers = Vec::new();
         for i in 0..audio_buffer_list.number_buffers {
             let audio_buffer = audio_buffer_list.buffers[i as usize];
             buffers.push(CopiedAudioBuffer {
-                number_channels: number_buffers,
+                number_channels: audio_buffer.number_channels,
                 data: unsafe {
                     std::slice::from_raw_parts(
                         audio_buffer.data,
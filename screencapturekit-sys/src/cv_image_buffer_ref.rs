@@ -10,6 +10,8 @@ impl CVImageBufferRef {
     pub fn as_pixel_buffer(&self) -> ShareId<CVPixelBufferRef> {
         unsafe { ShareId::from_ptr(self.as_mut_ptr().cast()) }
     }
+    /// Encodes the image with CoreImage, which creates a `CIContext` on every call. The
+    /// `image_encoding` module of the `screencapturekit` crate encodes PNG and JPEG without one.
     pub fn get_jpeg_data(&self) -> ShareId<NSData> {
         unsafe {
             let ci_image_class = class!(CIImage);
@@ -1,6 +1,6 @@
 use crate::{
     macros::declare_ref_type,
-    os_types::base::{Boolean, CVPixelBufferLockFlags, CVReturn, SizeT, VoidPtr},
+    os_types::base::{Boolean, CVPixelBufferLockFlags, CVReturn, OSType, SizeT, VoidPtr},
 };
 
 declare_ref_type!(CVPixelBufferRef);
@@ -12,6 +12,27 @@ impl CVPixelBufferRef {
     pub fn plane_count(&self) -> SizeT {
         unsafe { CVPixelBufferGetPlaneCount(self) }
     }
+    pub fn get_pixel_format_type(&self) -> OSType {
+        unsafe { CVPixelBufferGetPixelFormatType(self) }
+    }
+    pub fn get_width(&self) -> SizeT {
+        unsafe { CVPixelBufferGetWidth(self) }
+    }
+    pub fn get_height(&self) -> SizeT {
+        unsafe { CVPixelBufferGetHeight(self) }
+    }
+    pub fn get_bytes_per_row(&self) -> SizeT {
+        unsafe { CVPixelBufferGetBytesPerRow(self) }
+    }
+    pub fn get_width_of_plane(&self, plane_index: SizeT) -> SizeT {
+        unsafe { CVPixelBufferGetWidthOfPlane(self, plane_index) }
+    }
+    pub fn get_height_of_plane(&self, plane_index: SizeT) -> SizeT {
+        unsafe { CVPixelBufferGetHeightOfPlane(self, plane_index) }
+    }
+    pub fn get_bytes_per_row_of_plane(&self, plane_index: SizeT) -> SizeT {
+        unsafe { CVPixelBufferGetBytesPerRowOfPlane(self, plane_index) }
+    }
     pub fn get_base_address(&self) -> VoidPtr {
         unsafe { CVPixelBufferGetBaseAddress(self) }
     }
@@ -34,6 +55,22 @@ extern "C" {
         plane_index: SizeT,
     ) -> VoidPtr;
     fn CVPixelBufferGetPlaneCount(pixel_buf: *const CVPixelBufferRef) -> SizeT;
+    fn CVPixelBufferGetPixelFormatType(pixel_buf: *const CVPixelBufferRef) -> OSType;
+    fn CVPixelBufferGetWidth(pixel_buf: *const CVPixelBufferRef) -> SizeT;
+    fn CVPixelBufferGetHeight(pixel_buf: *const CVPixelBufferRef) -> SizeT;
+    fn CVPixelBufferGetBytesPerRow(pixel_buf: *const CVPixelBufferRef) -> SizeT;
+    fn CVPixelBufferGetWidthOfPlane(
+        pixel_buf: *const CVPixelBufferRef,
+        plane_index: SizeT,
+    ) -> SizeT;
+    fn CVPixelBufferGetHeightOfPlane(
+        pixel_buf: *const CVPixelBufferRef,
+        plane_index: SizeT,
+    ) -> SizeT;
+    fn CVPixelBufferGetBytesPerRowOfPlane(
+        pixel_buf: *const CVPixelBufferRef,
+        plane_index: SizeT,
+    ) -> SizeT;
 
     fn CVPixelBufferIsPlanar(pixel_buf: *const CVPixelBufferRef) -> Boolean;
     fn CVPixelBufferLockBaseAddress(
@@ -1,16 +1,26 @@
 pub mod as_ptr;
 pub mod audio_buffer;
+#[cfg(target_os = "macos")]
 pub mod cm_block_buffer_ref;
 pub mod cm_format_description_ref;
+#[cfg(target_os = "macos")]
 pub mod cm_sample_buffer_ref;
+#[cfg(target_os = "macos")]
 pub mod content_filter;
+#[cfg(target_os = "macos")]
 pub mod cv_image_buffer_ref;
+#[cfg(target_os = "macos")]
 pub mod cv_pixel_buffer_ref;
+#[cfg(target_os = "macos")]
 pub mod macros;
 pub mod os_types;
 pub mod sc_stream_frame_info;
+#[cfg(target_os = "macos")]
 pub mod shareable_content;
+#[cfg(target_os = "macos")]
 pub mod stream;
 pub mod stream_configuration;
+#[cfg(target_os = "macos")]
 pub mod stream_error_handler;
+#[cfg(target_os = "macos")]
 pub mod stream_output_handler;
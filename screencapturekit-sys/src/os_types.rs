@@ -1,5 +1,7 @@
 pub mod base;
+pub mod cm_time;
 pub mod four_char_code;
 pub mod geometry;
 pub mod graphics;
+#[cfg(target_os = "macos")]
 pub mod rc;
@@ -21,6 +21,7 @@ pub type BOOL = ::std::ffi::c_schar;
 pub type OSType = FourCharCode;
 pub type Boolean = ::std::ffi::c_uchar;
 pub type SizeT = ::std::ffi::c_ulong;
+pub type NSInteger = ::std::ffi::c_long;
 pub type PidT = ::std::ffi::c_int;
 pub type CMTimeValue = SInt64;
 pub type CMTimeScale = SInt32;
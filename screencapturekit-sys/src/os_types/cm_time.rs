@@ -0,0 +1,546 @@
+//! Arithmetic, comparison and conversions of [`CMTime`], following the rules of CoreMedia.
+//!
+//! A `CMTime` is numeric when it is valid and none of the infinity or indefinite flags are set.
+//! Times that are not numeric carry through arithmetic like NaN and infinities do for floats.
+
+use std::{
+    cmp::Ordering,
+    ops::{Add, Mul, Neg, Sub},
+    time::Duration,
+};
+
+use super::base::{
+    CMTime, CMTimeEpoch, CMTimeFlags, CMTimeScale, CMTimeValue, CMTIME_FLAGS_HAS_BEEN_ROUNDED,
+    CMTIME_FLAGS_INDEFINITE, CMTIME_FLAGS_NEGATIVE_INFINITY, CMTIME_FLAGS_POSITIVE_INFINITY,
+    CMTIME_FLAGS_VALID,
+};
+
+/// How to round a time that can't be represented exactly, like `CMTimeRoundingMethod`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u32)]
+pub enum CMTimeRoundingMethod {
+    #[default]
+    RoundHalfAwayFromZero = 1,
+    RoundTowardZero = 2,
+    RoundAwayFromZero = 3,
+    RoundTowardPositiveInfinity = 5,
+    RoundTowardNegativeInfinity = 6,
+}
+
+impl CMTimeRoundingMethod {
+    /// Divides `numerator` by a positive `denominator`, returning the quotient and whether it
+    /// is exact.
+    fn divide(self, numerator: i128, denominator: i128) -> (i128, bool) {
+        let quotient = numerator / denominator;
+        let remainder = numerator % denominator;
+        if remainder == 0 {
+            return (quotient, true);
+        }
+        let away = numerator.signum();
+        let rounded = match self {
+            CMTimeRoundingMethod::RoundHalfAwayFromZero if 2 * remainder.abs() >= denominator => {
+                quotient + away
+            }
+            CMTimeRoundingMethod::RoundHalfAwayFromZero | CMTimeRoundingMethod::RoundTowardZero => {
+                quotient
+            }
+            CMTimeRoundingMethod::RoundAwayFromZero => quotient + away,
+            CMTimeRoundingMethod::RoundTowardPositiveInfinity => quotient + i128::from(away > 0),
+            CMTimeRoundingMethod::RoundTowardNegativeInfinity => quotient - i128::from(away < 0),
+        };
+        (rounded, false)
+    }
+}
+
+/// The kinds of times in the order `CMTimeCompare` sorts them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    NegativeInfinity,
+    Numeric,
+    Indefinite,
+    PositiveInfinity,
+    Invalid,
+}
+
+const fn time(value: CMTimeValue, timescale: CMTimeScale, flags: CMTimeFlags) -> CMTime {
+    CMTime {
+        value,
+        timescale,
+        flags,
+        epoch: 0,
+    }
+}
+
+impl CMTime {
+    pub const INVALID: CMTime = time(0, 0, 0);
+    pub const ZERO: CMTime = time(0, 1, CMTIME_FLAGS_VALID);
+    pub const POSITIVE_INFINITY: CMTime =
+        time(0, 0, CMTIME_FLAGS_VALID | CMTIME_FLAGS_POSITIVE_INFINITY);
+    pub const NEGATIVE_INFINITY: CMTime =
+        time(0, 0, CMTIME_FLAGS_VALID | CMTIME_FLAGS_NEGATIVE_INFINITY);
+    pub const INDEFINITE: CMTime = time(0, 0, CMTIME_FLAGS_VALID | CMTIME_FLAGS_INDEFINITE);
+
+    /// `value / timescale` seconds, like `CMTimeMake`. A timescale below 1 makes an invalid time.
+    pub const fn new(value: CMTimeValue, timescale: CMTimeScale) -> Self {
+        if timescale > 0 {
+            time(value, timescale, CMTIME_FLAGS_VALID)
+        } else {
+            Self::INVALID
+        }
+    }
+    /// The time in `epoch`, like `CMTimeMakeWithEpoch`.
+    pub const fn with_epoch(self, epoch: CMTimeEpoch) -> Self {
+        CMTime { epoch, ..self }
+    }
+    /// The interval between frames at `fps` frames per second, for example for
+    /// `minimum_frame_interval`.
+    pub const fn from_fps(fps: u32) -> Self {
+        if fps > i32::MAX as u32 {
+            return Self::INVALID;
+        }
+        Self::new(1, fps as i32)
+    }
+    /// The nearest time to `seconds` in `timescale`, like `CMTimeMakeWithSeconds`.
+    ///
+    /// NaN makes an invalid time and times too large for `timescale` saturate to infinity.
+    pub fn from_seconds(seconds: f64, timescale: CMTimeScale) -> Self {
+        if seconds.is_nan() || timescale <= 0 {
+            return Self::INVALID;
+        }
+        let value = (seconds * f64::from(timescale)).round();
+        if value >= CMTimeValue::MAX as f64 {
+            return Self::POSITIVE_INFINITY;
+        }
+        if value < CMTimeValue::MIN as f64 {
+            return Self::NEGATIVE_INFINITY;
+        }
+        let mut time = Self::new(value as CMTimeValue, timescale);
+        if value / f64::from(timescale) != seconds {
+            time.flags |= CMTIME_FLAGS_HAS_BEEN_ROUNDED;
+        }
+        time
+    }
+
+    fn kind(&self) -> Kind {
+        if self.flags & CMTIME_FLAGS_VALID == 0 {
+            Kind::Invalid
+        } else if self.flags & CMTIME_FLAGS_INDEFINITE != 0 {
+            Kind::Indefinite
+        } else if self.flags & CMTIME_FLAGS_POSITIVE_INFINITY != 0 {
+            Kind::PositiveInfinity
+        } else if self.flags & CMTIME_FLAGS_NEGATIVE_INFINITY != 0 {
+            Kind::NegativeInfinity
+        } else if self.timescale <= 0 {
+            // a valid flag without a timescale is garbage
+            Kind::Invalid
+        } else {
+            Kind::Numeric
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.kind() != Kind::Invalid
+    }
+    /// Whether the time is valid and finite, so that it has a value.
+    pub fn is_numeric(&self) -> bool {
+        self.kind() == Kind::Numeric
+    }
+    pub fn is_positive_infinity(&self) -> bool {
+        self.kind() == Kind::PositiveInfinity
+    }
+    pub fn is_negative_infinity(&self) -> bool {
+        self.kind() == Kind::NegativeInfinity
+    }
+    pub fn is_indefinite(&self) -> bool {
+        self.kind() == Kind::Indefinite
+    }
+    /// Whether the time was rounded when it was made or computed.
+    pub fn has_been_rounded(&self) -> bool {
+        self.is_numeric() && self.flags & CMTIME_FLAGS_HAS_BEEN_ROUNDED != 0
+    }
+
+    /// The time in seconds, like `CMTimeGetSeconds`: NaN for invalid and indefinite times and
+    /// infinity for infinite ones.
+    pub fn get_seconds(&self) -> f64 {
+        match self.kind() {
+            Kind::Numeric => self.value as f64 / f64::from(self.timescale),
+            Kind::PositiveInfinity => f64::INFINITY,
+            Kind::NegativeInfinity => f64::NEG_INFINITY,
+            Kind::Indefinite | Kind::Invalid => f64::NAN,
+        }
+    }
+
+    /// Makes a numeric time from an exact or rounded value, saturating to infinity.
+    fn from_value(value: i128, timescale: CMTimeScale, epoch: CMTimeEpoch, exact: bool) -> Self {
+        let Ok(value) = CMTimeValue::try_from(value) else {
+            return if value > 0 {
+                Self::POSITIVE_INFINITY
+            } else {
+                Self::NEGATIVE_INFINITY
+            };
+        };
+        let mut time = Self::new(value, timescale).with_epoch(epoch);
+        if !exact {
+            time.flags |= CMTIME_FLAGS_HAS_BEEN_ROUNDED;
+        }
+        time
+    }
+
+    /// The time in another timescale, like `CMTimeConvertScale`.
+    ///
+    /// Times that are not numeric are returned as they are.
+    pub fn convert_scale(self, timescale: CMTimeScale, method: CMTimeRoundingMethod) -> Self {
+        if !self.is_numeric() {
+            return self;
+        }
+        if timescale <= 0 {
+            return Self::INVALID;
+        }
+        let (value, exact) = method.divide(
+            i128::from(self.value) * i128::from(timescale),
+            i128::from(self.timescale),
+        );
+        let mut time = Self::from_value(value, timescale, self.epoch, exact);
+        if self.has_been_rounded() && time.is_numeric() {
+            time.flags |= CMTIME_FLAGS_HAS_BEEN_ROUNDED;
+        }
+        time
+    }
+
+    /// The time multiplied by `multiplier / divisor`, like `CMTimeMultiplyByRatio`, rounding
+    /// half away from zero in the same timescale.
+    pub fn multiply_by_ratio(self, multiplier: i32, divisor: i32) -> Self {
+        if divisor == 0 {
+            return Self::INVALID;
+        }
+        match self.kind() {
+            Kind::Numeric => {
+                let (value, exact) = CMTimeRoundingMethod::RoundHalfAwayFromZero.divide(
+                    i128::from(self.value) * i128::from(multiplier) * i128::from(divisor.signum()),
+                    i128::from(divisor).abs(),
+                );
+                Self::from_value(
+                    value,
+                    self.timescale,
+                    self.epoch,
+                    exact && !self.has_been_rounded(),
+                )
+            }
+            Kind::PositiveInfinity | Kind::NegativeInfinity => {
+                match (multiplier.signum() * divisor.signum()).cmp(&0) {
+                    Ordering::Equal => Self::INVALID,
+                    Ordering::Greater => self,
+                    Ordering::Less => -self,
+                }
+            }
+            Kind::Indefinite | Kind::Invalid => self,
+        }
+    }
+}
+
+impl Neg for CMTime {
+    type Output = CMTime;
+
+    fn neg(self) -> CMTime {
+        match self.kind() {
+            Kind::Numeric => match self.value.checked_neg() {
+                Some(value) => CMTime { value, ..self },
+                None => Self::POSITIVE_INFINITY,
+            },
+            Kind::PositiveInfinity => Self::NEGATIVE_INFINITY,
+            Kind::NegativeInfinity => Self::POSITIVE_INFINITY,
+            Kind::Indefinite | Kind::Invalid => self,
+        }
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Adds times like `CMTimeAdd`.
+///
+/// Sums of numeric times are in the least common multiple of their timescales, or in the larger
+/// timescale with rounding when that doesn't fit. The epoch of the left side is kept. Invalid
+/// times make invalid sums, as do opposite infinities, and indefinite times indefinite ones.
+impl Add for CMTime {
+    type Output = CMTime;
+
+    fn add(self, rhs: CMTime) -> CMTime {
+        match (self.kind(), rhs.kind()) {
+            (Kind::Invalid, _) | (_, Kind::Invalid) => Self::INVALID,
+            (Kind::PositiveInfinity, Kind::NegativeInfinity)
+            | (Kind::NegativeInfinity, Kind::PositiveInfinity) => Self::INVALID,
+            (Kind::Indefinite, _) | (_, Kind::Indefinite) => Self::INDEFINITE,
+            (Kind::PositiveInfinity, _) | (_, Kind::PositiveInfinity) => Self::POSITIVE_INFINITY,
+            (Kind::NegativeInfinity, _) | (_, Kind::NegativeInfinity) => Self::NEGATIVE_INFINITY,
+            (Kind::Numeric, Kind::Numeric) => {
+                let (a, b) = (i64::from(self.timescale), i64::from(rhs.timescale));
+                let lcm = a / gcd(a, b) * b;
+                let timescale =
+                    CMTimeScale::try_from(lcm).unwrap_or(self.timescale.max(rhs.timescale));
+                let method = CMTimeRoundingMethod::RoundHalfAwayFromZero;
+                let left = self.convert_scale(timescale, method);
+                let right = rhs.convert_scale(timescale, method);
+                let mut sum = Self::from_value(
+                    i128::from(left.value) + i128::from(right.value),
+                    timescale,
+                    self.epoch,
+                    !left.has_been_rounded() && !right.has_been_rounded(),
+                );
+                if !left.is_numeric() || !right.is_numeric() {
+                    // one side saturated while converting
+                    sum = left + right;
+                }
+                sum
+            }
+        }
+    }
+}
+
+impl Sub for CMTime {
+    type Output = CMTime;
+
+    fn sub(self, rhs: CMTime) -> CMTime {
+        self + -rhs
+    }
+}
+
+/// Multiplies the time like `CMTimeMultiply`, saturating to infinity.
+impl Mul<i32> for CMTime {
+    type Output = CMTime;
+
+    fn mul(self, rhs: i32) -> CMTime {
+        self.multiply_by_ratio(rhs, 1)
+    }
+}
+
+/// Orders times like `CMTimeCompare`: negative infinity, then numeric times by epoch and value,
+/// then indefinite times, positive infinity and last invalid times.
+impl Ord for CMTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.kind(), other.kind()) {
+            (Kind::Numeric, Kind::Numeric) => self.epoch.cmp(&other.epoch).then_with(|| {
+                let left = i128::from(self.value) * i128::from(other.timescale);
+                let right = i128::from(other.value) * i128::from(self.timescale);
+                left.cmp(&right)
+            }),
+            (left, right) => left.cmp(&right),
+        }
+    }
+}
+
+impl PartialOrd for CMTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Times are equal when they are the same instant, so `1/2` equals `2/4`.
+impl PartialEq for CMTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for CMTime {}
+
+impl From<Duration> for CMTime {
+    /// The duration in nanoseconds, saturating to infinity after about 292 years.
+    fn from(duration: Duration) -> Self {
+        match CMTimeValue::try_from(duration.as_nanos()) {
+            Ok(nanos) => CMTime::new(nanos, 1_000_000_000),
+            Err(_) => CMTime::POSITIVE_INFINITY,
+        }
+    }
+}
+
+impl TryFrom<CMTime> for Duration {
+    type Error = String;
+
+    /// Converts non-negative numeric times, rounding toward zero to whole nanoseconds.
+    fn try_from(time: CMTime) -> Result<Self, Self::Error> {
+        if !time.is_numeric() || time.value < 0 {
+            return Err(format!("{time:?} is not a duration"));
+        }
+        let timescale = time.timescale as u64;
+        let value = time.value as u64;
+        let nanos = u128::from(value % timescale) * 1_000_000_000 / u128::from(timescale);
+        Ok(Duration::new(value / timescale, nanos as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constructors() {
+        assert!(CMTime::new(1, 60).is_numeric());
+        assert!(!CMTime::new(1, 0).is_valid());
+        assert!(!CMTime::default().is_valid());
+        assert_eq!(CMTime::from_fps(60), CMTime::new(1, 60));
+        assert_eq!(CMTime::from_fps(0), CMTime::INVALID);
+        assert_eq!(CMTime::from_seconds(1.5, 600), CMTime::new(900, 600));
+        assert!(!CMTime::from_seconds(1.5, 600).has_been_rounded());
+        let third = CMTime::from_seconds(1.0 / 3.0, 1000);
+        assert_eq!(third.value, 333);
+        assert!(third.has_been_rounded());
+        assert!(CMTime::from_seconds(f64::NAN, 1).flags & CMTIME_FLAGS_VALID == 0);
+        assert!(CMTime::from_seconds(f64::INFINITY, 1).is_positive_infinity());
+        assert!(CMTime::from_seconds(-1e300, 1).is_negative_infinity());
+        assert_eq!(CMTime::new(5, 10).with_epoch(2).epoch, 2);
+    }
+
+    #[test]
+    fn test_seconds_and_durations() {
+        assert_eq!(CMTime::new(-3, 4).get_seconds(), -0.75);
+        assert_eq!(CMTime::POSITIVE_INFINITY.get_seconds(), f64::INFINITY);
+        assert_eq!(CMTime::NEGATIVE_INFINITY.get_seconds(), f64::NEG_INFINITY);
+        assert!(CMTime::INDEFINITE.get_seconds().is_nan());
+        assert!(CMTime::INVALID.get_seconds().is_nan());
+
+        let time = CMTime::from(Duration::from_millis(1500));
+        assert_eq!(time, CMTime::new(3, 2));
+        assert_eq!(time.timescale, 1_000_000_000);
+        assert_eq!(CMTime::from(Duration::MAX), CMTime::POSITIVE_INFINITY);
+        assert_eq!(
+            Duration::try_from(CMTime::new(1, 3)),
+            Ok(Duration::from_nanos(333_333_333))
+        );
+        assert_eq!(
+            Duration::try_from(CMTime::new(i64::MAX, 1)),
+            Ok(Duration::from_secs(i64::MAX as u64))
+        );
+        assert!(Duration::try_from(CMTime::new(-1, 3)).is_err());
+        assert!(Duration::try_from(CMTime::POSITIVE_INFINITY).is_err());
+        assert!(Duration::try_from(CMTime::INVALID).is_err());
+    }
+
+    #[test]
+    fn test_convert_scale() {
+        let time = CMTime::new(1001, 30000);
+        assert_eq!(
+            time.convert_scale(600, CMTimeRoundingMethod::default())
+                .value,
+            20
+        );
+        let rounded = |value, method| CMTime::new(value, 4).convert_scale(2, method).value;
+        use CMTimeRoundingMethod::*;
+        // 1.5 and -1.5 in halves, and 0.5 and -0.5
+        for (value, method, expected) in [
+            (3, RoundHalfAwayFromZero, 2),
+            (-3, RoundHalfAwayFromZero, -2),
+            (1, RoundHalfAwayFromZero, 1),
+            (3, RoundTowardZero, 1),
+            (-3, RoundTowardZero, -1),
+            (3, RoundAwayFromZero, 2),
+            (-3, RoundAwayFromZero, -2),
+            (3, RoundTowardPositiveInfinity, 2),
+            (-3, RoundTowardPositiveInfinity, -1),
+            (3, RoundTowardNegativeInfinity, 1),
+            (-3, RoundTowardNegativeInfinity, -2),
+            (-1, RoundTowardPositiveInfinity, 0),
+        ] {
+            assert_eq!(rounded(value, method), expected, "{value} {method:?}");
+        }
+        assert!(CMTime::new(3, 4)
+            .convert_scale(2, RoundTowardZero)
+            .has_been_rounded());
+        assert!(!CMTime::new(2, 4)
+            .convert_scale(2, RoundTowardZero)
+            .has_been_rounded());
+        assert!(CMTime::new(i64::MAX, 1)
+            .convert_scale(1000, RoundTowardZero)
+            .is_positive_infinity());
+        assert!(CMTime::INDEFINITE
+            .convert_scale(1000, RoundTowardZero)
+            .is_indefinite());
+        assert!(!CMTime::new(1, 2)
+            .convert_scale(0, RoundTowardZero)
+            .is_valid());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let sum = CMTime::new(1, 3) + CMTime::new(1, 4);
+        assert_eq!((sum.value, sum.timescale), (7, 12));
+        assert!(!sum.has_been_rounded());
+        let difference = CMTime::new(1, 3) - CMTime::new(1, 2);
+        assert_eq!(difference, CMTime::new(-1, 6));
+        assert_eq!(CMTime::new(5, 10) + CMTime::ZERO, CMTime::new(1, 2));
+        assert_eq!(CMTime::from_fps(60) * 60, CMTime::new(1, 1));
+        assert_eq!(CMTime::new(10, 1).multiply_by_ratio(2, 3).value, 7);
+        assert!(CMTime::new(10, 1)
+            .multiply_by_ratio(2, 3)
+            .has_been_rounded());
+        assert_eq!(
+            CMTime::new(10, 1).multiply_by_ratio(1, -2),
+            CMTime::new(-5, 1)
+        );
+        assert!(!CMTime::new(10, 1).multiply_by_ratio(1, 0).is_valid());
+
+        // timescales whose least common multiple doesn't fit use the larger one
+        let sum = CMTime::new(1, 1_000_000_007) + CMTime::new(1, 999_999_937);
+        assert_eq!(sum.timescale, 1_000_000_007);
+        assert!(sum.has_been_rounded());
+        assert!((sum.get_seconds() - 2e-9).abs() < 1e-15);
+
+        // overflow saturates
+        assert!((CMTime::new(i64::MAX, 1) + CMTime::new(1, 1)).is_positive_infinity());
+        assert!((CMTime::new(i64::MIN, 1) * 2).is_negative_infinity());
+        assert!((-CMTime::new(i64::MIN, 1)).is_positive_infinity());
+    }
+
+    #[test]
+    fn test_special_times() {
+        let one = CMTime::new(1, 1);
+        let inf = CMTime::POSITIVE_INFINITY;
+        let neg_inf = CMTime::NEGATIVE_INFINITY;
+        assert!((one + inf).is_positive_infinity());
+        assert!((one - inf).is_negative_infinity());
+        assert!(!(inf + neg_inf).is_valid());
+        assert!((inf - neg_inf).is_positive_infinity());
+        assert!((CMTime::INDEFINITE + one).is_indefinite());
+        assert!((CMTime::INDEFINITE + inf).is_indefinite());
+        assert!(!(CMTime::INVALID + CMTime::INDEFINITE).is_valid());
+        assert!((inf * -2).is_negative_infinity());
+        assert!(!inf.multiply_by_ratio(0, 1).is_valid());
+        assert!((-neg_inf).is_positive_infinity());
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut times = vec![
+            CMTime::INVALID,
+            CMTime::new(1, 2),
+            CMTime::POSITIVE_INFINITY,
+            CMTime::new(-7, 1),
+            CMTime::INDEFINITE,
+            CMTime::NEGATIVE_INFINITY,
+            CMTime::new(1, 3).with_epoch(1),
+            CMTime::new(2, 5),
+        ];
+        times.sort();
+        let expected = [
+            CMTime::NEGATIVE_INFINITY,
+            CMTime::new(-7, 1),
+            CMTime::new(2, 5),
+            CMTime::new(1, 2),
+            CMTime::new(1, 3).with_epoch(1),
+            CMTime::INDEFINITE,
+            CMTime::POSITIVE_INFINITY,
+            CMTime::INVALID,
+        ];
+        assert_eq!(times, expected);
+        assert_eq!(CMTime::new(1, 2), CMTime::new(500, 1000));
+        assert_ne!(CMTime::new(1, 2), CMTime::new(1, 2).with_epoch(1));
+        assert_eq!(CMTime::INVALID, CMTime::new(3, 0));
+        assert!(CMTime::new(i64::MAX, 1) > CMTime::new(i64::MAX, 2));
+        assert_eq!(CMTime::new(1, 3).max(CMTime::new(1, 2)), CMTime::new(1, 2));
+    }
+}
@@ -2,44 +2,108 @@
 #![allow(non_upper_case_globals)]
 #![allow(clippy::upper_case_acronyms)]
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use super::base::CGFloat;
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CGSize {
     pub width: CGFloat,
     pub height: CGFloat,
 }
 
 impl CGSize {
+    pub const ZERO: CGSize = CGSize::new(0.0, 0.0);
+
     #[inline]
-    pub fn new(width: CGFloat, height: CGFloat) -> CGSize {
+    pub const fn new(width: CGFloat, height: CGFloat) -> CGSize {
         CGSize { width, height }
     }
+    /// Returns `true` if the width or the height is zero, like [`CGRect::is_empty`].
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.width == 0.0 || self.height == 0.0
+    }
+    #[inline]
+    pub fn scale(&self, sx: CGFloat, sy: CGFloat) -> CGSize {
+        CGSize::new(self.width * sx, self.height * sy)
+    }
+}
+
+impl From<(CGFloat, CGFloat)> for CGSize {
+    fn from((width, height): (CGFloat, CGFloat)) -> Self {
+        CGSize::new(width, height)
+    }
+}
+
+impl From<CGSize> for (CGFloat, CGFloat) {
+    fn from(size: CGSize) -> Self {
+        (size.width, size.height)
+    }
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CGPoint {
     pub x: CGFloat,
     pub y: CGFloat,
 }
 
 impl CGPoint {
+    pub const ZERO: CGPoint = CGPoint::new(0.0, 0.0);
+
     #[inline]
-    pub fn new(x: CGFloat, y: CGFloat) -> CGPoint {
+    pub const fn new(x: CGFloat, y: CGFloat) -> CGPoint {
         CGPoint { x, y }
     }
+    #[inline]
+    pub fn offset(&self, dx: CGFloat, dy: CGFloat) -> CGPoint {
+        CGPoint::new(self.x + dx, self.y + dy)
+    }
+    #[inline]
+    pub fn scale(&self, sx: CGFloat, sy: CGFloat) -> CGPoint {
+        CGPoint::new(self.x * sx, self.y * sy)
+    }
+    pub fn distance_to(&self, other: &CGPoint) -> CGFloat {
+        (other.x - self.x).hypot(other.y - self.y)
+    }
 }
 
+impl From<(CGFloat, CGFloat)> for CGPoint {
+    fn from((x, y): (CGFloat, CGFloat)) -> Self {
+        CGPoint::new(x, y)
+    }
+}
+
+impl From<CGPoint> for (CGFloat, CGFloat) {
+    fn from(point: CGPoint) -> Self {
+        (point.x, point.y)
+    }
+}
+
+/// A rectangle in CoreGraphics coordinates.
+///
+/// Like the `CGRect` functions, the methods treat a rectangle with a negative width or height
+/// as its [`standardize`](CGRect::standardize)d form. Intersections that do not overlap are
+/// `None` instead of `CGRectNull`.
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CGRect {
     pub origin: CGPoint,
     pub size: CGSize,
 }
 
 impl CGRect {
+    pub const ZERO: CGRect = CGRect {
+        origin: CGPoint::ZERO,
+        size: CGSize::ZERO,
+    };
+
     #[inline]
     pub fn new(origin: &CGPoint, size: &CGSize) -> CGRect {
         CGRect {
@@ -47,4 +111,248 @@ impl CGRect {
             size: *size,
         }
     }
+    #[inline]
+    pub const fn from_xywh(x: CGFloat, y: CGFloat, width: CGFloat, height: CGFloat) -> CGRect {
+        CGRect {
+            origin: CGPoint::new(x, y),
+            size: CGSize::new(width, height),
+        }
+    }
+
+    /// The same rectangle with a non-negative width and height.
+    pub fn standardize(&self) -> CGRect {
+        let (x, width) = standardize(self.origin.x, self.size.width);
+        let (y, height) = standardize(self.origin.y, self.size.height);
+        CGRect::from_xywh(x, y, width, height)
+    }
+    pub fn get_min_x(&self) -> CGFloat {
+        self.standardize().origin.x
+    }
+    pub fn get_mid_x(&self) -> CGFloat {
+        self.origin.x + self.size.width / 2.0
+    }
+    pub fn get_max_x(&self) -> CGFloat {
+        let rect = self.standardize();
+        rect.origin.x + rect.size.width
+    }
+    pub fn get_min_y(&self) -> CGFloat {
+        self.standardize().origin.y
+    }
+    pub fn get_mid_y(&self) -> CGFloat {
+        self.origin.y + self.size.height / 2.0
+    }
+    pub fn get_max_y(&self) -> CGFloat {
+        let rect = self.standardize();
+        rect.origin.y + rect.size.height
+    }
+    pub fn get_center(&self) -> CGPoint {
+        CGPoint::new(self.get_mid_x(), self.get_mid_y())
+    }
+    /// Returns `true` if the rectangle has no area, like `CGRectIsEmpty`. A negative width or
+    /// height is not empty.
+    pub fn is_empty(&self) -> bool {
+        self.size.is_empty()
+    }
+
+    /// Returns `true` if `point` is inside the rectangle, including its minimum edges but not
+    /// its maximum edges, like `CGRectContainsPoint`.
+    pub fn contains_point(&self, point: &CGPoint) -> bool {
+        (self.get_min_x()..self.get_max_x()).contains(&point.x)
+            && (self.get_min_y()..self.get_max_y()).contains(&point.y)
+    }
+    pub fn contains_rect(&self, other: &CGRect) -> bool {
+        !other.is_empty()
+            && self.get_min_x() <= other.get_min_x()
+            && self.get_min_y() <= other.get_min_y()
+            && self.get_max_x() >= other.get_max_x()
+            && self.get_max_y() >= other.get_max_y()
+    }
+    /// Returns `true` if the rectangles share some area.
+    pub fn intersects(&self, other: &CGRect) -> bool {
+        self.intersection(other)
+            .is_some_and(|rect| !rect.is_empty())
+    }
+    /// The area both rectangles cover, or `None` if they do not touch.
+    pub fn intersection(&self, other: &CGRect) -> Option<CGRect> {
+        let min_x = self.get_min_x().max(other.get_min_x());
+        let min_y = self.get_min_y().max(other.get_min_y());
+        let max_x = self.get_max_x().min(other.get_max_x());
+        let max_y = self.get_max_y().min(other.get_max_y());
+        (min_x <= max_x && min_y <= max_y)
+            .then(|| CGRect::from_xywh(min_x, min_y, max_x - min_x, max_y - min_y))
+    }
+    /// The smallest rectangle that contains both rectangles. Like `CGRectUnion`, a rectangle
+    /// with a zero size still extends the union to its origin.
+    pub fn union(&self, other: &CGRect) -> CGRect {
+        let min_x = self.get_min_x().min(other.get_min_x());
+        let min_y = self.get_min_y().min(other.get_min_y());
+        let max_x = self.get_max_x().max(other.get_max_x());
+        let max_y = self.get_max_y().max(other.get_max_y());
+        CGRect::from_xywh(min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+
+    /// Moves every edge `dx` and `dy` inwards, or outwards for negative values. Returns `None`
+    /// when the rectangle would have a negative size, like `CGRectInset` returning `CGRectNull`.
+    pub fn inset(&self, dx: CGFloat, dy: CGFloat) -> Option<CGRect> {
+        let rect = self.standardize();
+        let (width, height) = (rect.size.width - 2.0 * dx, rect.size.height - 2.0 * dy);
+        (width >= 0.0 && height >= 0.0)
+            .then(|| CGRect::from_xywh(rect.origin.x + dx, rect.origin.y + dy, width, height))
+    }
+    pub fn offset(&self, dx: CGFloat, dy: CGFloat) -> CGRect {
+        CGRect::new(&self.origin.offset(dx, dy), &self.size)
+    }
+    /// Scales the origin and the size, for example from points to pixels.
+    pub fn scale(&self, sx: CGFloat, sy: CGFloat) -> CGRect {
+        CGRect::new(&self.origin.scale(sx, sy), &self.size.scale(sx, sy))
+    }
+    /// The smallest rectangle with integer coordinates that contains this one, like
+    /// `CGRectIntegral`.
+    pub fn integral(&self) -> CGRect {
+        let rect = self.standardize();
+        let (min_x, min_y) = (rect.origin.x.floor(), rect.origin.y.floor());
+        let max_x = (rect.origin.x + rect.size.width).ceil();
+        let max_y = (rect.origin.y + rect.size.height).ceil();
+        CGRect::from_xywh(min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+    /// Converts between a top-left origin, as CoreGraphics and ScreenCaptureKit use, and a
+    /// bottom-left origin, as Cocoa uses, in a space that is `container_height` high. Flipping
+    /// twice returns the original rectangle.
+    pub fn flip_y(&self, container_height: CGFloat) -> CGRect {
+        let rect = self.standardize();
+        CGRect::from_xywh(
+            rect.origin.x,
+            container_height - rect.origin.y - rect.size.height,
+            rect.size.width,
+            rect.size.height,
+        )
+    }
+}
+
+fn standardize(origin: CGFloat, length: CGFloat) -> (CGFloat, CGFloat) {
+    if length < 0.0 {
+        (origin + length, -length)
+    } else {
+        (origin, length)
+    }
+}
+
+/// `(x, y, width, height)`.
+impl From<(CGFloat, CGFloat, CGFloat, CGFloat)> for CGRect {
+    fn from((x, y, width, height): (CGFloat, CGFloat, CGFloat, CGFloat)) -> Self {
+        CGRect::from_xywh(x, y, width, height)
+    }
+}
+
+/// `(x, y, width, height)`.
+impl From<CGRect> for (CGFloat, CGFloat, CGFloat, CGFloat) {
+    fn from(rect: CGRect) -> Self {
+        (
+            rect.origin.x,
+            rect.origin.y,
+            rect.size.width,
+            rect.size.height,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edges() {
+        let rect = CGRect::from_xywh(10.0, 20.0, 30.0, 40.0);
+        assert_eq!(
+            (rect.get_min_x(), rect.get_mid_x(), rect.get_max_x()),
+            (10.0, 25.0, 40.0)
+        );
+        assert_eq!(
+            (rect.get_min_y(), rect.get_mid_y(), rect.get_max_y()),
+            (20.0, 40.0, 60.0)
+        );
+        let negative = CGRect::from_xywh(40.0, 60.0, -30.0, -40.0);
+        assert_eq!(negative.standardize(), rect);
+        assert_eq!(negative.get_min_x(), 10.0);
+        assert_eq!(negative.get_center(), rect.get_center());
+        assert!(CGRect::from_xywh(5.0, 5.0, 0.0, 10.0).is_empty());
+        assert!(CGSize::new(10.0, 0.0).is_empty());
+        assert!(!CGSize::new(-1.0, 10.0).is_empty());
+        assert!(!CGRect::from_xywh(5.0, 5.0, -1.0, 10.0).is_empty());
+    }
+
+    #[test]
+    fn test_containment_and_intersection() {
+        let a = CGRect::from_xywh(0.0, 0.0, 100.0, 100.0);
+        let b = CGRect::from_xywh(50.0, 50.0, 100.0, 100.0);
+        assert!(a.contains_point(&CGPoint::new(0.0, 0.0)));
+        assert!(!a.contains_point(&CGPoint::new(100.0, 50.0)));
+        assert!(a.contains_rect(&CGRect::from_xywh(10.0, 10.0, 90.0, 90.0)));
+        assert!(!a.contains_rect(&b));
+        assert!(a.intersects(&b));
+        assert_eq!(
+            a.intersection(&b),
+            Some(CGRect::from_xywh(50.0, 50.0, 50.0, 50.0))
+        );
+        assert_eq!(a.union(&b), CGRect::from_xywh(0.0, 0.0, 150.0, 150.0));
+        let empty = CGRect::from_xywh(500.0, 500.0, 0.0, 0.0);
+        assert_eq!(a.union(&empty), CGRect::from_xywh(0.0, 0.0, 500.0, 500.0));
+
+        // Touching edges intersect in an empty rectangle, disjoint ones not at all.
+        let touching = CGRect::from_xywh(100.0, 0.0, 10.0, 10.0);
+        assert!(a.intersection(&touching).unwrap().is_empty());
+        assert!(!a.intersects(&touching));
+        assert_eq!(a.intersection(&touching.offset(1.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_transforms() {
+        let rect = CGRect::from_xywh(10.0, 20.0, 30.0, 40.0);
+        assert_eq!(
+            rect.inset(5.0, 10.0),
+            Some(CGRect::from_xywh(15.0, 30.0, 20.0, 20.0))
+        );
+        assert_eq!(
+            rect.inset(-5.0, 0.0),
+            Some(CGRect::from_xywh(5.0, 20.0, 40.0, 40.0))
+        );
+        assert_eq!(rect.inset(20.0, 0.0), None);
+        assert_eq!(rect.offset(-10.0, 5.0).origin, CGPoint::new(0.0, 25.0));
+        assert_eq!(
+            rect.scale(2.0, 2.0),
+            CGRect::from_xywh(20.0, 40.0, 60.0, 80.0)
+        );
+        assert_eq!(
+            CGRect::from_xywh(0.5, 1.2, 10.0, 10.6).integral(),
+            CGRect::from_xywh(0.0, 1.0, 11.0, 11.0)
+        );
+
+        // A window 25 points below the top of a 1080 high display.
+        let top_left = CGRect::from_xywh(0.0, 25.0, 1280.0, 800.0);
+        let bottom_left = top_left.flip_y(1080.0);
+        assert_eq!(bottom_left, CGRect::from_xywh(0.0, 255.0, 1280.0, 800.0));
+        assert_eq!(bottom_left.flip_y(1080.0), top_left);
+        assert_eq!(
+            CGPoint::new(0.0, 0.0).distance_to(&CGPoint::new(3.0, 4.0)),
+            5.0
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        let rect = CGRect::from((1.0, 2.0, 3.0, 4.0));
+        assert_eq!(rect.origin, CGPoint::from((1.0, 2.0)));
+        assert_eq!(rect.size, CGSize::from((3.0, 4.0)));
+        assert_eq!(<(f64, f64, f64, f64)>::from(rect), (1.0, 2.0, 3.0, 4.0));
+        assert_eq!(<(f64, f64)>::from(rect.size), (3.0, 4.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let rect = CGRect::from_xywh(1.0, 2.0, 3.0, 4.0);
+        let json = r#"{"origin":{"x":1.0,"y":2.0},"size":{"width":3.0,"height":4.0}}"#;
+        assert_eq!(serde_json::to_string(&rect).unwrap(), json);
+        assert_eq!(serde_json::from_str::<CGRect>(json).unwrap(), rect);
+    }
 }
@@ -2,10 +2,14 @@
 #![allow(non_upper_case_globals)]
 #![allow(clippy::upper_case_acronyms)]
 
+#[cfg(target_os = "macos")]
 use objc::{runtime::Class, *};
+#[cfg(target_os = "macos")]
 use objc_foundation::INSObject;
+#[cfg(target_os = "macos")]
 use objc_id::Id;
 
+#[cfg(target_os = "macos")]
 use super::base::CGFloat;
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -13,7 +17,9 @@ use super::base::CGFloat;
 pub struct CGColor {
     _unused: [u8; 0],
 }
+#[cfg(target_os = "macos")]
 unsafe impl Message for CGColor {}
+#[cfg(target_os = "macos")]
 impl INSObject for CGColor {
     fn class() -> &'static Class {
         Class::get("CGColor")
@@ -21,17 +27,19 @@ impl INSObject for CGColor {
     }
 }
 
+#[cfg(target_os = "macos")]
 impl CGColor {
     pub fn rgb(red: CGFloat, green: CGFloat, blue: CGFloat, alpha: CGFloat) -> Id<Self> {
         unsafe {
             let ptr = CGColorCreateGenericRGB(red, green, blue, alpha);
-            Id::from_ptr(ptr)
+            Id::from_retained_ptr(ptr)
         }
     }
 }
 
 pub type CGColorRef = *mut CGColor;
 
+#[cfg(target_os = "macos")]
 extern "C" {
     pub fn CGColorCreateGenericRGB(
         red: CGFloat,
@@ -1,9 +1,15 @@
+#[cfg(target_os = "macos")]
 use std::mem;
 
+#[cfg(target_os = "macos")]
 use objc::{Message, *};
+#[cfg(target_os = "macos")]
 use objc_foundation::{INSString, INSValue, NSString, NSValue};
+#[cfg(target_os = "macos")]
 use objc_id::Id;
+#[cfg(target_os = "macos")]
 use runtime::Object;
+#[cfg(target_os = "macos")]
 #[derive(Debug)]
 #[repr(C)]
 pub struct SCStreamFrameInfo {
@@ -11,7 +17,7 @@ pub struct SCStreamFrameInfo {
 }
 
 // TODO: Documnent using comment docs matching apple
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(i32)]
 pub enum SCFrameStatus {
     // A status that indicates the system successfully generated a new frame.
@@ -28,7 +34,25 @@ pub enum SCFrameStatus {
     Stopped,
 }
 
+impl TryFrom<i32> for SCFrameStatus {
+    type Error = i32;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SCFrameStatus::Complete),
+            1 => Ok(SCFrameStatus::Idle),
+            2 => Ok(SCFrameStatus::Blank),
+            3 => Ok(SCFrameStatus::Suspended),
+            4 => Ok(SCFrameStatus::Started),
+            5 => Ok(SCFrameStatus::Stopped),
+            _ => Err(value),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
 unsafe impl Message for SCStreamFrameInfo {}
+#[cfg(target_os = "macos")]
 impl SCStreamFrameInfo {
     pub fn status(&self) -> SCFrameStatus {
         unsafe {
@@ -86,6 +86,30 @@ impl UnsafeSCStream {
         }
     }
 
+    pub fn update_configuration(
+        &self,
+        config: Id<UnsafeStreamConfigurationRef>,
+    ) -> Result<(), String> {
+        unsafe {
+            let (handler, rx) = Self::new_completion_handler();
+            let _: () = msg_send!(self, updateConfiguration: &*config completionHandler: handler);
+            match rx.recv_timeout(std::time::Duration::from_secs(5)) {
+                Ok(result) => result,
+                Err(_) => Err("Configuration update timed out".to_string()),
+            }
+        }
+    }
+    pub fn update_content_filter(&self, filter: Id<UnsafeContentFilter>) -> Result<(), String> {
+        unsafe {
+            let (handler, rx) = Self::new_completion_handler();
+            let _: () = msg_send!(self, updateContentFilter: &*filter completionHandler: handler);
+            match rx.recv_timeout(std::time::Duration::from_secs(5)) {
+                Ok(result) => result,
+                Err(_) => Err("Content filter update timed out".to_string()),
+            }
+        }
+    }
+
     pub fn add_stream_output(&self, handle: impl UnsafeSCStreamOutput, output_type: u8) {
         unsafe {
             let queue = dispatch_get_global_queue(DISPATCH_QUEUE_PRIORITY_DEFAULT, 0);
@@ -162,7 +186,7 @@ mod stream_test {
             ..Default::default()
         };
         let (tx, rx) = sync_channel(1);
-        let stream = UnsafeSCStream::init(filter, config.into(), ErrorHandler {});
+        let stream = UnsafeSCStream::init(filter, config.try_into().unwrap(), ErrorHandler {});
         let a = OutputHandler { tx };
 
         println!("ADDING OUTPUT");
@@ -188,7 +212,7 @@ mod stream_test {
             height: 100,
             ..Default::default()
         };
-        let stream = UnsafeSCStream::init(filter, config.into(), ErrorHandler {});
+        let stream = UnsafeSCStream::init(filter, config.try_into().unwrap(), ErrorHandler {});
 
         println!("start capture");
         assert!(stream.start_capture().is_ok());
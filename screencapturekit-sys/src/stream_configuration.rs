@@ -1,16 +1,27 @@
+#[cfg(target_os = "macos")]
 use objc::{msg_send, runtime::Class, *};
 
+#[cfg(target_os = "macos")]
 use objc_foundation::INSObject;
+#[cfg(target_os = "macos")]
 use objc_id::Id;
+#[cfg(target_os = "macos")]
 use runtime::Object;
 
+#[cfg(target_os = "macos")]
+use objc::MessageError;
+#[cfg(target_os = "macos")]
+use objc_foundation::{INSString, NSString};
+
+#[cfg(target_os = "macos")]
+use crate::os_types::graphics::CGColor;
 use crate::os_types::{
-    base::{CMTime, OSType, UInt32, BOOL},
+    base::{CGFloat, CMTime, NSInteger, OSType, SizeT, UInt32, BOOL},
     four_char_code::FourCharCode,
     geometry::CGRect,
-    graphics::CGColor,
 };
 // Implement Encode for CGRect
+#[cfg(target_os = "macos")]
 unsafe impl Encode for CGRect {
     fn encode() -> Encoding {
         unsafe { Encoding::from_str("{CGRect={CGPoint=dd}{CGSize=dd}}") }
@@ -18,6 +29,7 @@ unsafe impl Encode for CGRect {
 }
 
 // Implement Encode for OSType (assuming it's a typedef for u32)
+#[cfg(target_os = "macos")]
 unsafe impl Encode for OSType {
     fn encode() -> Encoding {
         u32::encode()
@@ -25,43 +37,226 @@ unsafe impl Encode for OSType {
 }
 
 // Implement Encode for CMTime
+#[cfg(target_os = "macos")]
 unsafe impl Encode for CMTime {
     fn encode() -> Encoding {
         unsafe { Encoding::from_str("{CMTime=qiIq}") }
     }
 }
 
+#[cfg(target_os = "macos")]
 #[derive(Debug)]
 pub struct UnsafeStreamConfigurationRef;
+#[cfg(target_os = "macos")]
 unsafe impl Message for UnsafeStreamConfigurationRef {}
-impl From<UnsafeStreamConfiguration> for Id<UnsafeStreamConfigurationRef> {
-    fn from(value: UnsafeStreamConfiguration) -> Self {
+#[cfg(target_os = "macos")]
+impl TryFrom<UnsafeStreamConfiguration> for Id<UnsafeStreamConfigurationRef> {
+    type Error = String;
+
+    fn try_from(value: UnsafeStreamConfiguration) -> Result<Self, Self::Error> {
         objc::rc::autoreleasepool(|| unsafe {
-            let unsafe_config: UnsafeStreamConfiguration = value;
             let cls = UnsafeStreamConfigurationRef::class();
             let obj: *mut UnsafeStreamConfigurationRef = msg_send![cls, alloc];
             let obj: *mut UnsafeStreamConfigurationRef = msg_send![obj, init];
             let obj = Id::from_ptr(obj);
 
-            let _: () = msg_send![&*obj, setWidth:unsafe_config.width];
-            let _: () = msg_send![&*obj, setHeight:unsafe_config.height];
-            let _: () = msg_send![&*obj, setCapturesAudio:unsafe_config.captures_audio];
-            let _: () = msg_send![&*obj, setSourceRect:unsafe_config.source_rect];
-            let _: () = msg_send![&*obj, setDestinationRect:unsafe_config.destination_rect];
-            let _: () = msg_send![&*obj, setPixelFormat:unsafe_config.pixel_format];
-            let _: () =
-                msg_send![&*obj, setMinimumFrameInterval:unsafe_config.minimum_frame_interval];
-            let _: () = msg_send![&*obj, setScalesToFit:unsafe_config.scales_to_fit];
-            let _: () = msg_send![&*obj, setShowsCursor:unsafe_config.shows_cursor];
-            let _: () = msg_send![&*obj, setChannelCount:unsafe_config.channel_count];
-            let _: () = msg_send![&*obj, setSampleRate:unsafe_config.sample_rate];
-            let _: () =
-                msg_send![&*obj, setPreservesAspectRatio:unsafe_config.preserves_aspect_ratio];
-
-            obj
+            value.apply(&mut NativeProperties(&obj))?;
+
+            Ok(obj)
         })
     }
 }
+
+/// A value assigned to a property of a native `SCStreamConfiguration`, in the representation
+/// that is sent to it.
+#[derive(Debug, Clone)]
+pub enum PropertyValue {
+    /// A `size_t`.
+    SizeT(SizeT),
+    NSInteger(NSInteger),
+    Bool(BOOL),
+    OSType(OSType),
+    Rect(CGRect),
+    Time(CMTime),
+    /// Sent as an `NSString`.
+    String(String),
+    /// Red, green, blue and alpha components, sent as a generic RGB `CGColorRef`.
+    Color([CGFloat; 4]),
+}
+
+impl PropertyValue {
+    /// The Objective-C type encoding of the argument the setter receives, on 64-bit Apple
+    /// targets.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            PropertyValue::SizeT(_) => "Q",
+            PropertyValue::NSInteger(_) => "q",
+            PropertyValue::OSType(_) => "I",
+            // `BOOL` is a `bool` on Apple silicon and a `signed char` on Intel.
+            PropertyValue::Bool(_) if cfg!(target_arch = "aarch64") => "B",
+            PropertyValue::Bool(_) => "c",
+            PropertyValue::Rect(_) => "{CGRect={CGPoint=dd}{CGSize=dd}}",
+            PropertyValue::Time(_) => "{CMTime=qiIq}",
+            PropertyValue::String(_) => "@",
+            PropertyValue::Color(_) => "^{CGColor=}",
+        }
+    }
+}
+
+/// Receives the property assignments of [`UnsafeStreamConfiguration::apply`].
+pub trait PropertySink {
+    fn set_property(&mut self, selector: &'static str, value: PropertyValue) -> Result<(), String>;
+}
+
+/// A [`PropertySink`] that keeps every assignment in order, to inspect a configuration without
+/// ScreenCaptureKit.
+#[derive(Debug, Default)]
+pub struct PropertyRecorder {
+    assignments: Vec<(&'static str, PropertyValue)>,
+}
+
+impl PropertyRecorder {
+    pub fn get_assignments(&self) -> &[(&'static str, PropertyValue)] {
+        &self.assignments
+    }
+    /// The last value assigned with `selector`.
+    pub fn get(&self, selector: &str) -> Option<&PropertyValue> {
+        self.assignments
+            .iter()
+            .rev()
+            .find(|(s, _)| *s == selector)
+            .map(|(_, value)| value)
+    }
+}
+
+impl PropertySink for PropertyRecorder {
+    fn set_property(&mut self, selector: &'static str, value: PropertyValue) -> Result<(), String> {
+        self.assignments.push((selector, value));
+        Ok(())
+    }
+}
+
+impl UnsafeStreamConfiguration {
+    /// Assigns every field to `sink` with the setter selector of `SCStreamConfiguration`, and
+    /// stops at the first assignment the sink rejects.
+    ///
+    /// An empty `color_matrix` or `color_space_name`, and a zero `queue_depth`, `sample_rate` or
+    /// `channel_count`, are not assigned, which leaves the ScreenCaptureKit default in place.
+    pub fn apply(&self, sink: &mut impl PropertySink) -> Result<(), String> {
+        sink.set_property("setWidth:", PropertyValue::SizeT(self.width.into()))?;
+        sink.set_property("setHeight:", PropertyValue::SizeT(self.height.into()))?;
+        sink.set_property("setScalesToFit:", PropertyValue::Bool(self.scales_to_fit))?;
+        sink.set_property("setSourceRect:", PropertyValue::Rect(self.source_rect))?;
+        sink.set_property(
+            "setDestinationRect:",
+            PropertyValue::Rect(self.destination_rect),
+        )?;
+        sink.set_property(
+            "setPreservesAspectRatio:",
+            PropertyValue::Bool(self.preserves_aspect_ratio),
+        )?;
+        sink.set_property("setPixelFormat:", PropertyValue::OSType(self.pixel_format))?;
+        if !self.color_matrix.is_empty() {
+            sink.set_property(
+                "setColorMatrix:",
+                PropertyValue::String(self.color_matrix.clone()),
+            )?;
+        }
+        if !self.color_space_name.is_empty() {
+            sink.set_property(
+                "setColorSpaceName:",
+                PropertyValue::String(self.color_space_name.clone()),
+            )?;
+        }
+        sink.set_property(
+            "setBackgroundColor:",
+            PropertyValue::Color(self.background_color),
+        )?;
+        sink.set_property("setShowsCursor:", PropertyValue::Bool(self.shows_cursor))?;
+        for (selector, value) in [
+            ("setQueueDepth:", self.queue_depth),
+            ("setSampleRate:", self.sample_rate),
+            ("setChannelCount:", self.channel_count),
+        ] {
+            if value != 0 {
+                sink.set_property(selector, PropertyValue::NSInteger(value.into()))?;
+            }
+        }
+        sink.set_property(
+            "setMinimumFrameInterval:",
+            PropertyValue::Time(self.minimum_frame_interval),
+        )?;
+        sink.set_property(
+            "setCapturesAudio:",
+            PropertyValue::Bool(self.captures_audio),
+        )?;
+        sink.set_property(
+            "setExcludesCurrentProcessAudio:",
+            PropertyValue::Bool(self.excludes_current_process_audio),
+        )
+    }
+}
+
+// Sends each assignment to the native object.
+#[cfg(target_os = "macos")]
+struct NativeProperties<'a>(&'a UnsafeStreamConfigurationRef);
+
+#[cfg(target_os = "macos")]
+const OBJC_ASSOCIATION_RETAIN_NONATOMIC: usize = 1;
+
+// The address is the key of the background color associated with a configuration.
+#[cfg(target_os = "macos")]
+static BACKGROUND_COLOR_KEY: u8 = 0;
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn objc_setAssociatedObject(
+        object: *mut Object,
+        key: *const std::ffi::c_void,
+        value: *mut Object,
+        policy: usize,
+    );
+}
+
+#[cfg(target_os = "macos")]
+impl PropertySink for NativeProperties<'_> {
+    fn set_property(&mut self, selector: &'static str, value: PropertyValue) -> Result<(), String> {
+        let sel = runtime::Sel::register(selector);
+        let obj = self.0;
+        let result: Result<(), MessageError> = unsafe {
+            match value {
+                PropertyValue::SizeT(v) => obj.send_message(sel, (v,)),
+                PropertyValue::NSInteger(v) => obj.send_message(sel, (v,)),
+                PropertyValue::OSType(v) => obj.send_message(sel, (v,)),
+                PropertyValue::Bool(v) => obj.send_message(sel, (v,)),
+                PropertyValue::Rect(v) => obj.send_message(sel, (v,)),
+                PropertyValue::Time(v) => obj.send_message(sel, (v,)),
+                PropertyValue::String(v) => {
+                    let string = NSString::from_str(&v);
+                    obj.send_message(sel, (&*string as *const NSString as *mut Object,))
+                }
+                PropertyValue::Color([red, green, blue, alpha]) => {
+                    let color = CGColor::rgb(red, green, blue, alpha);
+                    let color = &*color as *const CGColor as *mut Object;
+                    // `backgroundColor` is an `assign` property, so the configuration does not
+                    // retain the color and it would be released when `color` is dropped.
+                    // Associating it with the configuration retains it until the configuration
+                    // is freed, or releases the previous color when it is set again.
+                    objc_setAssociatedObject(
+                        obj as *const UnsafeStreamConfigurationRef as *mut Object,
+                        &BACKGROUND_COLOR_KEY as *const u8 as *const std::ffi::c_void,
+                        color,
+                        OBJC_ASSOCIATION_RETAIN_NONATOMIC,
+                    );
+                    obj.send_message(sel, (color,))
+                }
+            }
+        };
+        result.map_err(|e| format!("{selector} failed: {e:?}"))
+    }
+}
+
+#[cfg(target_os = "macos")]
 impl INSObject for UnsafeStreamConfigurationRef {
     fn class() -> &'static Class {
         Class::get("SCStreamConfiguration")
@@ -69,6 +264,7 @@ impl INSObject for UnsafeStreamConfigurationRef {
     }
 }
 
+#[cfg(target_os = "macos")]
 impl Drop for UnsafeStreamConfigurationRef {
     fn drop(&mut self) {
         unsafe {
@@ -99,9 +295,9 @@ pub struct UnsafeStreamConfiguration {
     pub color_matrix: String,
     // A color space to use for the output buffer.
     pub color_space_name: String,
-    // A background color for the output.
+    // A background color for the output, as red, green, blue and alpha components.
     // Controlling Visibility
-    pub background_color: CGColor,
+    pub background_color: [CGFloat; 4],
 
     // A boolean value that determines whether the cursor is visible in the stream.
     pub shows_cursor: BOOL,
@@ -133,7 +329,7 @@ impl Default for UnsafeStreamConfiguration {
             pixel_format: FourCharCode::from_chars(*b"BGRA"),
             color_matrix: Default::default(),
             color_space_name: Default::default(),
-            background_color: Default::default(),
+            background_color: [0.0, 0.0, 0.0, 1.0],
             shows_cursor: Default::default(),
             queue_depth: Default::default(),
             minimum_frame_interval: Default::default(),
@@ -145,12 +341,117 @@ impl Default for UnsafeStreamConfiguration {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, target_os = "macos"))]
 mod get_shareable_content {
 
     use super::*;
     #[test]
     fn test_from() {
-        let _: Id<UnsafeStreamConfigurationRef> = UnsafeStreamConfiguration::default().into();
+        let _: Id<UnsafeStreamConfigurationRef> =
+            UnsafeStreamConfiguration::default().try_into().unwrap();
+    }
+
+    // The runtime may leave structs unnamed, like `{?=qiIq}`, which has the same layout.
+    fn without_struct_names(encoding: &str) -> String {
+        let mut result = String::new();
+        let mut in_name = false;
+        for c in encoding.chars() {
+            match c {
+                '{' => in_name = true,
+                '=' => in_name = false,
+                _ if in_name => continue,
+                _ => {}
+            }
+            result.push(c);
+        }
+        result
+    }
+
+    #[test]
+    fn test_encodings_match_the_native_setters() {
+        let config = UnsafeStreamConfiguration {
+            queue_depth: 5,
+            sample_rate: 48000,
+            channel_count: 2,
+            color_matrix: "ITU_R_709_2".to_owned(),
+            color_space_name: "kCGColorSpaceSRGB".to_owned(),
+            ..Default::default()
+        };
+        let mut recorder = PropertyRecorder::default();
+        config.apply(&mut recorder).unwrap();
+        let cls = UnsafeStreamConfigurationRef::class();
+        for (selector, value) in recorder.get_assignments() {
+            let method = cls
+                .instance_method(runtime::Sel::register(selector))
+                .unwrap_or_else(|| panic!("{selector} is not a setter"));
+            let native = method.argument_type(2).unwrap();
+            assert_eq!(
+                without_struct_names(value.encoding()),
+                without_struct_names(native.as_str()),
+                "{selector}"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod apply {
+    use super::*;
+
+    struct RejectingSink;
+
+    impl PropertySink for RejectingSink {
+        fn set_property(&mut self, selector: &'static str, _: PropertyValue) -> Result<(), String> {
+            Err(format!("{selector} rejected"))
+        }
+    }
+
+    #[test]
+    fn test_apply_stops_at_the_first_error() {
+        assert_eq!(
+            UnsafeStreamConfiguration::default().apply(&mut RejectingSink),
+            Err("setWidth: rejected".to_string())
+        );
+    }
+
+    #[test]
+    fn test_apply_records_every_field() {
+        let config = UnsafeStreamConfiguration {
+            queue_depth: 5,
+            color_matrix: "ITU_R_709_2".to_owned(),
+            color_space_name: "kCGColorSpaceSRGB".to_owned(),
+            excludes_current_process_audio: 1,
+            ..Default::default()
+        };
+        let mut recorder = PropertyRecorder::default();
+        config.apply(&mut recorder).unwrap();
+        let selectors: Vec<_> = recorder.get_assignments().iter().map(|(s, _)| *s).collect();
+        assert_eq!(selectors.len(), 15);
+        assert!(matches!(
+            recorder.get("setQueueDepth:"),
+            Some(PropertyValue::NSInteger(5))
+        ));
+        assert!(matches!(
+            recorder.get("setWidth:"),
+            Some(PropertyValue::SizeT(0))
+        ));
+        assert!(matches!(
+            recorder.get("setColorMatrix:"),
+            Some(PropertyValue::String(s)) if s == "ITU_R_709_2"
+        ));
+        assert_eq!(
+            recorder.get("setBackgroundColor:").unwrap().encoding(),
+            "^{CGColor=}"
+        );
+
+        let mut recorder = PropertyRecorder::default();
+        UnsafeStreamConfiguration::default()
+            .apply(&mut recorder)
+            .unwrap();
+        assert!(recorder.get("setColorMatrix:").is_none());
+        assert!(recorder.get("setColorSpaceName:").is_none());
+        assert!(recorder.get("setQueueDepth:").is_none());
+        assert!(recorder.get("setSampleRate:").is_none());
+        assert!(recorder.get("setChannelCount:").is_none());
     }
 }
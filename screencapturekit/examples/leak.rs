@@ -7,23 +7,11 @@ use screencapturekit::{
     sc_output_handler::{SCStreamOutputType, StreamOutput},
     sc_shareable_content::SCShareableContent,
     sc_stream::SCStream,
-    sc_stream_configuration::{PixelFormat, SCStreamConfiguration},
-    sc_types::base::CMTime,
-};
-use screencapturekit_sys::{
-    content_filter::{UnsafeContentFilter, UnsafeInitParams},
-    shareable_content::UnsafeSCShareableContent,
+    sc_stream_configuration::SCStreamConfiguration,
 };
 
 pub struct Capturer {}
 
-impl Capturer {
-    pub fn new() -> Self {
-        println!("Capturer initialized");
-        Capturer {}
-    }
-}
-
 impl StreamErrorHandler for Capturer {
     fn on_error(&self) {
         eprintln!("ERROR!");
@@ -48,10 +36,12 @@ fn main() {
         let _filter1 = SCContentFilter::new(InitParams::DisplayExcludingWindows(
             display.clone(),
             windows,
-        ));
-        let _filter2 = SCContentFilter::new(InitParams::Display(display.clone()));
+        ))
+        .unwrap();
+        let _filter2 = SCContentFilter::new(InitParams::Display(display.clone())).unwrap();
         let _filter3 =
-            SCContentFilter::new(InitParams::DisplayExcludingWindows(display.clone(), vec![]));
+            SCContentFilter::new(InitParams::DisplayExcludingWindows(display.clone(), vec![]))
+                .unwrap();
 
         // Create multiple configurations
         let _config1 = SCStreamConfiguration {
@@ -67,13 +57,11 @@ fn main() {
 
         // Create and immediately drop streams
         let init_params = InitParams::Display(display);
-        let filter = SCContentFilter::new(init_params);
-        let mut sc_stream = SCStream::new(filter, _config1, Capturer {});
+        let filter = SCContentFilter::new(init_params).unwrap();
+        let mut sc_stream = SCStream::new(filter, _config1, Capturer {}).unwrap();
         let output = Capturer {};
         sc_stream.add_output(output, SCStreamOutputType::Screen);
-
-        // Force drop of sc_stream
-        drop(sc_stream);
+        // sc_stream is dropped at the end of each iteration
     }
 
     // Get the current process ID
@@ -0,0 +1,48 @@
+use std::{error::Error, thread::sleep, time::Duration};
+
+use screencapturekit::{
+    sc_content_filter::{InitParams, SCContentFilter},
+    sc_error_handler::StreamErrorHandler,
+    sc_output_handler::SCStreamOutputType,
+    sc_shareable_content::SCShareableContent,
+    sc_stream::SCStream,
+    sc_stream_configuration::SCStreamConfiguration,
+    wav_writer::WavWriter,
+};
+
+struct ErrorHandler;
+
+impl StreamErrorHandler for ErrorHandler {
+    fn on_error(&self) {
+        eprintln!("ERROR!");
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let display = SCShareableContent::try_current()?
+        .displays
+        .pop()
+        .ok_or("No display to capture")?;
+    let config = SCStreamConfiguration {
+        width: display.width,
+        height: display.height,
+        captures_audio: true,
+        sample_rate: 48000,
+        channel_count: 2,
+        excludes_current_process_audio: true,
+        ..Default::default()
+    };
+
+    let wav = WavWriter::create("capture.wav")?;
+    let filter = SCContentFilter::new(InitParams::Display(display))?;
+    let mut stream = SCStream::new(filter, config, ErrorHandler)?;
+    stream.add_output(wav.clone(), SCStreamOutputType::Audio);
+    stream.start_capture()?;
+
+    sleep(Duration::from_secs(5));
+
+    stream.stop_capture()?;
+    wav.finish()?;
+    println!("Wrote {} frames to capture.wav", wav.get_frame_count());
+    Ok(())
+}
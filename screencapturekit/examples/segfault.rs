@@ -1,4 +1,4 @@
-use std::{path::PathBuf, thread, time};
+use std::{thread, time};
 
 use screencapturekit::{
     cm_sample_buffer::CMSampleBuffer,
@@ -9,7 +9,6 @@ use screencapturekit::{
     sc_stream::SCStream,
     sc_stream_configuration::SCStreamConfiguration,
 };
-use screencapturekit_sys::os_types::geometry::{CGPoint, CGRect, CGSize};
 
 struct ErrorHandler;
 impl StreamErrorHandler for ErrorHandler {
@@ -27,6 +26,12 @@ impl Capturer {
     }
 }
 
+impl Default for Capturer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StreamErrorHandler for Capturer {
     fn on_error(&self) {
         eprintln!("ERROR!");
@@ -34,7 +39,7 @@ impl StreamErrorHandler for Capturer {
 }
 
 impl StreamOutput for Capturer {
-    fn did_output_sample_buffer(&self, sample: CMSampleBuffer, of_type: SCStreamOutputType) {
+    fn did_output_sample_buffer(&self, _sample: CMSampleBuffer, _of_type: SCStreamOutputType) {
         println!("New frame recvd");
     }
 }
@@ -53,7 +58,7 @@ fn main() {
     let height = display.height;
 
     let params = InitParams::Display(display);
-    let filter = SCContentFilter::new(params);
+    let filter = SCContentFilter::new(params).unwrap();
 
     let stream_config = SCStreamConfiguration {
         width,
@@ -61,17 +66,21 @@ fn main() {
         ..Default::default()
     };
 
-    let mut stream = SCStream::new(filter, stream_config, ErrorHandler);
+    let mut stream = SCStream::new(filter, stream_config, ErrorHandler).unwrap();
     let capturer = Capturer::new();
     stream.add_output(capturer, SCStreamOutputType::Screen);
 
-    stream.start_capture();
+    if let Err(error) = stream.start_capture() {
+        eprintln!("Could not start the capture: {error}");
+    }
 
     let ten_millis = time::Duration::from_millis(10000);
 
     thread::sleep(ten_millis);
 
-    stream.stop_capture();
+    if let Err(error) = stream.stop_capture() {
+        eprintln!("Could not stop the capture: {error}");
+    }
 
     println!("Ended");
 }
@@ -0,0 +1,549 @@
+//! Sample format, layout and channel conversion of captured audio.
+//!
+//! ScreenCaptureKit delivers non-interleaved 32-bit float audio. An [`AudioConverter`] turns an
+//! [`AudioFrame`] into any combination of sample format, interleaving and channel count, mixing
+//! the channels through a [`MixMatrix`].
+
+use crate::audio_frame::{AudioFrame, Sample, SampleFormat, I24};
+
+/// The gain of the center and surround channels when folding 5.1 down to stereo.
+const MINUS_3_DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// The gains that mix input channels into output channels.
+///
+/// Every output sample is the sum of the input samples of the same frame, each multiplied by
+/// the gain from its channel to the output channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixMatrix {
+    input_channels: usize,
+    output_channels: usize,
+    /// One row of `input_channels` gains for each output channel.
+    gains: Vec<f32>,
+}
+
+impl MixMatrix {
+    /// Creates a matrix from one row of `input_channels` gains for each output channel.
+    pub fn new(
+        input_channels: usize,
+        output_channels: usize,
+        gains: Vec<f32>,
+    ) -> Result<Self, String> {
+        if input_channels == 0 || output_channels == 0 {
+            return Err("A mix matrix needs at least one input and one output channel".into());
+        }
+        if gains.len() != input_channels * output_channels {
+            return Err(format!(
+                "Mixing {input_channels} into {output_channels} channels takes {} gains, got {}",
+                input_channels * output_channels,
+                gains.len()
+            ));
+        }
+        if let Some(gain) = gains.iter().find(|gain| !gain.is_finite()) {
+            return Err(format!("Invalid gain {gain}"));
+        }
+        Ok(Self {
+            input_channels,
+            output_channels,
+            gains,
+        })
+    }
+
+    /// Passes every channel through unchanged.
+    pub fn identity(channels: usize) -> Self {
+        let mut matrix = Self::silent(channels, channels);
+        (0..channels).for_each(|channel| matrix.set_gain(channel, channel, 1.0));
+        matrix
+    }
+
+    /// Returns the usual mix from `input_channels` to `output_channels`.
+    ///
+    /// Mono is copied to every output channel and any layout is averaged down to mono.
+    /// 5.1 in the order L, R, C, LFE, Ls, Rs folds down to stereo with the center and
+    /// surround channels at -3 dB and the LFE dropped, which can clip loud passages.
+    /// Otherwise the first channels are passed through and extra outputs stay silent.
+    pub fn for_channels(input_channels: usize, output_channels: usize) -> Self {
+        let mut matrix = Self::silent(input_channels, output_channels);
+        match (input_channels, output_channels) {
+            (1, _) => {
+                (0..output_channels).for_each(|output| matrix.set_gain(output, 0, 1.0));
+            }
+            (_, 1) => {
+                let gain = 1.0 / input_channels as f32;
+                (0..input_channels).for_each(|input| matrix.set_gain(0, input, gain));
+            }
+            (6, 2) => {
+                for (output, front, surround) in [(0, 0, 4), (1, 1, 5)] {
+                    matrix.set_gain(output, front, 1.0);
+                    matrix.set_gain(output, 2, MINUS_3_DB);
+                    matrix.set_gain(output, surround, MINUS_3_DB);
+                }
+            }
+            _ => {
+                (0..input_channels.min(output_channels))
+                    .for_each(|channel| matrix.set_gain(channel, channel, 1.0));
+            }
+        }
+        matrix
+    }
+
+    fn silent(input_channels: usize, output_channels: usize) -> Self {
+        Self {
+            input_channels,
+            output_channels,
+            gains: vec![0.0; input_channels * output_channels],
+        }
+    }
+
+    pub fn get_input_channels(&self) -> usize {
+        self.input_channels
+    }
+    pub fn get_output_channels(&self) -> usize {
+        self.output_channels
+    }
+    /// Returns the gain from `input` to `output`, or `None` if either channel is out of range.
+    pub fn get_gain(&self, output: usize, input: usize) -> Option<f32> {
+        if output >= self.output_channels || input >= self.input_channels {
+            return None;
+        }
+        Some(self.gains[output * self.input_channels + input])
+    }
+    /// Sets the gain from `input` to `output`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either channel is out of range.
+    pub fn set_gain(&mut self, output: usize, input: usize, gain: f32) {
+        assert!(
+            output < self.output_channels && input < self.input_channels,
+            "No gain from channel {input} to channel {output} in a {}x{} mix",
+            self.input_channels,
+            self.output_channels
+        );
+        self.gains[output * self.input_channels + input] = gain;
+    }
+
+    fn is_identity(&self) -> bool {
+        *self == Self::identity(self.input_channels)
+    }
+
+    /// Mixes planar channels of samples.
+    fn apply(&self, channels: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        let frame_count = channels.first().map_or(0, Vec::len);
+        self.gains
+            .chunks_exact(self.input_channels)
+            .map(|row| {
+                let mut output = vec![0.0; frame_count];
+                for (input, &gain) in channels.iter().zip(row).filter(|(_, &gain)| gain != 0.0) {
+                    let gain = f64::from(gain);
+                    output
+                        .iter_mut()
+                        .zip(input)
+                        .for_each(|(mixed, sample)| *mixed += gain * sample);
+                }
+                output
+            })
+            .collect()
+    }
+}
+
+/// The sample format and layout an [`AudioConverter`] produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub sample_format: SampleFormat,
+    pub channel_count: usize,
+    pub is_interleaved: bool,
+}
+
+impl AudioFormat {
+    pub fn new(sample_format: SampleFormat, channel_count: usize, is_interleaved: bool) -> Self {
+        Self {
+            sample_format,
+            channel_count,
+            is_interleaved,
+        }
+    }
+    /// Returns the format of `frame`.
+    pub fn of(frame: &AudioFrame) -> Self {
+        Self::new(
+            frame.get_sample_format(),
+            frame.get_channel_count(),
+            frame.is_interleaved(),
+        )
+    }
+}
+
+/// Converts audio frames to a fixed [`AudioFormat`].
+///
+/// Samples are converted through `f64`, so integer formats widen exactly and narrow with
+/// rounding. Values outside the range of an integer format are clamped.
+///
+/// ```
+/// use screencapturekit::{
+///     audio_conversion::{AudioConverter, AudioFormat},
+///     audio_frame::{AudioFrame, SampleFormat},
+/// };
+/// use screencapturekit_sys::os_types::base::CMTime;
+///
+/// let captured = AudioFrame::from_planar(
+///     48000.0,
+///     vec![vec![0.5f32, -1.0], vec![0.25, 1.0]],
+///     CMTime::default(),
+/// )?;
+/// let converter = AudioConverter::new(AudioFormat::new(SampleFormat::I16, 1, true));
+/// let mono = converter.convert(&captured)?;
+/// assert_eq!(mono.buffer::<i16>(0), Some(&[12288, 0][..]));
+/// # Ok::<(), String>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioConverter {
+    format: AudioFormat,
+    mix: Option<MixMatrix>,
+}
+
+impl AudioConverter {
+    /// Converts to `format`, mixing channels with [`MixMatrix::for_channels`].
+    pub fn new(format: AudioFormat) -> Self {
+        Self { format, mix: None }
+    }
+    /// Mixes channels with `mix` instead of the default for the channel counts.
+    pub fn with_mix_matrix(mut self, mix: MixMatrix) -> Result<Self, String> {
+        if mix.output_channels != self.format.channel_count {
+            return Err(format!(
+                "A mix into {} channels can't produce {} channels",
+                mix.output_channels, self.format.channel_count
+            ));
+        }
+        self.mix = Some(mix);
+        Ok(self)
+    }
+
+    pub fn get_format(&self) -> AudioFormat {
+        self.format
+    }
+    pub fn get_mix_matrix(&self) -> Option<&MixMatrix> {
+        self.mix.as_ref()
+    }
+
+    pub fn convert(&self, frame: &AudioFrame) -> Result<AudioFrame, String> {
+        let input_channels = frame.get_channel_count();
+        if input_channels == 0 {
+            return Err("Can't convert audio without channels".into());
+        }
+        if self.format.channel_count == 0 {
+            return Err("Can't convert audio to zero channels".into());
+        }
+        let channels: Vec<Vec<f64>> = (0..input_channels)
+            .map(|channel| {
+                (0..frame.get_frame_count())
+                    .map(|index| frame.get_sample_f64(channel, index).unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        let mixed = match &self.mix {
+            Some(mix) if mix.input_channels != input_channels => {
+                return Err(format!(
+                    "A mix of {} channels can't take {input_channels} channels",
+                    mix.input_channels
+                ))
+            }
+            Some(mix) if !mix.is_identity() => mix.apply(&channels),
+            Some(_) => channels,
+            None if input_channels == self.format.channel_count => channels,
+            None => {
+                MixMatrix::for_channels(input_channels, self.format.channel_count).apply(&channels)
+            }
+        };
+        match self.format.sample_format {
+            SampleFormat::F32 => self.build::<f32>(frame, mixed),
+            SampleFormat::I16 => self.build::<i16>(frame, mixed),
+            SampleFormat::I24 => self.build::<I24>(frame, mixed),
+            SampleFormat::I32 => self.build::<i32>(frame, mixed),
+        }
+    }
+
+    fn build<T: Sample>(
+        &self,
+        frame: &AudioFrame,
+        channels: Vec<Vec<f64>>,
+    ) -> Result<AudioFrame, String> {
+        let channels = channels
+            .into_iter()
+            .map(|channel| channel.into_iter().map(T::from_f64).collect::<Vec<_>>());
+        if self.format.is_interleaved {
+            let channels: Vec<Vec<T>> = channels.collect();
+            let samples = (0..frame.get_frame_count())
+                .flat_map(|index| channels.iter().map(move |channel| channel[index]))
+                .collect();
+            AudioFrame::from_interleaved(
+                frame.get_sample_rate(),
+                self.format.channel_count,
+                samples,
+                frame.get_presentation_timestamp(),
+            )
+        } else {
+            AudioFrame::from_planar(
+                frame.get_sample_rate(),
+                channels.collect(),
+                frame.get_presentation_timestamp(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use screencapturekit_sys::os_types::base::CMTime;
+
+    use super::*;
+
+    fn i24(value: i32) -> I24 {
+        I24::new(value).unwrap()
+    }
+
+    fn convert(frame: &AudioFrame, sample_format: SampleFormat) -> AudioFrame {
+        let format = AudioFormat::new(sample_format, frame.get_channel_count(), true);
+        AudioConverter::new(format).convert(frame).unwrap()
+    }
+
+    fn mono<T: Sample>(samples: Vec<T>) -> AudioFrame {
+        AudioFrame::from_interleaved(48000.0, 1, samples, CMTime::default()).unwrap()
+    }
+
+    #[test]
+    fn test_sample_formats() {
+        // the same values in every format: -1, -0.5, 0, 0.5, 0.25 and the largest positive
+        let f32s = mono(vec![-1.0f32, -0.5, 0.0, 0.5, 0.25, 1.0]);
+        let i16s = mono(vec![i16::MIN, -16384, 0, 16384, 8192, i16::MAX]);
+        let i24s = mono(
+            [-8388608, -4194304, 0, 4194304, 2097152, 8388607]
+                .map(i24)
+                .to_vec(),
+        );
+        let i32s = mono(vec![i32::MIN, -1 << 30, 0, 1 << 30, 1 << 29, i32::MAX]);
+        for frame in [&f32s, &i16s, &i24s, &i32s] {
+            assert_eq!(
+                convert(frame, SampleFormat::I16).buffer::<i16>(0),
+                i16s.buffer::<i16>(0)
+            );
+            assert_eq!(
+                convert(frame, SampleFormat::I24).buffer::<I24>(0).unwrap()[..5],
+                i24s.buffer::<I24>(0).unwrap()[..5]
+            );
+            assert_eq!(
+                convert(frame, SampleFormat::I32).buffer::<i32>(0).unwrap()[..5],
+                i32s.buffer::<i32>(0).unwrap()[..5]
+            );
+        }
+        // the largest value of a narrower format stays below the largest of a wider one
+        let largest = |frame, format| convert(frame, format).get_sample_f32(0, 5).unwrap();
+        assert_eq!(largest(&i16s, SampleFormat::I24), 8388352.0 / 8388608.0);
+        assert_eq!(largest(&i24s, SampleFormat::I32), 8388607.0 / 8388608.0);
+        assert_eq!(largest(&f32s, SampleFormat::I24), 8388607.0 / 8388608.0);
+        assert_eq!(largest(&i32s, SampleFormat::I24), 8388607.0 / 8388608.0);
+        assert_eq!(
+            convert(&i16s, SampleFormat::F32).buffer::<f32>(0).unwrap(),
+            [-1.0, -0.5, 0.0, 0.5, 0.25, 32767.0 / 32768.0]
+        );
+        assert_eq!(
+            convert(&i24s, SampleFormat::F32).buffer::<f32>(0).unwrap()[5],
+            8388607.0 / 8388608.0
+        );
+    }
+
+    #[test]
+    fn test_integer_precision() {
+        // widening is exact
+        let i16s = mono(vec![1i16, -1, 12345, -32767]);
+        assert_eq!(
+            convert(&i16s, SampleFormat::I24).buffer::<I24>(0).unwrap(),
+            [256, -256, 12345 * 256, -32767 * 256].map(i24)
+        );
+        assert_eq!(
+            convert(&i16s, SampleFormat::I32).buffer::<i32>(0).unwrap(),
+            [65536, -65536, 12345 << 16, -32767 << 16]
+        );
+        let i24s = mono([1, -1, 8388607, -8388607].map(i24).to_vec());
+        assert_eq!(
+            convert(&i24s, SampleFormat::I32).buffer::<i32>(0).unwrap(),
+            [256, -256, 8388607 << 8, -8388607 << 8]
+        );
+        // narrowing rounds to nearest, halfway away from zero
+        let i32s = mono(vec![32767, 32768, 98304, -32768, -32769, i32::MAX]);
+        assert_eq!(
+            convert(&i32s, SampleFormat::I16).buffer::<i16>(0).unwrap(),
+            [0, 1, 2, -1, -1, i16::MAX]
+        );
+        let i24s = mono([127, 128, -129, 8388607].map(i24).to_vec());
+        assert_eq!(
+            convert(&i24s, SampleFormat::I16).buffer::<i16>(0).unwrap(),
+            [0, 1, -1, i16::MAX]
+        );
+    }
+
+    #[test]
+    fn test_clamping() {
+        let loud = mono(vec![1.5f32, -2.0, 0.9999999, f32::INFINITY]);
+        assert_eq!(
+            convert(&loud, SampleFormat::I16).buffer::<i16>(0).unwrap(),
+            [i16::MAX, i16::MIN, i16::MAX, i16::MAX]
+        );
+        assert_eq!(
+            convert(&loud, SampleFormat::I24).buffer::<I24>(0).unwrap(),
+            [I24::MAX, I24::MIN, I24::MAX, I24::MAX]
+        );
+        assert_eq!(
+            convert(&loud, SampleFormat::I32).buffer::<i32>(0).unwrap(),
+            [i32::MAX, i32::MIN, 2147483392, i32::MAX]
+        );
+        // floats keep values outside the range
+        assert_eq!(
+            convert(&loud, SampleFormat::F32).buffer::<f32>(0).unwrap()[..2],
+            [1.5, -2.0]
+        );
+    }
+
+    #[test]
+    fn test_layouts() {
+        let planar = AudioFrame::from_planar(
+            44100.0,
+            vec![vec![0.5f32, -0.5, 0.25], vec![1.0, 0.0, -1.0]],
+            CMTime {
+                value: 7,
+                timescale: 44100,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let interleaved = AudioConverter::new(AudioFormat::new(SampleFormat::I16, 2, true))
+            .convert(&planar)
+            .unwrap();
+        assert!(interleaved.is_interleaved());
+        assert_eq!(interleaved.get_sample_rate(), 44100.0);
+        assert_eq!(interleaved.get_presentation_timestamp().value, 7);
+        assert_eq!(
+            interleaved.buffer::<i16>(0).unwrap(),
+            [16384, i16::MAX, -16384, 0, 8192, i16::MIN]
+        );
+        assert_eq!(
+            AudioFormat::of(&interleaved),
+            AudioFormat::new(SampleFormat::I16, 2, true)
+        );
+
+        let back = AudioConverter::new(AudioFormat::of(&planar))
+            .convert(&interleaved)
+            .unwrap();
+        assert!(!back.is_interleaved());
+        assert_eq!(back.buffer::<f32>(0).unwrap(), [0.5, -0.5, 0.25]);
+        assert_eq!(
+            back.buffer::<f32>(1).unwrap(),
+            [32767.0 / 32768.0, 0.0, -1.0]
+        );
+
+        let empty = AudioFrame::from_planar(48000.0, vec![Vec::<f32>::new(); 2], CMTime::default())
+            .unwrap();
+        let converted = AudioConverter::new(AudioFormat::new(SampleFormat::I24, 1, true))
+            .convert(&empty)
+            .unwrap();
+        assert_eq!(converted.get_frame_count(), 0);
+    }
+
+    #[test]
+    fn test_default_mixes() {
+        let stereo = AudioFrame::from_planar(
+            48000.0,
+            vec![vec![0.5f32, 1.0, -0.25], vec![0.25, -1.0, -0.25]],
+            CMTime::default(),
+        )
+        .unwrap();
+        let to_mono = AudioConverter::new(AudioFormat::new(SampleFormat::F32, 1, true));
+        assert_eq!(
+            to_mono.convert(&stereo).unwrap().buffer::<f32>(0).unwrap(),
+            [0.375, 0.0, -0.25]
+        );
+
+        let mono =
+            AudioFrame::from_planar(48000.0, vec![vec![0.5f32, -1.0]], CMTime::default()).unwrap();
+        let to_stereo = AudioConverter::new(AudioFormat::new(SampleFormat::I16, 2, true));
+        assert_eq!(
+            to_stereo.convert(&mono).unwrap().buffer::<i16>(0).unwrap(),
+            [16384, 16384, i16::MIN, i16::MIN]
+        );
+
+        // L, R, C, LFE, Ls, Rs
+        let surround = AudioFrame::from_interleaved(
+            48000.0,
+            6,
+            vec![0.25f32, -0.25, 0.5, 1.0, 0.125, 0.0],
+            CMTime::default(),
+        )
+        .unwrap();
+        let folded = to_stereo.convert(&surround).unwrap();
+        let expected_left = 0.25 + MINUS_3_DB * 0.5 + MINUS_3_DB * 0.125;
+        let expected_right = -0.25 + MINUS_3_DB * 0.5;
+        assert_eq!(
+            folded.buffer::<i16>(0).unwrap(),
+            [
+                (f64::from(expected_left) * 32768.0).round() as i16,
+                (f64::from(expected_right) * 32768.0).round() as i16
+            ]
+        );
+
+        let to_quad = AudioConverter::new(AudioFormat::new(SampleFormat::F32, 4, true));
+        assert_eq!(
+            to_quad.convert(&stereo).unwrap().buffer::<f32>(0).unwrap()[..4],
+            [0.5, 0.25, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_mix_matrix() {
+        let matrix = MixMatrix::for_channels(6, 2);
+        assert_eq!(matrix.get_input_channels(), 6);
+        assert_eq!(matrix.get_output_channels(), 2);
+        assert_eq!(matrix.get_gain(0, 0), Some(1.0));
+        assert_eq!(matrix.get_gain(0, 1), Some(0.0));
+        assert_eq!(matrix.get_gain(1, 2), Some(MINUS_3_DB));
+        assert_eq!(matrix.get_gain(1, 3), Some(0.0));
+        assert_eq!(matrix.get_gain(2, 0), None);
+        assert_eq!(MixMatrix::for_channels(3, 3), MixMatrix::identity(3));
+
+        assert!(MixMatrix::new(2, 1, vec![0.5]).is_err());
+        assert!(MixMatrix::new(0, 1, vec![]).is_err());
+        assert!(MixMatrix::new(1, 1, vec![f32::NAN]).is_err());
+
+        // swap the channels and put the left one at half volume
+        let swap = MixMatrix::new(2, 2, vec![0.0, 1.0, 0.5, 0.0]).unwrap();
+        let converter = AudioConverter::new(AudioFormat::new(SampleFormat::I16, 2, true))
+            .with_mix_matrix(swap.clone())
+            .unwrap();
+        assert_eq!(converter.get_mix_matrix(), Some(&swap));
+        let stereo = AudioFrame::from_interleaved(
+            48000.0,
+            2,
+            vec![1000i16, -2000, 400, 300],
+            CMTime::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            converter
+                .convert(&stereo)
+                .unwrap()
+                .buffer::<i16>(0)
+                .unwrap(),
+            [-2000, 500, 300, 200]
+        );
+
+        let mono = AudioFrame::from_interleaved(48000.0, 1, vec![0i16], CMTime::default()).unwrap();
+        assert!(converter.convert(&mono).is_err());
+        assert!(
+            AudioConverter::new(AudioFormat::new(SampleFormat::I16, 1, true))
+                .with_mix_matrix(swap)
+                .is_err()
+        );
+        assert!(
+            AudioConverter::new(AudioFormat::new(SampleFormat::I16, 0, true))
+                .convert(&mono)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,801 @@
+use screencapturekit_sys::{
+    audio_buffer::CopiedAudioBuffer,
+    cm_format_description_ref::{
+        kAudioFormatFlagIsBigEndian, kAudioFormatFlagIsFloat, kAudioFormatFlagIsNonInterleaved,
+        kAudioFormatFlagIsPacked, kAudioFormatFlagIsSignedInteger, kAudioFormatLinearPCM,
+        AudioStreamBasicDescription,
+    },
+    os_types::base::CMTime,
+};
+
+use crate::cm_sample_buffer::CMSampleBuffer;
+
+/// The type of the samples of linear PCM audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    F32,
+    I16,
+    /// 24-bit integers packed into three bytes.
+    I24,
+    I32,
+}
+
+impl SampleFormat {
+    pub const fn bytes_per_sample(self) -> usize {
+        match self {
+            SampleFormat::F32 | SampleFormat::I32 => 4,
+            SampleFormat::I24 => 3,
+            SampleFormat::I16 => 2,
+        }
+    }
+    /// Returns the format of linear PCM audio, failing for compressed audio and other sizes.
+    pub fn from_description(description: &AudioStreamBasicDescription) -> Result<Self, String> {
+        if description.format_id != kAudioFormatLinearPCM {
+            return Err(format!(
+                "Only linear PCM audio can be decoded, got {}",
+                description.get_format_name().unwrap_or("an unknown format")
+            ));
+        }
+        let is_float = description.format_flags & kAudioFormatFlagIsFloat != 0;
+        let is_signed = description.format_flags & kAudioFormatFlagIsSignedInteger != 0;
+        match (is_float, is_signed, description.bits_per_channel) {
+            (true, _, 32) => Ok(SampleFormat::F32),
+            (false, true, 16) => Ok(SampleFormat::I16),
+            (false, true, 24) => Ok(SampleFormat::I24),
+            (false, true, 32) => Ok(SampleFormat::I32),
+            (_, _, bits) => Err(format!(
+                "Unsupported {bits}-bit {} samples",
+                if is_float {
+                    "floating point"
+                } else if is_signed {
+                    "signed integer"
+                } else {
+                    "unsigned integer"
+                }
+            )),
+        }
+    }
+}
+
+/// A 24-bit signed integer sample, stored in the low bits of an `i32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct I24(i32);
+
+impl I24 {
+    pub const MIN: I24 = I24(-(1 << 23));
+    pub const MAX: I24 = I24((1 << 23) - 1);
+
+    /// Returns `None` for values outside the 24-bit range.
+    pub const fn new(value: i32) -> Option<Self> {
+        if value >= Self::MIN.0 && value <= Self::MAX.0 {
+            Some(Self(value))
+        } else {
+            None
+        }
+    }
+    pub const fn get(self) -> i32 {
+        self.0
+    }
+    pub const fn from_le_bytes(bytes: [u8; 3]) -> Self {
+        // shift the sign bit into place and back
+        Self(i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8)
+    }
+    pub const fn from_be_bytes(bytes: [u8; 3]) -> Self {
+        Self::from_le_bytes([bytes[2], bytes[1], bytes[0]])
+    }
+    pub const fn to_le_bytes(self) -> [u8; 3] {
+        let bytes = self.0.to_le_bytes();
+        [bytes[0], bytes[1], bytes[2]]
+    }
+}
+
+pub(crate) mod sealed {
+    use super::I24;
+
+    /// The decoded samples of one audio buffer.
+    #[derive(Debug, Clone)]
+    pub enum SampleBuffer {
+        F32(Vec<f32>),
+        I16(Vec<i16>),
+        I24(Vec<I24>),
+        I32(Vec<i32>),
+    }
+
+    pub trait Sealed: Sized {
+        fn slice(buffer: &SampleBuffer) -> Option<&[Self]>;
+        fn wrap(samples: Vec<Self>) -> SampleBuffer;
+        /// Converts the sample to the range -1.0 to 1.0 without losing integer precision.
+        fn to_f64(self) -> f64;
+        fn from_f64(value: f64) -> Self;
+        fn write_le(self, bytes: &mut Vec<u8>);
+    }
+}
+
+use sealed::{SampleBuffer, Sealed};
+
+/// A type that audio samples can be read as, implemented for `f32`, `i16`, [`I24`] and `i32`.
+pub trait Sample: Copy + Send + Sync + 'static + sealed::Sealed {
+    const FORMAT: SampleFormat;
+    /// Converts the sample to the range -1.0 to 1.0.
+    fn to_f32(self) -> f32 {
+        self.to_f64() as f32
+    }
+    /// Converts a sample in the range -1.0 to 1.0, clamping integers to their range.
+    fn from_f32(value: f32) -> Self {
+        Self::from_f64(f64::from(value))
+    }
+}
+
+macro_rules! impl_sample {
+    ($type:ty, $format:ident, $to_f64:expr, $from_f64:expr, $write_le:expr) => {
+        impl sealed::Sealed for $type {
+            fn slice(buffer: &SampleBuffer) -> Option<&[Self]> {
+                match buffer {
+                    SampleBuffer::$format(samples) => Some(samples),
+                    _ => None,
+                }
+            }
+            fn wrap(samples: Vec<Self>) -> SampleBuffer {
+                SampleBuffer::$format(samples)
+            }
+            fn to_f64(self) -> f64 {
+                $to_f64(self)
+            }
+            fn from_f64(value: f64) -> Self {
+                $from_f64(value)
+            }
+            fn write_le(self, bytes: &mut Vec<u8>) {
+                bytes.extend($write_le(self))
+            }
+        }
+        impl Sample for $type {
+            const FORMAT: SampleFormat = SampleFormat::$format;
+        }
+    };
+}
+
+/// Scales a sample in the range -1.0 to 1.0 to an integer of `bits` bits.
+fn scale_to_integer(value: f64, bits: u32) -> f64 {
+    let scale = f64::from(1u32 << (bits - 1));
+    (value * scale).round().clamp(-scale, scale - 1.0)
+}
+
+impl_sample!(f32, F32, f64::from, |value| value as f32, f32::to_le_bytes);
+impl_sample!(
+    i16,
+    I16,
+    |sample| f64::from(sample) / 32768.0,
+    |value| scale_to_integer(value, 16) as i16,
+    i16::to_le_bytes
+);
+impl_sample!(
+    I24,
+    I24,
+    |sample: I24| f64::from(sample.0) / 8388608.0,
+    |value| I24(scale_to_integer(value, 24) as i32),
+    I24::to_le_bytes
+);
+impl_sample!(
+    i32,
+    I32,
+    |sample| f64::from(sample) / 2147483648.0,
+    |value| scale_to_integer(value, 32) as i32,
+    i32::to_le_bytes
+);
+
+impl SampleBuffer {
+    fn format(&self) -> SampleFormat {
+        match self {
+            SampleBuffer::F32(_) => SampleFormat::F32,
+            SampleBuffer::I16(_) => SampleFormat::I16,
+            SampleBuffer::I24(_) => SampleFormat::I24,
+            SampleBuffer::I32(_) => SampleFormat::I32,
+        }
+    }
+    fn get_f64(&self, index: usize) -> f64 {
+        match self {
+            SampleBuffer::F32(samples) => samples[index].to_f64(),
+            SampleBuffer::I16(samples) => samples[index].to_f64(),
+            SampleBuffer::I24(samples) => samples[index].to_f64(),
+            SampleBuffer::I32(samples) => samples[index].to_f64(),
+        }
+    }
+    fn to_le_bytes(&self) -> Vec<u8> {
+        fn write<T: Sample>(samples: &[T]) -> Vec<u8> {
+            let mut bytes = Vec::with_capacity(samples.len() * T::FORMAT.bytes_per_sample());
+            samples
+                .iter()
+                .for_each(|sample| sample.write_le(&mut bytes));
+            bytes
+        }
+        match self {
+            SampleBuffer::F32(samples) => write(samples),
+            SampleBuffer::I16(samples) => write(samples),
+            SampleBuffer::I24(samples) => write(samples),
+            SampleBuffer::I32(samples) => write(samples),
+        }
+    }
+    fn decode(bytes: &[u8], format: SampleFormat, big_endian: bool) -> Self {
+        macro_rules! decode {
+            ($type:ty, $size:literal) => {
+                bytes
+                    .chunks_exact($size)
+                    .map(|sample| {
+                        let sample: [u8; $size] = sample.try_into().unwrap();
+                        if big_endian {
+                            <$type>::from_be_bytes(sample)
+                        } else {
+                            <$type>::from_le_bytes(sample)
+                        }
+                    })
+                    .collect()
+            };
+        }
+        match format {
+            SampleFormat::F32 => SampleBuffer::F32(decode!(f32, 4)),
+            SampleFormat::I16 => SampleBuffer::I16(decode!(i16, 2)),
+            SampleFormat::I24 => SampleBuffer::I24(decode!(I24, 3)),
+            SampleFormat::I32 => SampleBuffer::I32(decode!(i32, 4)),
+        }
+    }
+}
+
+/// Linear PCM audio whose samples were copied out of the sample buffer.
+///
+/// Planar audio has one buffer per channel, interleaved audio one buffer with the samples of
+/// all channels for each frame in turn. ScreenCaptureKit delivers planar `f32` audio.
+#[derive(Debug, Clone)]
+pub struct AudioFrame {
+    sample_rate: f64,
+    channel_count: usize,
+    frame_count: usize,
+    is_interleaved: bool,
+    presentation_timestamp: CMTime,
+    buffers: Vec<SampleBuffer>,
+}
+
+impl AudioFrame {
+    /// Creates planar audio from the samples of each channel.
+    pub fn from_planar<T: Sample>(
+        sample_rate: f64,
+        channels: Vec<Vec<T>>,
+        presentation_timestamp: CMTime,
+    ) -> Result<Self, String> {
+        if channels.is_empty() {
+            return Err("Audio must have at least one channel".to_string());
+        }
+        let frame_count = channels[0].len();
+        if channels.iter().any(|channel| channel.len() != frame_count) {
+            return Err("Every channel must have the same number of samples".to_string());
+        }
+        Ok(Self {
+            sample_rate,
+            channel_count: channels.len(),
+            frame_count,
+            is_interleaved: false,
+            presentation_timestamp,
+            buffers: channels.into_iter().map(T::wrap).collect(),
+        })
+    }
+
+    /// Creates interleaved audio from the samples of each frame in turn.
+    pub fn from_interleaved<T: Sample>(
+        sample_rate: f64,
+        channel_count: usize,
+        samples: Vec<T>,
+        presentation_timestamp: CMTime,
+    ) -> Result<Self, String> {
+        if channel_count == 0 || samples.len() % channel_count != 0 {
+            return Err(format!(
+                "{} samples can't be split into frames of {channel_count} channels",
+                samples.len()
+            ));
+        }
+        Ok(Self {
+            sample_rate,
+            channel_count,
+            frame_count: samples.len() / channel_count,
+            is_interleaved: true,
+            presentation_timestamp,
+            buffers: vec![T::wrap(samples)],
+        })
+    }
+
+    /// Decodes the buffers of linear PCM audio in the format of `description`.
+    pub fn from_buffers(
+        description: &AudioStreamBasicDescription,
+        buffers: &[CopiedAudioBuffer],
+        presentation_timestamp: CMTime,
+    ) -> Result<Self, String> {
+        let format = SampleFormat::from_description(description)?;
+        let channel_count = description.channels_per_frame as usize;
+        let is_interleaved = description.format_flags & kAudioFormatFlagIsNonInterleaved == 0;
+        let channels_per_buffer = if is_interleaved { channel_count } else { 1 };
+        let expected_buffers = if is_interleaved { 1 } else { channel_count };
+        if channel_count == 0 || buffers.len() != expected_buffers {
+            return Err(format!(
+                "Expected {expected_buffers} buffers for {channel_count} channels, got {}",
+                buffers.len()
+            ));
+        }
+        let bytes_per_frame = format.bytes_per_sample() * channels_per_buffer;
+        if description.bytes_per_frame as usize != bytes_per_frame {
+            return Err(format!(
+                "{} bytes per frame don't match {channels_per_buffer} channels of {format:?}",
+                description.bytes_per_frame
+            ));
+        }
+        let frame_count = buffers[0].data.len() / bytes_per_frame;
+        if let Some(buffer) = buffers
+            .iter()
+            .find(|buffer| buffer.data.len() != frame_count * bytes_per_frame)
+        {
+            return Err(format!(
+                "An audio buffer of {} bytes doesn't hold whole frames of {frame_count} samples",
+                buffer.data.len()
+            ));
+        }
+        let big_endian = description.format_flags & kAudioFormatFlagIsBigEndian != 0;
+        Ok(Self {
+            sample_rate: description.sample_rate,
+            channel_count,
+            frame_count,
+            is_interleaved,
+            presentation_timestamp,
+            buffers: buffers
+                .iter()
+                .map(|buffer| SampleBuffer::decode(&buffer.data, format, big_endian))
+                .collect(),
+        })
+    }
+
+    pub fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+    pub fn get_channel_count(&self) -> usize {
+        self.channel_count
+    }
+    /// The number of samples of each channel.
+    pub fn get_frame_count(&self) -> usize {
+        self.frame_count
+    }
+    pub fn get_presentation_timestamp(&self) -> CMTime {
+        self.presentation_timestamp
+    }
+    pub fn get_sample_format(&self) -> SampleFormat {
+        self.buffers
+            .first()
+            .map_or(SampleFormat::F32, SampleBuffer::format)
+    }
+    pub fn is_interleaved(&self) -> bool {
+        self.is_interleaved
+    }
+    /// The duration in seconds.
+    pub fn get_duration(&self) -> f64 {
+        self.frame_count as f64 / self.sample_rate
+    }
+
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+    /// Returns the samples of a buffer, or `None` if they are not of type `T`.
+    pub fn buffer<T: Sample>(&self, buffer_index: usize) -> Option<&[T]> {
+        self.buffers.get(buffer_index).and_then(T::slice)
+    }
+    pub fn buffers<T: Sample>(&self) -> Option<Vec<&[T]>> {
+        self.buffers.iter().map(T::slice).collect()
+    }
+    /// Returns the samples of one channel, or `None` if they are not of type `T`.
+    pub fn channel<T: Sample>(&self, channel: usize) -> Option<impl Iterator<Item = T> + '_> {
+        if channel >= self.channel_count {
+            return None;
+        }
+        let (samples, start, step) = if self.is_interleaved {
+            (self.buffer::<T>(0)?, channel, self.channel_count)
+        } else {
+            (self.buffer::<T>(channel)?, 0, 1)
+        };
+        Some(samples[start..].iter().step_by(step).copied())
+    }
+
+    /// Returns the samples of every frame in turn, or `None` if they are not of type `T`.
+    pub fn to_interleaved<T: Sample>(&self) -> Option<Vec<T>> {
+        if self.is_interleaved {
+            return self.buffer::<T>(0).map(<[T]>::to_vec);
+        }
+        let channels = self.buffers::<T>()?;
+        Some(
+            (0..self.frame_count)
+                .flat_map(|frame| channels.iter().map(move |channel| channel[frame]))
+                .collect(),
+        )
+    }
+
+    /// Returns sample `frame` of `channel` in the range -1.0 to 1.0.
+    pub fn get_sample_f32(&self, channel: usize, frame: usize) -> Option<f32> {
+        self.get_sample_f64(channel, frame)
+            .map(|sample| sample as f32)
+    }
+    /// Like [`AudioFrame::get_sample_f32`], but exact for every integer format.
+    pub(crate) fn get_sample_f64(&self, channel: usize, frame: usize) -> Option<f64> {
+        if channel >= self.channel_count || frame >= self.frame_count {
+            return None;
+        }
+        Some(if self.is_interleaved {
+            self.buffers[0].get_f64(frame * self.channel_count + channel)
+        } else {
+            self.buffers[channel].get_f64(frame)
+        })
+    }
+    /// Returns the samples of every channel in the range -1.0 to 1.0.
+    pub fn to_planar_f32(&self) -> Vec<Vec<f32>> {
+        (0..self.channel_count)
+            .map(|channel| {
+                (0..self.frame_count)
+                    .map(|frame| self.get_sample_f32(channel, frame).unwrap_or_default())
+                    .collect()
+            })
+            .collect()
+    }
+    /// Returns the samples of every frame in turn in the range -1.0 to 1.0.
+    pub fn to_interleaved_f32(&self) -> Vec<f32> {
+        (0..self.frame_count)
+            .flat_map(|frame| {
+                (0..self.channel_count)
+                    .map(move |channel| self.get_sample_f32(channel, frame).unwrap_or_default())
+            })
+            .collect()
+    }
+}
+
+impl AudioFrame {
+    /// Describes the samples as packed native linear PCM, the inverse of [`AudioFrame::from_buffers`].
+    pub fn get_description(&self) -> AudioStreamBasicDescription {
+        let format = self.get_sample_format();
+        let channels_per_buffer = if self.is_interleaved {
+            self.channel_count
+        } else {
+            1
+        };
+        let bytes_per_frame = (format.bytes_per_sample() * channels_per_buffer) as u32;
+        let format_flags = match format {
+            SampleFormat::F32 => kAudioFormatFlagIsFloat,
+            _ => kAudioFormatFlagIsSignedInteger,
+        } | kAudioFormatFlagIsPacked
+            | if self.is_interleaved {
+                0
+            } else {
+                kAudioFormatFlagIsNonInterleaved
+            };
+        AudioStreamBasicDescription {
+            sample_rate: self.sample_rate,
+            format_id: kAudioFormatLinearPCM,
+            format_flags,
+            bytes_per_packet: bytes_per_frame,
+            frames_per_packet: 1,
+            bytes_per_frame,
+            channels_per_frame: self.channel_count as u32,
+            bits_per_channel: format.bytes_per_sample() as u32 * 8,
+            reserved: 0,
+        }
+    }
+    /// Encodes the samples as little-endian buffers in the layout of [`AudioFrame::get_description`].
+    pub fn to_audio_buffers(&self) -> Vec<CopiedAudioBuffer> {
+        let number_channels = if self.is_interleaved {
+            self.channel_count as u32
+        } else {
+            1
+        };
+        self.buffers
+            .iter()
+            .map(|buffer| CopiedAudioBuffer {
+                number_channels,
+                data: buffer.to_le_bytes(),
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<&CMSampleBuffer> for AudioFrame {
+    type Error = String;
+
+    /// Copies the samples of an audio sample, failing for video samples.
+    fn try_from(sample: &CMSampleBuffer) -> Result<Self, Self::Error> {
+        let description = sample
+            .audio_description
+            .as_ref()
+            .ok_or_else(|| "The sample buffer has no audio".to_string())?;
+        AudioFrame::from_buffers(
+            description,
+            &sample.get_audio_buffers(),
+            sample.presentation_timestamp,
+        )
+    }
+}
+
+impl TryFrom<CMSampleBuffer> for AudioFrame {
+    type Error = String;
+
+    fn try_from(sample: CMSampleBuffer) -> Result<Self, Self::Error> {
+        AudioFrame::try_from(&sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use screencapturekit_sys::{
+        cm_format_description_ref::kAudioFormatMPEG4AAC, sc_stream_frame_info::SCFrameStatus,
+    };
+
+    use super::*;
+    use crate::audio_conversion::{AudioConverter, AudioFormat};
+
+    fn description(
+        format_flags: u32,
+        bits_per_channel: u32,
+        channels_per_frame: u32,
+    ) -> AudioStreamBasicDescription {
+        let channels_per_buffer = if format_flags & kAudioFormatFlagIsNonInterleaved != 0 {
+            1
+        } else {
+            channels_per_frame
+        };
+        AudioStreamBasicDescription {
+            sample_rate: 48000.0,
+            format_id: kAudioFormatLinearPCM,
+            format_flags: format_flags | kAudioFormatFlagIsPacked,
+            bytes_per_packet: bits_per_channel / 8 * channels_per_buffer,
+            frames_per_packet: 1,
+            bytes_per_frame: bits_per_channel / 8 * channels_per_buffer,
+            channels_per_frame,
+            bits_per_channel,
+            reserved: 0,
+        }
+    }
+
+    fn buffer(number_channels: u32, data: Vec<u8>) -> CopiedAudioBuffer {
+        CopiedAudioBuffer {
+            number_channels,
+            data,
+        }
+    }
+
+    #[test]
+    fn test_planar_f32() {
+        let left: Vec<u8> = [0.5f32, -0.25, 1.0]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let right: Vec<u8> = [0.0f32, 0.125, -1.0]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let sample = CMSampleBuffer::from_audio_buffers(
+            description(
+                kAudioFormatFlagIsFloat | kAudioFormatFlagIsNonInterleaved,
+                32,
+                2,
+            ),
+            vec![buffer(1, left), buffer(1, right)],
+            CMTime {
+                value: 3,
+                timescale: 48000,
+                ..Default::default()
+            },
+        );
+        let frame = AudioFrame::try_from(&sample).unwrap();
+        assert_eq!(frame.get_sample_rate(), 48000.0);
+        assert_eq!(frame.get_channel_count(), 2);
+        assert_eq!(frame.get_frame_count(), 3);
+        assert_eq!(frame.get_sample_format(), SampleFormat::F32);
+        assert_eq!(frame.get_presentation_timestamp().value, 3);
+        assert!(!frame.is_interleaved());
+        assert_eq!(frame.buffer_count(), 2);
+        assert_eq!(frame.buffer::<f32>(0).unwrap(), [0.5, -0.25, 1.0]);
+        assert!(frame.buffer::<i16>(0).is_none());
+        assert_eq!(
+            frame.channel::<f32>(1).unwrap().collect::<Vec<_>>(),
+            [0.0, 0.125, -1.0]
+        );
+        assert!(frame.channel::<f32>(2).is_none());
+        assert_eq!(
+            frame.to_interleaved_f32(),
+            [0.5, 0.0, -0.25, 0.125, 1.0, -1.0]
+        );
+    }
+
+    #[test]
+    fn test_interleaved_integers() {
+        // three frames of two channels of big-endian 16-bit samples
+        let data = [
+            0x40, 0x00, 0xc0, 0x00, 0x7f, 0xff, 0x80, 0x00, 0x00, 0x01, 0xff, 0xff,
+        ];
+        let frame = AudioFrame::from_buffers(
+            &description(
+                kAudioFormatFlagIsSignedInteger | kAudioFormatFlagIsBigEndian,
+                16,
+                2,
+            ),
+            &[buffer(2, data.to_vec())],
+            CMTime::default(),
+        )
+        .unwrap();
+        assert!(frame.is_interleaved());
+        assert_eq!(frame.get_frame_count(), 3);
+        assert_eq!(
+            frame.buffer::<i16>(0).unwrap(),
+            [16384, -16384, 32767, -32768, 1, -1]
+        );
+        assert_eq!(
+            frame.channel::<i16>(1).unwrap().collect::<Vec<_>>(),
+            [-16384, -32768, -1]
+        );
+        assert_eq!(frame.get_sample_f32(0, 0), Some(0.5));
+        assert_eq!(frame.get_sample_f32(1, 1), Some(-1.0));
+        assert_eq!(frame.to_planar_f32()[0][..2], [0.5, 32767.0 / 32768.0]);
+
+        let words: Vec<u8> = [i32::MIN, i32::MAX / 2 + 1]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let frame = AudioFrame::from_buffers(
+            &description(kAudioFormatFlagIsSignedInteger, 32, 1),
+            &[buffer(1, words)],
+            CMTime::default(),
+        )
+        .unwrap();
+        assert_eq!(frame.get_sample_format(), SampleFormat::I32);
+        assert_eq!(frame.to_interleaved_f32(), [-1.0, 0.5]);
+    }
+
+    #[test]
+    fn test_sample_conversions() {
+        assert_eq!(i16::from_f32(1.5), i16::MAX);
+        assert_eq!(i16::from_f32(-1.0), i16::MIN);
+        assert_eq!(i16::from_f32(0.5), 16384);
+        assert_eq!(i32::from_f32(-2.0), i32::MIN);
+        assert_eq!(i32::from_f32(0.25).to_f32(), 0.25);
+        assert_eq!(f32::from_f32(0.75), 0.75);
+    }
+
+    #[test]
+    fn test_24_bit() {
+        let data = [
+            0x00, 0x00, 0x40, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f, 0x00, 0x00, 0x80,
+        ];
+        let frame = AudioFrame::from_buffers(
+            &description(kAudioFormatFlagIsSignedInteger, 24, 2),
+            &[buffer(2, data.to_vec())],
+            CMTime::default(),
+        )
+        .unwrap();
+        assert_eq!(frame.get_sample_format(), SampleFormat::I24);
+        assert_eq!(
+            frame.to_interleaved::<I24>().unwrap(),
+            [0x400000, -1, I24::MAX.get(), I24::MIN.get()].map(|sample| I24::new(sample).unwrap())
+        );
+        assert_eq!(frame.to_interleaved_f32()[..2], [0.5, -1.0 / 8388608.0]);
+        assert_eq!(
+            I24::from_be_bytes([0x80, 0x00, 0x01]),
+            I24::new(-8388607).unwrap()
+        );
+        assert_eq!(I24::new(1 << 23), None);
+        assert_eq!(I24::from_f32(-1.5), I24::MIN);
+        assert_eq!(I24::from_f32(0.25).get(), 0x200000);
+    }
+
+    #[test]
+    fn test_to_audio_buffers() {
+        let frame = AudioFrame::from_planar(
+            16000.0,
+            vec![vec![1i16, -2], vec![3, i16::MIN]],
+            CMTime::default(),
+        )
+        .unwrap();
+        let description = frame.get_description();
+        assert_eq!(description.bytes_per_frame, 2);
+        assert_eq!(
+            SampleFormat::from_description(&description),
+            Ok(SampleFormat::I16)
+        );
+        let buffers = frame.to_audio_buffers();
+        assert_eq!(buffers[1].data, [3, 0, 0, 0x80]);
+        let decoded = AudioFrame::from_buffers(&description, &buffers, CMTime::default()).unwrap();
+        assert_eq!(
+            decoded.to_interleaved::<i16>(),
+            frame.to_interleaved::<i16>()
+        );
+
+        let frame =
+            AudioFrame::from_interleaved(48000.0, 2, vec![I24::MIN, I24::MAX], CMTime::default())
+                .unwrap();
+        let buffers = frame.to_audio_buffers();
+        assert_eq!(buffers[0].data, [0, 0, 0x80, 0xff, 0xff, 0x7f]);
+        let decoded =
+            AudioFrame::from_buffers(&frame.get_description(), &buffers, CMTime::default())
+                .unwrap();
+        assert_eq!(
+            decoded.to_interleaved::<I24>().unwrap(),
+            [I24::MIN, I24::MAX]
+        );
+    }
+
+    #[test]
+    fn test_constructors() {
+        let planar =
+            AudioFrame::from_planar(44100.0, vec![vec![0.5f32; 441]; 2], CMTime::default())
+                .unwrap();
+        assert_eq!(planar.get_duration(), 0.01);
+        assert_eq!(planar.buffers::<f32>().unwrap().len(), 2);
+        assert!(AudioFrame::from_planar(
+            44100.0,
+            vec![vec![0i16; 2], vec![0; 3]],
+            CMTime::default()
+        )
+        .is_err());
+        assert!(
+            AudioFrame::from_planar(44100.0, Vec::<Vec<f32>>::new(), CMTime::default()).is_err()
+        );
+
+        let interleaved =
+            AudioFrame::from_interleaved(8000.0, 2, vec![1i16, 2, 3, 4], CMTime::default())
+                .unwrap();
+        assert_eq!(interleaved.get_frame_count(), 2);
+        assert_eq!(
+            interleaved.channel::<i16>(0).unwrap().collect::<Vec<_>>(),
+            [1, 3]
+        );
+        assert!(AudioFrame::from_interleaved(8000.0, 2, vec![1i16], CMTime::default()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_audio() {
+        let float = description(kAudioFormatFlagIsFloat, 32, 2);
+        let aac = AudioStreamBasicDescription {
+            format_id: kAudioFormatMPEG4AAC,
+            ..float
+        };
+        assert!(
+            AudioFrame::from_buffers(&aac, &[buffer(2, vec![0; 8])], CMTime::default()).is_err()
+        );
+
+        let unsigned_8_bit = description(0, 8, 2);
+        assert!(AudioFrame::from_buffers(
+            &unsigned_8_bit,
+            &[buffer(2, vec![0; 2])],
+            CMTime::default()
+        )
+        .is_err());
+
+        let partial_frame = [buffer(2, vec![0; 12])];
+        assert!(AudioFrame::from_buffers(&float, &partial_frame, CMTime::default()).is_err());
+
+        let planar = description(
+            kAudioFormatFlagIsFloat | kAudioFormatFlagIsNonInterleaved,
+            32,
+            2,
+        );
+        let one_channel = [buffer(1, vec![0; 8])];
+        assert!(AudioFrame::from_buffers(&planar, &one_channel, CMTime::default()).is_err());
+        let uneven = [buffer(1, vec![0; 8]), buffer(1, vec![0; 4])];
+        assert!(AudioFrame::from_buffers(&planar, &uneven, CMTime::default()).is_err());
+
+        let video = CMSampleBuffer::from_pixel_buffer(None, SCFrameStatus::Idle, CMTime::default());
+        assert!(AudioFrame::try_from(video).is_err());
+    }
+
+    #[test]
+    fn test_converting_no_channels_fails() {
+        // None of the constructors makes audio without channels, so build it by hand.
+        let silent = AudioFrame {
+            sample_rate: 48000.0,
+            channel_count: 0,
+            frame_count: 0,
+            is_interleaved: false,
+            presentation_timestamp: CMTime::default(),
+            buffers: Vec::new(),
+        };
+        let converter = AudioConverter::new(AudioFormat::new(SampleFormat::F32, 2, false));
+        assert!(converter.convert(&silent).is_err());
+    }
+}
@@ -0,0 +1,599 @@
+//! A ring buffer between the audio a stream pushes and a consumer that pulls it, without locks
+//! between the two sides.
+//!
+//! ScreenCaptureKit delivers audio in buffers of varying size on its own queue. An
+//! [`AudioRingWriter`] is a [`StreamOutput`] that copies them into a ring buffer, and the
+//! matching [`AudioRingReader`] takes blocks of exactly as many frames as it asks for, with the
+//! timestamp of their first frame. Neither side ever waits for the other: audio that doesn't fit
+//! is dropped and counted as an overrun, and a reader that asks for more than there is can pad
+//! the block with silence and have it counted as an underrun.
+
+use std::sync::{
+    atomic::{AtomicU32, AtomicU64, Ordering},
+    Arc, Mutex, OnceLock,
+};
+
+use screencapturekit_sys::os_types::base::CMTime;
+
+use crate::{
+    audio_frame::AudioFrame,
+    cm_sample_buffer::CMSampleBuffer,
+    sc_output_handler::{SCStreamOutputType, StreamOutput},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioRingBufferOptions {
+    /// The number of frames the ring buffer holds.
+    pub capacity: usize,
+    /// Whether to write silence where the timestamps of the buffers leave a gap, so that the
+    /// timestamps of the blocks stay right. Otherwise the buffers are joined back to back.
+    pub fill_gaps: bool,
+    /// The largest difference in seconds between where a buffer is and where its timestamp says
+    /// it should be that is not a gap.
+    pub gap_tolerance: f64,
+}
+
+impl Default for AudioRingBufferOptions {
+    fn default() -> Self {
+        Self {
+            capacity: 48000,
+            fill_gaps: true,
+            gap_tolerance: 0.002,
+        }
+    }
+}
+
+/// The numbers of frames that did not pass through the ring buffer as they were.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AudioRingBufferStats {
+    /// Frames dropped because the ring buffer was full.
+    pub overrun_frames: u64,
+    /// Frames of silence the reader padded blocks with because the ring buffer was empty.
+    pub underrun_frames: u64,
+    /// Frames that were dropped because they arrived after the reader padded their place.
+    pub late_frames: u64,
+    /// Frames of silence written for gaps in the timestamps.
+    pub gap_frames: u64,
+    /// Sample buffers dropped because they did not hold audio in the format of the ring buffer.
+    pub invalid_buffers: u64,
+}
+
+struct Shared {
+    sample_rate: f64,
+    channel_count: usize,
+    capacity: usize,
+    /// The interleaved samples as the bits of `f32`s.
+    samples: Box<[AtomicU32]>,
+    /// The number of frames written since the start of the stream, owned by the writer.
+    head: AtomicU64,
+    /// The number of frames the reader is done with, owned by the reader.
+    tail: AtomicU64,
+    /// The timestamp of the first frame of the stream.
+    first_timestamp: OnceLock<CMTime>,
+    overrun_frames: AtomicU64,
+    underrun_frames: AtomicU64,
+    late_frames: AtomicU64,
+    gap_frames: AtomicU64,
+    invalid_buffers: AtomicU64,
+}
+
+impl Shared {
+    fn get_stats(&self) -> AudioRingBufferStats {
+        AudioRingBufferStats {
+            overrun_frames: self.overrun_frames.load(Ordering::Relaxed),
+            underrun_frames: self.underrun_frames.load(Ordering::Relaxed),
+            late_frames: self.late_frames.load(Ordering::Relaxed),
+            gap_frames: self.gap_frames.load(Ordering::Relaxed),
+            invalid_buffers: self.invalid_buffers.load(Ordering::Relaxed),
+        }
+    }
+
+    fn slot(&self, position: u64, channel: usize) -> &AtomicU32 {
+        let frame = (position % self.capacity as u64) as usize;
+        &self.samples[frame * self.channel_count + channel]
+    }
+
+    /// The timestamp of frame `position` of the stream.
+    fn timestamp(&self, position: u64) -> CMTime {
+        let first = self.first_timestamp.get().copied().unwrap_or_default();
+        if first.timescale <= 0 {
+            return first;
+        }
+        let offset = position as f64 * f64::from(first.timescale) / self.sample_rate;
+        CMTime {
+            value: first.value + offset.round() as i64,
+            ..first
+        }
+    }
+}
+
+/// Creates a ring buffer for audio with `channel_count` channels at `sample_rate`.
+pub fn audio_ring_buffer(
+    sample_rate: f64,
+    channel_count: usize,
+    options: AudioRingBufferOptions,
+) -> Result<(AudioRingWriter, AudioRingReader), String> {
+    if !(sample_rate > 0.0 && sample_rate.is_finite()) {
+        return Err(format!("Can't buffer audio at {sample_rate} Hz"));
+    }
+    if channel_count == 0 || options.capacity == 0 {
+        return Err(format!(
+            "Can't buffer {} frames of {channel_count} channels",
+            options.capacity
+        ));
+    }
+    if !(options.gap_tolerance >= 0.0 && options.gap_tolerance.is_finite()) {
+        return Err(format!(
+            "Invalid gap tolerance of {} seconds",
+            options.gap_tolerance
+        ));
+    }
+    let shared = Arc::new(Shared {
+        sample_rate,
+        channel_count,
+        capacity: options.capacity,
+        samples: (0..options.capacity * channel_count)
+            .map(|_| AtomicU32::new(0))
+            .collect(),
+        head: AtomicU64::new(0),
+        tail: AtomicU64::new(0),
+        first_timestamp: OnceLock::new(),
+        overrun_frames: AtomicU64::new(0),
+        underrun_frames: AtomicU64::new(0),
+        late_frames: AtomicU64::new(0),
+        gap_frames: AtomicU64::new(0),
+        invalid_buffers: AtomicU64::new(0),
+    });
+    let writer = AudioRingWriter {
+        shared: shared.clone(),
+        write_lock: Mutex::new(()),
+        fill_gaps: options.fill_gaps,
+        gap_tolerance: (options.gap_tolerance * sample_rate).round() as i64,
+    };
+    let reader = AudioRingReader {
+        shared,
+        position: 0,
+    };
+    Ok((writer, reader))
+}
+
+/// The writing end of an audio ring buffer, to add to a stream as an audio output.
+///
+/// Writes from several threads take turns, so the writer can be shared, but their audio is
+/// interleaved in whatever order they get to write. The reader never waits for a writer.
+pub struct AudioRingWriter {
+    shared: Arc<Shared>,
+    /// Held while writing, since only one writer may move the head at a time.
+    write_lock: Mutex<()>,
+    fill_gaps: bool,
+    /// The gap tolerance in frames.
+    gap_tolerance: i64,
+}
+
+impl AudioRingWriter {
+    /// Adds the frames of `frame` and returns how many fit.
+    pub fn write(&self, frame: &AudioFrame) -> Result<usize, String> {
+        let shared = &*self.shared;
+        if frame.get_sample_rate() != shared.sample_rate
+            || frame.get_channel_count() != shared.channel_count
+        {
+            return Err(format!(
+                "Expected {} channels at {} Hz, got {} channels at {} Hz",
+                shared.channel_count,
+                shared.sample_rate,
+                frame.get_channel_count(),
+                frame.get_sample_rate()
+            ));
+        }
+        // A writer that panicked left the head where it was, so the lock is still good.
+        let _guard = self
+            .write_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let timestamp = frame.get_presentation_timestamp();
+        let first = *shared.first_timestamp.get_or_init(|| timestamp);
+        let mut head = shared.head.load(Ordering::Relaxed);
+        if self.fill_gaps && first.timescale > 0 && timestamp.timescale > 0 {
+            let seconds = timestamp.value as f64 / f64::from(timestamp.timescale)
+                - first.value as f64 / f64::from(first.timescale);
+            let gap = (seconds * shared.sample_rate).round() as i64 - head as i64;
+            if gap > 0 && gap > self.gap_tolerance {
+                let written = self.write_frames(head, gap as usize, |_, _| 0.0);
+                shared
+                    .gap_frames
+                    .fetch_add(written as u64, Ordering::Relaxed);
+                head += written as u64;
+            }
+        }
+        let count = frame.get_frame_count();
+        let written = self.write_frames(head, count, |channel, index| {
+            frame.get_sample_f32(channel, index).unwrap_or_default()
+        });
+        shared
+            .overrun_frames
+            .fetch_add((count - written) as u64, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    /// Writes `count` frames after `head` as far as they fit and publishes them.
+    fn write_frames(&self, head: u64, count: usize, sample: impl Fn(usize, usize) -> f32) -> usize {
+        let shared = &*self.shared;
+        let tail = shared.tail.load(Ordering::Acquire);
+        let free = shared.capacity - (head - tail) as usize;
+        let written = count.min(free);
+        for index in 0..written {
+            for channel in 0..shared.channel_count {
+                shared
+                    .slot(head + index as u64, channel)
+                    .store(sample(channel, index).to_bits(), Ordering::Relaxed);
+            }
+        }
+        shared.head.store(head + written as u64, Ordering::Release);
+        written
+    }
+
+    pub fn get_stats(&self) -> AudioRingBufferStats {
+        self.shared.get_stats()
+    }
+}
+
+impl StreamOutput for AudioRingWriter {
+    fn did_output_sample_buffer(&self, sample_buffer: CMSampleBuffer, of_type: SCStreamOutputType) {
+        if of_type != SCStreamOutputType::Audio {
+            return;
+        }
+        let written = AudioFrame::try_from(&sample_buffer).and_then(|frame| self.write(&frame));
+        if written.is_err() {
+            self.shared.invalid_buffers.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The reading end of an audio ring buffer.
+pub struct AudioRingReader {
+    shared: Arc<Shared>,
+    /// The stream position of the next frame to read, which is ahead of the writer after
+    /// padding.
+    position: u64,
+}
+
+impl AudioRingReader {
+    pub fn get_sample_rate(&self) -> f64 {
+        self.shared.sample_rate
+    }
+    pub fn get_channel_count(&self) -> usize {
+        self.shared.channel_count
+    }
+    /// The number of frames that can be read without padding.
+    pub fn available(&self) -> usize {
+        let head = self.shared.head.load(Ordering::Acquire);
+        head.saturating_sub(self.position) as usize
+    }
+
+    /// Reads exactly `frame_count` frames as planar `f32` audio, or returns `None` without
+    /// reading anything if fewer are available.
+    pub fn read(&mut self, frame_count: usize) -> Option<AudioFrame> {
+        (self.available() >= frame_count).then(|| self.read_padded(frame_count))
+    }
+
+    /// Reads `frame_count` frames as planar `f32` audio, with silence after the available
+    /// frames.
+    ///
+    /// The silence takes the place of audio that has yet to arrive, so that audio is dropped
+    /// when it does and the timestamps stay right.
+    pub fn read_padded(&mut self, frame_count: usize) -> AudioFrame {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Acquire);
+        let tail = shared.tail.load(Ordering::Relaxed);
+        // audio that arrived for frames that were padded
+        let late = head.min(self.position).saturating_sub(tail);
+        shared.late_frames.fetch_add(late, Ordering::Relaxed);
+
+        let available = (head.saturating_sub(self.position) as usize).min(frame_count);
+        let channels = (0..shared.channel_count)
+            .map(|channel| {
+                let mut samples: Vec<f32> = (0..available as u64)
+                    .map(|index| {
+                        let bits = shared
+                            .slot(self.position + index, channel)
+                            .load(Ordering::Relaxed);
+                        f32::from_bits(bits)
+                    })
+                    .collect();
+                samples.resize(frame_count, 0.0);
+                samples
+            })
+            .collect();
+        let timestamp = shared.timestamp(self.position);
+        shared
+            .underrun_frames
+            .fetch_add((frame_count - available) as u64, Ordering::Relaxed);
+        self.position += frame_count as u64;
+        shared
+            .tail
+            .store(head.min(self.position), Ordering::Release);
+        AudioFrame::from_planar(shared.sample_rate, channels, timestamp)
+            .expect("every channel has the same number of frames")
+    }
+
+    pub fn get_stats(&self) -> AudioRingBufferStats {
+        self.shared.get_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use screencapturekit_sys::{
+        audio_buffer::CopiedAudioBuffer,
+        cm_format_description_ref::{
+            kAudioFormatFlagIsFloat, kAudioFormatFlagIsNonInterleaved, kAudioFormatFlagIsPacked,
+            kAudioFormatLinearPCM, AudioStreamBasicDescription,
+        },
+        sc_stream_frame_info::SCFrameStatus,
+    };
+
+    use super::*;
+
+    const RATE: f64 = 1000.0;
+
+    fn at(value: i64) -> CMTime {
+        CMTime {
+            value,
+            timescale: 1000,
+            ..Default::default()
+        }
+    }
+
+    /// Stereo frames numbered from `start`, with the right channel negated.
+    fn frames(start: usize, count: usize) -> AudioFrame {
+        let left: Vec<f32> = (start..start + count).map(|index| index as f32).collect();
+        let right = left.iter().map(|sample| -sample).collect();
+        AudioFrame::from_planar(RATE, vec![left, right], at(start as i64)).unwrap()
+    }
+
+    fn left(frame: &AudioFrame) -> Vec<f32> {
+        frame.buffer::<f32>(0).unwrap().to_vec()
+    }
+
+    fn options(capacity: usize) -> AudioRingBufferOptions {
+        AudioRingBufferOptions {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fixed_blocks_from_varying_buffers() {
+        let (writer, mut reader) = audio_ring_buffer(RATE, 2, options(64)).unwrap();
+        assert_eq!(writer.write(&frames(0, 7)).unwrap(), 7);
+        assert_eq!(writer.write(&frames(7, 13)).unwrap(), 13);
+        assert_eq!(reader.available(), 20);
+
+        let block = reader.read(16).unwrap();
+        assert_eq!(
+            left(&block),
+            (0..16).map(|index| index as f32).collect::<Vec<_>>()
+        );
+        assert_eq!(block.buffer::<f32>(1).unwrap()[15], -15.0);
+        assert_eq!(block.get_presentation_timestamp().value, 0);
+        assert!(reader.read(16).is_none());
+        assert_eq!(reader.available(), 4);
+
+        // wrap around the end of the ring
+        for start in (20..200).step_by(30) {
+            writer.write(&frames(start, 30)).unwrap();
+            while let Some(block) = reader.read(16) {
+                let first = block.get_presentation_timestamp().value;
+                assert_eq!(left(&block)[0], first as f32);
+                assert_eq!(left(&block)[15], (first + 15) as f32);
+            }
+        }
+        assert_eq!(reader.get_stats(), AudioRingBufferStats::default());
+    }
+
+    #[test]
+    fn test_overrun_keeps_timestamps() {
+        let (writer, mut reader) = audio_ring_buffer(RATE, 2, options(10)).unwrap();
+        assert_eq!(writer.write(&frames(0, 15)).unwrap(), 10);
+        assert_eq!(writer.get_stats().overrun_frames, 5);
+        assert_eq!(left(&reader.read(10).unwrap())[9], 9.0);
+
+        // the frames that were dropped come back as silence
+        writer.write(&frames(15, 5)).unwrap();
+        assert_eq!(writer.get_stats().gap_frames, 5);
+        let block = reader.read(10).unwrap();
+        assert_eq!(block.get_presentation_timestamp().value, 10);
+        assert_eq!(
+            left(&block),
+            [0.0, 0.0, 0.0, 0.0, 0.0, 15.0, 16.0, 17.0, 18.0, 19.0]
+        );
+    }
+
+    #[test]
+    fn test_gaps() {
+        let (writer, mut reader) = audio_ring_buffer(RATE, 2, options(64)).unwrap();
+        writer.write(&frames(0, 4)).unwrap();
+        // within the tolerance of 2 frames
+        writer.write(&frames(6, 4)).unwrap();
+        writer.write(&frames(13, 2)).unwrap();
+        // the gap is measured from the timestamp of the first buffer, which takes up the jitter
+        assert_eq!(reader.get_stats().gap_frames, 5);
+        let block = reader.read(15).unwrap();
+        assert_eq!(
+            left(&block)[4..],
+            [6.0, 7.0, 8.0, 9.0, 0.0, 0.0, 0.0, 0.0, 0.0, 13.0, 14.0]
+        );
+        assert_eq!(reader.read_padded(1).get_presentation_timestamp().value, 15);
+
+        // overlapping timestamps are no gap, even without a tolerance
+        let (writer, _reader) = audio_ring_buffer(
+            RATE,
+            2,
+            AudioRingBufferOptions {
+                gap_tolerance: 0.0,
+                ..options(100)
+            },
+        )
+        .unwrap();
+        writer.write(&frames(0, 4)).unwrap();
+        writer.write(&frames(2, 4)).unwrap();
+        assert_eq!(writer.get_stats().gap_frames, 0);
+        assert_eq!(writer.get_stats().overrun_frames, 0);
+
+        let (writer, mut reader) = audio_ring_buffer(
+            RATE,
+            2,
+            AudioRingBufferOptions {
+                fill_gaps: false,
+                ..options(64)
+            },
+        )
+        .unwrap();
+        writer.write(&frames(0, 2)).unwrap();
+        writer.write(&frames(100, 2)).unwrap();
+        assert_eq!(left(&reader.read(4).unwrap()), [0.0, 1.0, 100.0, 101.0]);
+        assert_eq!(reader.get_stats().gap_frames, 0);
+    }
+
+    #[test]
+    fn test_underrun_and_late_audio() {
+        let (writer, mut reader) = audio_ring_buffer(RATE, 2, options(64)).unwrap();
+        writer.write(&frames(0, 4)).unwrap();
+        let block = reader.read_padded(10);
+        assert_eq!(
+            left(&block),
+            [0.0, 1.0, 2.0, 3.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(reader.get_stats().underrun_frames, 6);
+        assert_eq!(reader.available(), 0);
+
+        writer.write(&frames(4, 10)).unwrap();
+        assert_eq!(reader.available(), 4);
+        let block = reader.read(4).unwrap();
+        assert_eq!(block.get_presentation_timestamp().value, 10);
+        assert_eq!(left(&block), [10.0, 11.0, 12.0, 13.0]);
+        assert_eq!(reader.get_stats().late_frames, 6);
+        // the space of the late frames is free again
+        assert_eq!(writer.write(&frames(14, 64)).unwrap(), 64);
+    }
+
+    #[test]
+    fn test_stream_output() {
+        let (writer, mut reader) = audio_ring_buffer(RATE, 2, options(64)).unwrap();
+        let description = AudioStreamBasicDescription {
+            sample_rate: RATE,
+            format_id: kAudioFormatLinearPCM,
+            format_flags: kAudioFormatFlagIsFloat
+                | kAudioFormatFlagIsPacked
+                | kAudioFormatFlagIsNonInterleaved,
+            bytes_per_packet: 4,
+            frames_per_packet: 1,
+            bytes_per_frame: 4,
+            channels_per_frame: 2,
+            bits_per_channel: 32,
+            reserved: 0,
+        };
+        let channel = |sample: f32| CopiedAudioBuffer {
+            number_channels: 1,
+            data: [sample; 3].iter().flat_map(|s| s.to_le_bytes()).collect(),
+        };
+        let audio = || {
+            CMSampleBuffer::from_audio_buffers(
+                description,
+                vec![channel(0.5), channel(-0.5)],
+                at(0),
+            )
+        };
+        writer.did_output_sample_buffer(audio(), SCStreamOutputType::Audio);
+        writer.did_output_sample_buffer(audio(), SCStreamOutputType::Screen);
+        let video = CMSampleBuffer::from_pixel_buffer(None, SCFrameStatus::Complete, at(0));
+        writer.did_output_sample_buffer(video, SCStreamOutputType::Audio);
+
+        let block = reader.read(3).unwrap();
+        assert_eq!(block.buffer::<f32>(1).unwrap(), [-0.5; 3]);
+        assert_eq!(reader.available(), 0);
+        assert_eq!(reader.get_stats().invalid_buffers, 1);
+    }
+
+    #[test]
+    fn test_threads() {
+        let (writer, mut reader) = audio_ring_buffer(RATE, 2, options(50)).unwrap();
+        let producer = thread::spawn(move || {
+            let mut start = 0;
+            for size in (1..40).cycle().take(400) {
+                // retry what didn't fit, like a source that blocks
+                let mut written = 0;
+                while written < size {
+                    let frame = frames(start + written, size - written);
+                    let before = writer.get_stats().overrun_frames;
+                    written += writer.write(&frame).unwrap();
+                    let dropped = writer.get_stats().overrun_frames - before;
+                    assert!(dropped as usize <= size);
+                    thread::yield_now();
+                }
+                start += size;
+            }
+            start
+        });
+        let mut expected = 0.0;
+        let mut total = 0;
+        loop {
+            match reader.read(8) {
+                Some(block) => {
+                    for (left, right) in left(&block).iter().zip(block.buffer::<f32>(1).unwrap()) {
+                        assert_eq!(*left, expected);
+                        assert_eq!(*right, -expected);
+                        expected += 1.0;
+                    }
+                    total += 8;
+                }
+                None if producer.is_finished() => break,
+                None => thread::yield_now(),
+            }
+        }
+        let written = producer.join().unwrap();
+        assert_eq!(total + reader.available(), written);
+    }
+
+    #[test]
+    fn test_concurrent_writers() {
+        let (writer, reader) = audio_ring_buffer(
+            RATE,
+            2,
+            AudioRingBufferOptions {
+                fill_gaps: false,
+                ..options(1000)
+            },
+        )
+        .unwrap();
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for start in (0..100).step_by(10) {
+                        writer.write(&frames(start, 10)).unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(reader.available(), 400);
+        assert_eq!(reader.get_stats(), AudioRingBufferStats::default());
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(audio_ring_buffer(0.0, 2, options(10)).is_err());
+        assert!(audio_ring_buffer(RATE, 0, options(10)).is_err());
+        assert!(audio_ring_buffer(RATE, 2, options(0)).is_err());
+        for gap_tolerance in [-0.01, f64::NAN, f64::INFINITY] {
+            let options = AudioRingBufferOptions {
+                gap_tolerance,
+                ..options(10)
+            };
+            assert!(audio_ring_buffer(RATE, 2, options).is_err());
+        }
+        let (writer, _reader) = audio_ring_buffer(RATE, 1, options(10)).unwrap();
+        assert!(writer.write(&frames(0, 1)).is_err());
+    }
+}
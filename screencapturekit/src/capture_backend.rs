@@ -0,0 +1,106 @@
+pub mod synthetic;
+
+#[cfg(target_os = "macos")]
+use screencapturekit_sys::{os_types::rc::Id, stream::UnsafeSCStream};
+
+#[cfg(not(target_os = "macos"))]
+use crate::platform::UnsupportedPlatformError;
+use crate::{
+    platform::CaptureError,
+    sc_content_filter::SCContentFilter,
+    sc_error_handler::StreamErrorHandler,
+    sc_output_handler::{SCStreamOutputType, StreamOutput},
+    sc_stream_configuration::SCStreamConfiguration,
+};
+#[cfg(target_os = "macos")]
+use crate::{sc_error_handler::StreamErrorHandlerWrapper, sc_output_handler::StreamOutputWrapper};
+
+/// The source of sample buffers behind an [`SCStream`](crate::sc_stream::SCStream).
+///
+/// Implementations deliver samples to every registered [`StreamOutput`] whose output type
+/// matches the sample, through `StreamOutput::did_output_sample_buffer`.
+pub trait CaptureBackend {
+    fn add_output(&mut self, output: impl StreamOutput, output_type: SCStreamOutputType);
+    fn start_capture(&self) -> Result<(), CaptureError>;
+    fn stop_capture(&self) -> Result<(), CaptureError>;
+    /// Applies a new configuration to the stream, while capturing or before starting.
+    fn update_configuration(&self, config: SCStreamConfiguration) -> Result<(), CaptureError>;
+    /// Changes the content the stream captures, while capturing or before starting.
+    fn update_content_filter(&self, filter: SCContentFilter) -> Result<(), CaptureError>;
+}
+
+/// Captures through ScreenCaptureKit's `SCStream`.
+///
+/// On platforms without ScreenCaptureKit, creating it fails with
+/// [`CaptureError::UnsupportedPlatform`].
+pub struct NativeBackend {
+    #[cfg(target_os = "macos")]
+    pub(crate) _unsafe_ref: Id<UnsafeSCStream>,
+}
+
+impl NativeBackend {
+    #[cfg(target_os = "macos")]
+    pub fn new(
+        filter: SCContentFilter,
+        config: SCStreamConfiguration,
+        handler: impl StreamErrorHandler,
+    ) -> Result<Self, CaptureError> {
+        Ok(Self {
+            _unsafe_ref: UnsafeSCStream::init(
+                filter._unsafe_ref,
+                config.try_into()?,
+                StreamErrorHandlerWrapper::new(handler),
+            ),
+        })
+    }
+    #[cfg(not(target_os = "macos"))]
+    pub fn new(
+        _filter: SCContentFilter,
+        _config: SCStreamConfiguration,
+        _handler: impl StreamErrorHandler,
+    ) -> Result<Self, CaptureError> {
+        Err(UnsupportedPlatformError.into())
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl CaptureBackend for NativeBackend {
+    fn add_output(&mut self, output: impl StreamOutput, output_type: SCStreamOutputType) {
+        self._unsafe_ref.add_stream_output(
+            StreamOutputWrapper::new(output),
+            match output_type {
+                SCStreamOutputType::Screen => 0,
+                SCStreamOutputType::Audio => 1,
+            },
+        );
+    }
+    fn start_capture(&self) -> Result<(), CaptureError> {
+        Ok(self._unsafe_ref.start_capture()?)
+    }
+    fn stop_capture(&self) -> Result<(), CaptureError> {
+        Ok(self._unsafe_ref.stop_capture()?)
+    }
+    fn update_configuration(&self, config: SCStreamConfiguration) -> Result<(), CaptureError> {
+        Ok(self._unsafe_ref.update_configuration(config.try_into()?)?)
+    }
+    fn update_content_filter(&self, filter: SCContentFilter) -> Result<(), CaptureError> {
+        Ok(self._unsafe_ref.update_content_filter(filter._unsafe_ref)?)
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+impl CaptureBackend for NativeBackend {
+    fn add_output(&mut self, _output: impl StreamOutput, _output_type: SCStreamOutputType) {}
+    fn start_capture(&self) -> Result<(), CaptureError> {
+        Err(UnsupportedPlatformError.into())
+    }
+    fn stop_capture(&self) -> Result<(), CaptureError> {
+        Err(UnsupportedPlatformError.into())
+    }
+    fn update_configuration(&self, _config: SCStreamConfiguration) -> Result<(), CaptureError> {
+        Err(UnsupportedPlatformError.into())
+    }
+    fn update_content_filter(&self, _filter: SCContentFilter) -> Result<(), CaptureError> {
+        Err(UnsupportedPlatformError.into())
+    }
+}
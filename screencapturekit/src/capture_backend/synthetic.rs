@@ -0,0 +1,633 @@
+use std::{
+    f64::consts::PI,
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use screencapturekit_sys::{
+    audio_buffer::CopiedAudioBuffer,
+    cm_format_description_ref::{
+        kAudioFormatFlagIsFloat, kAudioFormatFlagIsNonInterleaved, kAudioFormatFlagIsPacked,
+        kAudioFormatLinearPCM, AudioStreamBasicDescription,
+    },
+    os_types::base::CMTime,
+    sc_stream_frame_info::SCFrameStatus,
+};
+
+use crate::{
+    cm_sample_buffer::CMSampleBuffer,
+    cv_pixel_buffer::{CVPixelBuffer, OwnedPlane},
+    platform::CaptureError,
+    sc_content_filter::SCContentFilter,
+    sc_output_handler::{SCStreamOutputType, StreamOutput},
+    sc_stream_configuration::{PixelFormat, SCStreamConfiguration},
+};
+
+use super::CaptureBackend;
+
+const DEFAULT_WIDTH: u32 = 1920;
+const DEFAULT_HEIGHT: u32 = 1080;
+const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+const DEFAULT_CHANNEL_COUNT: u32 = 2;
+// ScreenCaptureKit delivers system audio in chunks of 1024 frames.
+const AUDIO_FRAMES_PER_BUFFER: u64 = 1024;
+// CoreVideo pads rows to 64 bytes for the pixel formats ScreenCaptureKit produces.
+const ROW_ALIGNMENT: usize = 64;
+const TONE_FREQUENCY: f64 = 440.0;
+const TONE_AMPLITUDE: f64 = 0.25;
+const BAR_WIDTH: usize = 16;
+
+#[derive(Debug, Clone, Copy)]
+struct SyntheticSettings {
+    width: usize,
+    height: usize,
+    pixel_format: PixelFormat,
+    frame_interval: Duration,
+    captures_audio: bool,
+    sample_rate: u32,
+    channel_count: u32,
+    idle_every: u64,
+}
+
+impl SyntheticSettings {
+    fn new(config: &SCStreamConfiguration, idle_every: u64) -> Self {
+        let frame_interval = Duration::try_from(config.minimum_frame_interval)
+            .ok()
+            .filter(|interval| !interval.is_zero())
+            .unwrap_or(DEFAULT_FRAME_INTERVAL);
+        let or_default = |value: u32, default: u32| if value == 0 { default } else { value };
+        Self {
+            width: or_default(config.width, DEFAULT_WIDTH) as usize,
+            height: or_default(config.height, DEFAULT_HEIGHT) as usize,
+            pixel_format: match config.pixel_format {
+                format @ (PixelFormat::ARGB2101010
+                | PixelFormat::YCbCr420v
+                | PixelFormat::YCbCr420f) => format,
+                _ => PixelFormat::ARGB8888,
+            },
+            frame_interval,
+            captures_audio: config.captures_audio,
+            sample_rate: or_default(config.sample_rate, DEFAULT_SAMPLE_RATE),
+            channel_count: or_default(config.channel_count, DEFAULT_CHANNEL_COUNT),
+            idle_every,
+        }
+    }
+}
+
+enum Control {
+    Stop,
+    Update(SyntheticSettings),
+}
+
+type Outputs = Arc<RwLock<Vec<(Arc<dyn StreamOutput>, SCStreamOutputType)>>>;
+
+/// A capture backend that generates frames and audio in Rust instead of capturing the screen.
+///
+/// Video is a gradient with a moving bar in the configured pixel format, audio is a 440 Hz
+/// tone in the non-interleaved float32 layout ScreenCaptureKit uses. Samples are delivered
+/// from a background thread at the configured frame interval and sample rate, the first
+/// frame with [`SCFrameStatus::Started`] and the rest with [`SCFrameStatus::Complete`], or
+/// [`SCFrameStatus::Idle`] for the frames selected by [`SyntheticBackend::with_idle_frames_every`].
+///
+/// Zero values in the configuration fall back to 1920x1080 at 60 fps and 48 kHz stereo, and
+/// pixel formats other than `BGRA`, `l10r`, `420v` and `420f` to `BGRA`.
+/// Configuration updates take effect from the next sample, and content filter updates are
+/// accepted and ignored since there is no content.
+pub struct SyntheticBackend {
+    settings: Mutex<SyntheticSettings>,
+    outputs: Outputs,
+    worker: Mutex<Option<(Sender<Control>, JoinHandle<()>)>>,
+}
+
+impl SyntheticBackend {
+    pub fn new(config: SCStreamConfiguration) -> Self {
+        Self {
+            settings: Mutex::new(SyntheticSettings::new(&config, 0)),
+            outputs: Arc::new(RwLock::new(Vec::new())),
+            worker: Mutex::new(None),
+        }
+    }
+
+    /// Marks every `n`th frame as [`SCFrameStatus::Idle`], without a pixel buffer, the way
+    /// ScreenCaptureKit reports frames where the screen did not change. `0` disables idle frames.
+    pub fn with_idle_frames_every(mut self, n: u64) -> Self {
+        self.settings.get_mut().unwrap().idle_every = n;
+        self
+    }
+}
+
+impl CaptureBackend for SyntheticBackend {
+    fn add_output(&mut self, output: impl StreamOutput, output_type: SCStreamOutputType) {
+        self.outputs
+            .write()
+            .unwrap()
+            .push((Arc::new(output), output_type));
+    }
+    fn start_capture(&self) -> Result<(), CaptureError> {
+        let mut worker = self.worker.lock().unwrap();
+        if worker.is_some() {
+            return Err("The stream is already capturing".into());
+        }
+        let (control_tx, control_rx) = channel();
+        let settings = *self.settings.lock().unwrap();
+        let outputs = self.outputs.clone();
+        let handle = thread::spawn(move || run(settings, &outputs, &control_rx));
+        *worker = Some((control_tx, handle));
+        Ok(())
+    }
+    fn stop_capture(&self) -> Result<(), CaptureError> {
+        let (control_tx, handle) = self
+            .worker
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(CaptureError::from("The stream is not capturing"))?;
+        control_tx.send(Control::Stop).ok();
+        handle
+            .join()
+            .map_err(|_| "The capture thread panicked".into())
+    }
+    fn update_configuration(&self, config: SCStreamConfiguration) -> Result<(), CaptureError> {
+        let mut settings = self.settings.lock().unwrap();
+        *settings = SyntheticSettings::new(&config, settings.idle_every);
+        if let Some((control_tx, _)) = self.worker.lock().unwrap().as_ref() {
+            control_tx.send(Control::Update(*settings)).ok();
+        }
+        Ok(())
+    }
+    fn update_content_filter(&self, _filter: SCContentFilter) -> Result<(), CaptureError> {
+        Ok(())
+    }
+}
+
+impl Drop for SyntheticBackend {
+    fn drop(&mut self) {
+        self.stop_capture().ok();
+    }
+}
+
+fn run(mut settings: SyntheticSettings, outputs: &Outputs, control_rx: &Receiver<Control>) {
+    let started_at = Instant::now();
+    let mut frame_index = 0u64;
+    let mut next_video = Duration::ZERO;
+    // Audio positions count samples from `audio_origin`, which moves when the sample rate changes.
+    let mut audio_origin = Duration::ZERO;
+    let mut audio_position = 0u64;
+    loop {
+        // An audio buffer is ready once its last sample has been "recorded".
+        let next_audio = settings.captures_audio.then(|| {
+            audio_origin
+                + samples_to_duration(
+                    audio_position + AUDIO_FRAMES_PER_BUFFER,
+                    settings.sample_rate,
+                )
+        });
+        let due = next_audio.map_or(next_video, |audio| audio.min(next_video));
+        match control_rx.recv_timeout(due.saturating_sub(started_at.elapsed())) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(Control::Update(update)) => {
+                audio_origin = if settings.captures_audio {
+                    audio_origin + samples_to_duration(audio_position, settings.sample_rate)
+                } else {
+                    started_at.elapsed()
+                };
+                audio_position = 0;
+                settings = update;
+                continue;
+            }
+            _ => break,
+        }
+        if next_video <= due {
+            deliver(outputs, SCStreamOutputType::Screen, || {
+                render_video(&settings, frame_index, next_video)
+            });
+            frame_index += 1;
+            next_video += settings.frame_interval;
+        }
+        if next_audio.is_some_and(|audio| audio <= due) {
+            deliver(outputs, SCStreamOutputType::Audio, || {
+                render_audio(&settings, audio_origin, audio_position)
+            });
+            audio_position += AUDIO_FRAMES_PER_BUFFER;
+        }
+    }
+}
+
+// Renders the sample once for all the outputs of its type, and calls them without holding the
+// lock so a slow output does not block `add_output`.
+fn deliver(
+    outputs: &Outputs,
+    of_type: SCStreamOutputType,
+    render: impl FnOnce() -> CMSampleBuffer,
+) {
+    let outputs: Vec<_> = outputs
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(_, output_type)| *output_type == of_type)
+        .map(|(output, _)| output.clone())
+        .collect();
+    let Some((last, rest)) = outputs.split_last() else {
+        return;
+    };
+    let sample = render();
+    for output in rest {
+        output.did_output_sample_buffer(sample.clone(), of_type);
+    }
+    last.did_output_sample_buffer(sample, of_type);
+}
+
+fn samples_to_duration(samples: u64, sample_rate: u32) -> Duration {
+    Duration::from_nanos((samples as u128 * 1_000_000_000 / sample_rate as u128) as u64)
+}
+
+fn render_video(
+    settings: &SyntheticSettings,
+    frame_index: u64,
+    offset: Duration,
+) -> CMSampleBuffer {
+    let frame_status = match frame_index {
+        0 => SCFrameStatus::Started,
+        n if settings.idle_every > 0 && n % settings.idle_every == 0 => SCFrameStatus::Idle,
+        _ => SCFrameStatus::Complete,
+    };
+    let pixel_buffer = (frame_status != SCFrameStatus::Idle).then(|| {
+        CVPixelBuffer::from_planes(
+            settings.pixel_format.into(),
+            render_planes(settings, frame_index),
+        )
+    });
+    CMSampleBuffer::from_pixel_buffer(pixel_buffer, frame_status, CMTime::from(offset))
+}
+
+fn aligned_bytes_per_row(bytes: usize) -> usize {
+    bytes.div_ceil(ROW_ALIGNMENT) * ROW_ALIGNMENT
+}
+
+/// The test pattern: a red/green gradient over blue with a white bar moving to the right.
+fn pattern(settings: &SyntheticSettings, frame_index: u64, x: usize, y: usize) -> [f32; 3] {
+    let bar_start = (frame_index as usize * 8) % settings.width;
+    if x >= bar_start && x < bar_start + BAR_WIDTH {
+        return [1.0, 1.0, 1.0];
+    }
+    [
+        x as f32 / settings.width as f32,
+        y as f32 / settings.height as f32,
+        0.5,
+    ]
+}
+
+fn render_planes(settings: &SyntheticSettings, frame_index: u64) -> Vec<OwnedPlane> {
+    let (width, height) = (settings.width, settings.height);
+    match settings.pixel_format {
+        PixelFormat::ARGB2101010 => {
+            let bytes_per_row = aligned_bytes_per_row(width * 4);
+            let mut data = vec![0u8; bytes_per_row * height];
+            for y in 0..height {
+                for x in 0..width {
+                    let [r, g, b] = pattern(settings, frame_index, x, y);
+                    let word = (0b11 << 30)
+                        | (((r * 1023.0) as u32) << 20)
+                        | (((g * 1023.0) as u32) << 10)
+                        | ((b * 1023.0) as u32);
+                    let offset = y * bytes_per_row + x * 4;
+                    data[offset..offset + 4].copy_from_slice(&word.to_le_bytes());
+                }
+            }
+            vec![OwnedPlane {
+                width,
+                height,
+                bytes_per_row,
+                data,
+            }]
+        }
+        PixelFormat::YCbCr420v | PixelFormat::YCbCr420f => {
+            // Video range keeps luma in 16..=235 and chroma in 16..=240.
+            let (luma_range, chroma_range) = match settings.pixel_format {
+                PixelFormat::YCbCr420v => ((16.0, 219.0), (16.0, 224.0)),
+                _ => ((0.0, 255.0), (0.0, 255.0)),
+            };
+            let quantize = |value: f32, (low, span): (f32, f32)| (low + value * span) as u8;
+
+            let luma_bytes_per_row = aligned_bytes_per_row(width);
+            let mut luma = vec![0u8; luma_bytes_per_row * height];
+            for y in 0..height {
+                for x in 0..width {
+                    let [r, g, b] = pattern(settings, frame_index, x, y);
+                    let value = 0.299 * r + 0.587 * g + 0.114 * b;
+                    luma[y * luma_bytes_per_row + x] = quantize(value, luma_range);
+                }
+            }
+
+            let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+            let chroma_bytes_per_row = aligned_bytes_per_row(chroma_width * 2);
+            let mut chroma = vec![0u8; chroma_bytes_per_row * chroma_height];
+            for y in 0..chroma_height {
+                for x in 0..chroma_width {
+                    let [r, g, b] = pattern(settings, frame_index, x * 2, y * 2);
+                    let value = 0.299 * r + 0.587 * g + 0.114 * b;
+                    let cb = 0.5 + (b - value) * 0.564;
+                    let cr = 0.5 + (r - value) * 0.713;
+                    let offset = y * chroma_bytes_per_row + x * 2;
+                    chroma[offset] = quantize(cb, chroma_range);
+                    chroma[offset + 1] = quantize(cr, chroma_range);
+                }
+            }
+
+            vec![
+                OwnedPlane {
+                    width,
+                    height,
+                    bytes_per_row: luma_bytes_per_row,
+                    data: luma,
+                },
+                OwnedPlane {
+                    width: chroma_width,
+                    height: chroma_height,
+                    bytes_per_row: chroma_bytes_per_row,
+                    data: chroma,
+                },
+            ]
+        }
+        // BGRA, which is also rendered for the formats the backend has no renderer for.
+        _ => {
+            let bytes_per_row = aligned_bytes_per_row(width * 4);
+            let mut data = vec![0u8; bytes_per_row * height];
+            for y in 0..height {
+                for x in 0..width {
+                    let [r, g, b] = pattern(settings, frame_index, x, y);
+                    let offset = y * bytes_per_row + x * 4;
+                    data[offset..offset + 4].copy_from_slice(&[
+                        (b * 255.0) as u8,
+                        (g * 255.0) as u8,
+                        (r * 255.0) as u8,
+                        255,
+                    ]);
+                }
+            }
+            vec![OwnedPlane {
+                width,
+                height,
+                bytes_per_row,
+                data,
+            }]
+        }
+    }
+}
+
+fn render_audio(settings: &SyntheticSettings, origin: Duration, position: u64) -> CMSampleBuffer {
+    let description = AudioStreamBasicDescription {
+        sample_rate: settings.sample_rate as f64,
+        format_id: kAudioFormatLinearPCM,
+        format_flags: kAudioFormatFlagIsFloat
+            | kAudioFormatFlagIsPacked
+            | kAudioFormatFlagIsNonInterleaved,
+        bytes_per_packet: 4,
+        frames_per_packet: 1,
+        bytes_per_frame: 4,
+        channels_per_frame: settings.channel_count,
+        bits_per_channel: 32,
+        reserved: 0,
+    };
+    let channel: Vec<u8> = (position..position + AUDIO_FRAMES_PER_BUFFER)
+        .flat_map(|n| {
+            let phase = 2.0 * PI * TONE_FREQUENCY * n as f64 / settings.sample_rate as f64;
+            ((TONE_AMPLITUDE * phase.sin()) as f32).to_ne_bytes()
+        })
+        .collect();
+    let buffers = (0..settings.channel_count)
+        .map(|_| CopiedAudioBuffer {
+            number_channels: 1,
+            data: channel.clone(),
+        })
+        .collect();
+    CMSampleBuffer::from_audio_buffers(
+        description,
+        buffers,
+        CMTime::from(origin + samples_to_duration(position, settings.sample_rate)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{sync_channel, SyncSender};
+
+    use super::*;
+    use crate::sc_stream::SCStream;
+
+    struct ChannelOutput {
+        tx: SyncSender<(CMSampleBuffer, SCStreamOutputType)>,
+    }
+    impl StreamOutput for ChannelOutput {
+        fn did_output_sample_buffer(&self, sample: CMSampleBuffer, of_type: SCStreamOutputType) {
+            self.tx.send((sample, of_type)).ok();
+        }
+    }
+
+    fn one_millisecond() -> CMTime {
+        CMTime::new(1, 1000)
+    }
+
+    fn capture(
+        backend: SyntheticBackend,
+        output_type: SCStreamOutputType,
+        count: usize,
+    ) -> Vec<CMSampleBuffer> {
+        let (tx, rx) = sync_channel(count);
+        let mut stream = SCStream::with_backend(backend);
+        stream.add_output(ChannelOutput { tx }, output_type);
+        stream.start_capture().expect("start");
+        let samples = (0..count)
+            .map(|_| {
+                let (sample, of_type) = rx.recv().expect("sample");
+                assert_eq!(of_type, output_type);
+                sample
+            })
+            .collect();
+        // A full channel would block the worker before it sees the stop.
+        drop(rx);
+        stream.stop_capture().expect("stop");
+        samples
+    }
+
+    #[test]
+    fn test_bgra_frames() {
+        let config = SCStreamConfiguration {
+            width: 64,
+            height: 32,
+            minimum_frame_interval: one_millisecond(),
+            ..Default::default()
+        };
+        let frames = capture(SyntheticBackend::new(config), SCStreamOutputType::Screen, 3);
+
+        assert_eq!(frames[0].frame_status, SCFrameStatus::Started);
+        assert_eq!(frames[1].frame_status, SCFrameStatus::Complete);
+        assert_eq!(frames[2].frame_status, SCFrameStatus::Complete);
+        let pixel_buffer = frames[1].pixel_buffer.as_ref().expect("pixel buffer");
+        assert!(!pixel_buffer.is_planar);
+        assert_eq!(pixel_buffer.plane_count, 0);
+        assert!(!pixel_buffer.get_base_adress().is_null());
+        assert_eq!(frames[1].presentation_timestamp.value, 1_000_000);
+        assert_eq!(frames[2].presentation_timestamp.value, 2_000_000);
+    }
+
+    #[test]
+    fn test_outputs_share_each_frame() {
+        let config = SCStreamConfiguration {
+            width: 64,
+            height: 32,
+            minimum_frame_interval: one_millisecond(),
+            ..Default::default()
+        };
+        let (first_tx, first_rx) = sync_channel(2);
+        let (second_tx, second_rx) = sync_channel(2);
+        let mut stream = SCStream::with_backend(SyntheticBackend::new(config));
+        stream.add_output(ChannelOutput { tx: first_tx }, SCStreamOutputType::Screen);
+        stream.add_output(ChannelOutput { tx: second_tx }, SCStreamOutputType::Screen);
+        stream.start_capture().expect("start");
+        for _ in 0..2 {
+            let (first, _) = first_rx.recv().expect("sample");
+            let (second, _) = second_rx.recv().expect("sample");
+            assert_eq!(
+                first.presentation_timestamp.value,
+                second.presentation_timestamp.value
+            );
+            let plane = |sample: &CMSampleBuffer| {
+                let pixel_buffer = sample.pixel_buffer.as_ref().expect("pixel buffer");
+                let guard = pixel_buffer.lock_guard().expect("lock");
+                guard.plane(0).expect("plane").get_data().to_vec()
+            };
+            assert_eq!(plane(&first), plane(&second));
+        }
+        drop((first_rx, second_rx));
+        stream.stop_capture().expect("stop");
+    }
+
+    #[test]
+    fn test_ycbcr_frames_are_bi_planar() {
+        let config = SCStreamConfiguration {
+            width: 64,
+            height: 32,
+            pixel_format: PixelFormat::YCbCr420v,
+            minimum_frame_interval: one_millisecond(),
+            ..Default::default()
+        };
+        let frames = capture(SyntheticBackend::new(config), SCStreamOutputType::Screen, 1);
+
+        let pixel_buffer = frames[0].pixel_buffer.as_ref().expect("pixel buffer");
+        assert!(pixel_buffer.is_planar);
+        assert_eq!(pixel_buffer.plane_count, 2);
+        assert!(!pixel_buffer.get_base_adress_of_plane(1).is_null());
+        assert!(pixel_buffer.get_base_adress_of_plane(2).is_null());
+
+        let backend = SyntheticBackend::new(SCStreamConfiguration {
+            width: 64,
+            height: 32,
+            pixel_format: PixelFormat::YCbCr420v,
+            ..Default::default()
+        });
+        let planes = render_planes(&backend.settings.lock().unwrap(), 1);
+        assert_eq!((planes[1].width, planes[1].height), (32, 16));
+        assert!(planes[0].data.iter().all(|&y| (16..=235).contains(&y)));
+    }
+
+    #[test]
+    fn test_idle_frames() {
+        let config = SCStreamConfiguration {
+            width: 16,
+            height: 16,
+            minimum_frame_interval: one_millisecond(),
+            ..Default::default()
+        };
+        let backend = SyntheticBackend::new(config).with_idle_frames_every(2);
+        let frames = capture(backend, SCStreamOutputType::Screen, 4);
+
+        let statuses: Vec<SCFrameStatus> = frames.iter().map(|f| f.frame_status).collect();
+        assert_eq!(
+            statuses,
+            [
+                SCFrameStatus::Started,
+                SCFrameStatus::Complete,
+                SCFrameStatus::Idle,
+                SCFrameStatus::Complete
+            ]
+        );
+        assert!(frames[2].pixel_buffer.is_none());
+    }
+
+    #[test]
+    fn test_audio_buffers() {
+        let config = SCStreamConfiguration {
+            captures_audio: true,
+            sample_rate: 48000,
+            channel_count: 2,
+            minimum_frame_interval: one_millisecond(),
+            ..Default::default()
+        };
+        let samples = capture(SyntheticBackend::new(config), SCStreamOutputType::Audio, 2);
+
+        let description = samples[0].audio_description.expect("audio description");
+        assert_eq!(description.sample_rate, 48000.0);
+        assert_eq!(description.channels_per_frame, 2);
+        assert_ne!(
+            description.format_flags & kAudioFormatFlagIsNonInterleaved,
+            0
+        );
+        let buffers = samples[0].get_audio_buffers();
+        assert_eq!(buffers.len(), 2);
+        assert_eq!(buffers[0].data.len(), AUDIO_FRAMES_PER_BUFFER as usize * 4);
+        assert_eq!(samples[0].presentation_timestamp.value, 0);
+        assert_eq!(
+            samples[1].presentation_timestamp.value,
+            samples_to_duration(AUDIO_FRAMES_PER_BUFFER, 48000).as_nanos() as i64
+        );
+    }
+
+    #[test]
+    fn test_start_and_stop_errors() {
+        let stream = SCStream::with_backend(SyntheticBackend::new(Default::default()));
+        assert!(stream.stop_capture().is_err());
+        assert!(stream.start_capture().is_ok());
+        assert!(stream.start_capture().is_err());
+        assert!(stream.stop_capture().is_ok());
+        assert!(stream.stop_capture().is_err());
+    }
+
+    #[test]
+    fn test_update_configuration_while_capturing() {
+        let config = SCStreamConfiguration {
+            width: 16,
+            height: 16,
+            minimum_frame_interval: one_millisecond(),
+            ..Default::default()
+        };
+        let (tx, rx) = sync_channel(4);
+        let mut stream = SCStream::with_backend(SyntheticBackend::new(config));
+        stream.add_output(ChannelOutput { tx }, SCStreamOutputType::Screen);
+        stream.start_capture().expect("start");
+        let width = |sample: &CMSampleBuffer| sample.pixel_buffer.as_ref().unwrap().get_width();
+        assert_eq!(width(&rx.recv().unwrap().0), 16);
+
+        let update = SCStreamConfiguration {
+            width: 32,
+            height: 8,
+            minimum_frame_interval: one_millisecond(),
+            ..Default::default()
+        };
+        stream.update_configuration(update).expect("update");
+        // Frames rendered before the update may still be queued.
+        let updated = (0..8)
+            .map(|_| rx.recv().unwrap().0)
+            .find(|sample| width(sample) == 32)
+            .expect("a frame with the new size");
+        assert_eq!(updated.pixel_buffer.as_ref().unwrap().get_height(), 8);
+        assert_ne!(updated.frame_status, SCFrameStatus::Started);
+        drop(rx);
+        stream.stop_capture().expect("stop");
+    }
+}
@@ -0,0 +1,595 @@
+//! Recording of the sample buffers a stream delivers, and replaying them later.
+//!
+//! A session file starts with the magic bytes `SCKSESS\0` and a little-endian `u32` format
+//! version, followed by one record per sample buffer. All integers are little-endian:
+//!
+//! | Field                | Encoding                                                  |
+//! |----------------------|-----------------------------------------------------------|
+//! | output type          | `u8`, 0 for screen and 1 for audio                        |
+//! | presentation time    | `CMTime` as `i64` value, `i32` timescale, `u32` flags, `i64` epoch |
+//! | frame status         | `i32` raw `SCFrameStatus`                                 |
+//! | payload kind         | `u8`, 0 for none, 1 for pixels, 2 for audio               |
+//! | pixels               | `u32` pixel format, `u32` plane count, then per plane `u32` width, `u32` height, `u32` bytes per row, `u64` length and the bytes |
+//! | audio                | `AudioStreamBasicDescription` as `f64` and eight `u32`, `u32` buffer count, then per buffer `u32` channels, `u64` length and the bytes |
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use screencapturekit_sys::{
+    audio_buffer::CopiedAudioBuffer,
+    cm_format_description_ref::AudioStreamBasicDescription,
+    os_types::{base::CMTime, four_char_code::FourCharCode},
+    sc_stream_frame_info::SCFrameStatus,
+};
+
+use crate::{
+    cm_sample_buffer::CMSampleBuffer,
+    cv_pixel_buffer::{CVPixelBuffer, OwnedPlane},
+    sc_output_handler::{SCStreamOutputType, StreamOutput},
+};
+
+const MAGIC: &[u8; 8] = b"SCKSESS\0";
+const VERSION: u32 = 1;
+
+const PAYLOAD_NONE: u8 = 0;
+const PAYLOAD_PIXELS: u8 = 1;
+const PAYLOAD_AUDIO: u8 = 2;
+
+#[derive(Debug, Clone)]
+pub enum RecordedPayload {
+    None,
+    Pixels {
+        pixel_format: FourCharCode,
+        planes: Vec<OwnedPlane>,
+    },
+    Audio {
+        description: AudioStreamBasicDescription,
+        buffers: Vec<CopiedAudioBuffer>,
+    },
+}
+
+/// The data of a single sample buffer, as stored in a session file.
+#[derive(Debug, Clone)]
+pub struct RecordedSample {
+    pub output_type: SCStreamOutputType,
+    pub presentation_timestamp: CMTime,
+    pub frame_status: SCFrameStatus,
+    pub payload: RecordedPayload,
+}
+
+impl RecordedSample {
+    /// Copies the pixels or audio out of `sample`.
+    pub fn from_sample_buffer(sample: &CMSampleBuffer, output_type: SCStreamOutputType) -> Self {
+        let payload = if let Some(description) = sample.audio_description {
+            RecordedPayload::Audio {
+                description,
+                buffers: sample.get_audio_buffers(),
+            }
+        } else if let Some(pixel_buffer) = &sample.pixel_buffer {
+            match pixel_buffer.copy_planes() {
+                Some(planes) => RecordedPayload::Pixels {
+                    pixel_format: pixel_buffer.get_pixel_format(),
+                    planes,
+                },
+                None => RecordedPayload::None,
+            }
+        } else {
+            RecordedPayload::None
+        };
+        Self {
+            output_type,
+            presentation_timestamp: sample.presentation_timestamp,
+            frame_status: sample.frame_status,
+            payload,
+        }
+    }
+
+    pub fn into_sample_buffer(self) -> CMSampleBuffer {
+        match self.payload {
+            RecordedPayload::Audio {
+                description,
+                buffers,
+            } => {
+                let mut sample = CMSampleBuffer::from_audio_buffers(
+                    description,
+                    buffers,
+                    self.presentation_timestamp,
+                );
+                sample.frame_status = self.frame_status;
+                sample
+            }
+            RecordedPayload::Pixels {
+                pixel_format,
+                planes,
+            } => CMSampleBuffer::from_pixel_buffer(
+                Some(CVPixelBuffer::from_planes(pixel_format, planes)),
+                self.frame_status,
+                self.presentation_timestamp,
+            ),
+            RecordedPayload::None => CMSampleBuffer::from_pixel_buffer(
+                None,
+                self.frame_status,
+                self.presentation_timestamp,
+            ),
+        }
+    }
+}
+
+struct RecorderState<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+/// Writes every sample buffer it receives to a session file.
+///
+/// Add a clone of the recorder to a stream as an output for each type to record, and call
+/// [`SessionRecorder::finish`] on another clone after stopping the capture.
+pub struct SessionRecorder<W: Write> {
+    state: Arc<Mutex<RecorderState<W>>>,
+}
+
+impl<W: Write> Clone for SessionRecorder<W> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl SessionRecorder<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> SessionRecorder<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        write_u32(&mut writer, VERSION)?;
+        Ok(Self {
+            state: Arc::new(Mutex::new(RecorderState {
+                writer,
+                error: None,
+            })),
+        })
+    }
+
+    pub fn record(
+        &self,
+        sample: &CMSampleBuffer,
+        output_type: SCStreamOutputType,
+    ) -> io::Result<()> {
+        self.write(&RecordedSample::from_sample_buffer(sample, output_type))
+    }
+
+    pub fn write(&self, sample: &RecordedSample) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        write_sample(&mut state.writer, sample)
+    }
+
+    /// Flushes the session file and returns the first error that occurred while recording
+    /// from a stream callback.
+    pub fn finish(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(error) = state.error.take() {
+            return Err(error);
+        }
+        state.writer.flush()
+    }
+}
+
+impl<W: Write + Send + 'static> StreamOutput for SessionRecorder<W> {
+    fn did_output_sample_buffer(&self, sample_buffer: CMSampleBuffer, of_type: SCStreamOutputType) {
+        let sample = RecordedSample::from_sample_buffer(&sample_buffer, of_type);
+        let mut state = self.state.lock().unwrap();
+        if state.error.is_none() {
+            state.error = write_sample(&mut state.writer, &sample).err();
+        }
+    }
+}
+
+/// Reads the samples of a session file in the order they were recorded.
+pub struct SessionReader<R: Read> {
+    reader: R,
+}
+
+impl SessionReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> SessionReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a capture session file"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported capture session version {version}"
+            )));
+        }
+        Ok(Self { reader })
+    }
+
+    /// Returns the next sample, or `None` at the end of the file.
+    pub fn read_sample(&mut self) -> io::Result<Option<RecordedSample>> {
+        let mut output_type = [0u8; 1];
+        if self.reader.read(&mut output_type)? == 0 {
+            return Ok(None);
+        }
+        read_sample(&mut self.reader, output_type[0]).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for SessionReader<R> {
+    type Item = io::Result<RecordedSample>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_sample().transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayTiming {
+    /// Delivers samples with the gaps between their presentation timestamps.
+    Original,
+    /// Delivers samples with the gaps between their presentation timestamps divided by the factor.
+    Accelerated(f64),
+    /// Delivers samples as fast as `output` consumes them.
+    Immediate,
+}
+
+/// Delivers every sample of a session to `output` and returns how many samples were replayed.
+pub fn replay<R: Read>(
+    session: SessionReader<R>,
+    output: &impl StreamOutput,
+    timing: ReplayTiming,
+) -> io::Result<usize> {
+    let speed = match timing {
+        ReplayTiming::Original => Some(1.0),
+        ReplayTiming::Accelerated(factor) if factor > 0.0 => Some(factor),
+        ReplayTiming::Accelerated(_) | ReplayTiming::Immediate => None,
+    };
+    let started_at = Instant::now();
+    let mut first_timestamp = None;
+    let mut count = 0;
+    for sample in session {
+        let sample = sample?;
+        if let (Some(speed), Some(seconds)) = (speed, seconds(&sample.presentation_timestamp)) {
+            let offset = seconds - *first_timestamp.get_or_insert(seconds);
+            let due = Duration::from_secs_f64((offset / speed).max(0.0));
+            thread::sleep(due.saturating_sub(started_at.elapsed()));
+        }
+        let output_type = sample.output_type;
+        output.did_output_sample_buffer(sample.into_sample_buffer(), output_type);
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn seconds(time: &CMTime) -> Option<f64> {
+    time.is_numeric().then(|| time.get_seconds())
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.into())
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn write_sample(writer: &mut impl Write, sample: &RecordedSample) -> io::Result<()> {
+    writer.write_all(&[match sample.output_type {
+        SCStreamOutputType::Screen => 0,
+        SCStreamOutputType::Audio => 1,
+    }])?;
+    let time = &sample.presentation_timestamp;
+    writer.write_all(&time.value.to_le_bytes())?;
+    writer.write_all(&time.timescale.to_le_bytes())?;
+    write_u32(writer, time.flags)?;
+    writer.write_all(&time.epoch.to_le_bytes())?;
+    writer.write_all(&(sample.frame_status as i32).to_le_bytes())?;
+    match &sample.payload {
+        RecordedPayload::None => writer.write_all(&[PAYLOAD_NONE]),
+        RecordedPayload::Pixels {
+            pixel_format,
+            planes,
+        } => {
+            writer.write_all(&[PAYLOAD_PIXELS])?;
+            write_u32(writer, pixel_format.into_int())?;
+            write_u32(writer, planes.len() as u32)?;
+            for plane in planes {
+                write_u32(writer, plane.width as u32)?;
+                write_u32(writer, plane.height as u32)?;
+                write_u32(writer, plane.bytes_per_row as u32)?;
+                write_bytes(writer, &plane.data)?;
+            }
+            Ok(())
+        }
+        RecordedPayload::Audio {
+            description,
+            buffers,
+        } => {
+            writer.write_all(&[PAYLOAD_AUDIO])?;
+            writer.write_all(&description.sample_rate.to_le_bytes())?;
+            for field in [
+                description.format_id,
+                description.format_flags,
+                description.bytes_per_packet,
+                description.frames_per_packet,
+                description.bytes_per_frame,
+                description.channels_per_frame,
+                description.bits_per_channel,
+                description.reserved,
+            ] {
+                write_u32(writer, field)?;
+            }
+            write_u32(writer, buffers.len() as u32)?;
+            for buffer in buffers {
+                write_u32(writer, buffer.number_channels)?;
+                write_bytes(writer, &buffer.data)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    read_array(reader).map(u32::from_le_bytes)
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = u64::from_le_bytes(read_array(reader)?);
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn read_sample(reader: &mut impl Read, output_type: u8) -> io::Result<RecordedSample> {
+    let output_type = match output_type {
+        0 => SCStreamOutputType::Screen,
+        1 => SCStreamOutputType::Audio,
+        other => return Err(invalid_data(format!("unknown output type {other}"))),
+    };
+    let presentation_timestamp = CMTime {
+        value: i64::from_le_bytes(read_array(reader)?),
+        timescale: i32::from_le_bytes(read_array(reader)?),
+        flags: read_u32(reader)?,
+        epoch: i64::from_le_bytes(read_array(reader)?),
+    };
+    let frame_status = SCFrameStatus::try_from(i32::from_le_bytes(read_array(reader)?))
+        .map_err(|status| invalid_data(format!("unknown frame status {status}")))?;
+    let payload = match read_array::<1>(reader)?[0] {
+        PAYLOAD_NONE => RecordedPayload::None,
+        PAYLOAD_PIXELS => {
+            let pixel_format = FourCharCode::from_int(read_u32(reader)?);
+            let plane_count = read_u32(reader)?;
+            let planes = (0..plane_count)
+                .map(|_| {
+                    Ok(OwnedPlane {
+                        width: read_u32(reader)? as usize,
+                        height: read_u32(reader)? as usize,
+                        bytes_per_row: read_u32(reader)? as usize,
+                        data: read_bytes(reader)?,
+                    })
+                })
+                .collect::<io::Result<_>>()?;
+            RecordedPayload::Pixels {
+                pixel_format,
+                planes,
+            }
+        }
+        PAYLOAD_AUDIO => {
+            let description = AudioStreamBasicDescription {
+                sample_rate: f64::from_le_bytes(read_array(reader)?),
+                format_id: read_u32(reader)?,
+                format_flags: read_u32(reader)?,
+                bytes_per_packet: read_u32(reader)?,
+                frames_per_packet: read_u32(reader)?,
+                bytes_per_frame: read_u32(reader)?,
+                channels_per_frame: read_u32(reader)?,
+                bits_per_channel: read_u32(reader)?,
+                reserved: read_u32(reader)?,
+            };
+            let buffer_count = read_u32(reader)?;
+            let buffers = (0..buffer_count)
+                .map(|_| {
+                    Ok(CopiedAudioBuffer {
+                        number_channels: read_u32(reader)?,
+                        data: read_bytes(reader)?,
+                    })
+                })
+                .collect::<io::Result<_>>()?;
+            RecordedPayload::Audio {
+                description,
+                buffers,
+            }
+        }
+        other => return Err(invalid_data(format!("unknown payload kind {other}"))),
+    };
+    Ok(RecordedSample {
+        output_type,
+        presentation_timestamp,
+        frame_status,
+        payload,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, sync::mpsc::sync_channel, sync::mpsc::SyncSender};
+
+    use super::*;
+
+    struct ChannelOutput {
+        tx: SyncSender<(CMSampleBuffer, SCStreamOutputType)>,
+    }
+    impl StreamOutput for ChannelOutput {
+        fn did_output_sample_buffer(&self, sample: CMSampleBuffer, of_type: SCStreamOutputType) {
+            self.tx.send((sample, of_type)).ok();
+        }
+    }
+
+    fn time(millis: i64) -> CMTime {
+        CMTime::new(millis, 1000)
+    }
+
+    fn video_sample(millis: i64) -> CMSampleBuffer {
+        let planes = vec![
+            OwnedPlane {
+                width: 2,
+                height: 2,
+                bytes_per_row: 4,
+                data: vec![16, 17, 0, 0, 18, 19, 0, 0],
+            },
+            OwnedPlane {
+                width: 1,
+                height: 1,
+                bytes_per_row: 2,
+                data: vec![128, 129],
+            },
+        ];
+        CMSampleBuffer::from_pixel_buffer(
+            Some(CVPixelBuffer::from_planes(
+                FourCharCode::from_chars(*b"420v"),
+                planes,
+            )),
+            SCFrameStatus::Complete,
+            time(millis),
+        )
+    }
+
+    fn audio_sample(millis: i64) -> CMSampleBuffer {
+        let description = AudioStreamBasicDescription {
+            sample_rate: 48000.0,
+            channels_per_frame: 2,
+            bits_per_channel: 32,
+            ..Default::default()
+        };
+        let buffers = vec![
+            CopiedAudioBuffer {
+                number_channels: 1,
+                data: vec![1, 2, 3, 4],
+            },
+            CopiedAudioBuffer {
+                number_channels: 1,
+                data: vec![5, 6, 7, 8],
+            },
+        ];
+        CMSampleBuffer::from_audio_buffers(description, buffers, time(millis))
+    }
+
+    fn record(samples: Vec<(CMSampleBuffer, SCStreamOutputType)>) -> Vec<u8> {
+        let recorder = SessionRecorder::new(Vec::new()).unwrap();
+        for (sample, of_type) in samples {
+            recorder.did_output_sample_buffer(sample, of_type);
+        }
+        recorder.finish().unwrap();
+        let state = Arc::try_unwrap(recorder.state).ok().unwrap();
+        state.into_inner().unwrap().writer
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let idle = CMSampleBuffer::from_pixel_buffer(None, SCFrameStatus::Idle, time(20));
+        let bytes = record(vec![
+            (video_sample(0), SCStreamOutputType::Screen),
+            (audio_sample(10), SCStreamOutputType::Audio),
+            (idle, SCStreamOutputType::Screen),
+        ]);
+
+        let samples = SessionReader::new(Cursor::new(bytes))
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(samples.len(), 3);
+
+        let video = samples[0].clone().into_sample_buffer();
+        assert_eq!(video.frame_status, SCFrameStatus::Complete);
+        assert_eq!(video.presentation_timestamp.value, 0);
+        let pixel_buffer = video.pixel_buffer.unwrap();
+        assert_eq!(pixel_buffer.get_pixel_format().to_string(), "420v");
+        let planes = pixel_buffer.copy_planes().unwrap();
+        assert_eq!(planes[0].data, [16, 17, 0, 0, 18, 19, 0, 0]);
+        assert_eq!((planes[1].width, planes[1].bytes_per_row), (1, 2));
+
+        assert_eq!(samples[1].output_type, SCStreamOutputType::Audio);
+        let audio = samples[1].clone().into_sample_buffer();
+        assert_eq!(audio.audio_description.unwrap().channels_per_frame, 2);
+        let buffers = audio.get_audio_buffers();
+        assert_eq!(buffers[1].data, [5, 6, 7, 8]);
+
+        assert_eq!(samples[2].frame_status, SCFrameStatus::Idle);
+        assert!(matches!(samples[2].payload, RecordedPayload::None));
+    }
+
+    #[test]
+    fn test_replay() {
+        let bytes = record(vec![
+            (video_sample(0), SCStreamOutputType::Screen),
+            (video_sample(100), SCStreamOutputType::Screen),
+            (audio_sample(200), SCStreamOutputType::Audio),
+        ]);
+        let (tx, rx) = sync_channel(3);
+        let started_at = Instant::now();
+        let count = replay(
+            SessionReader::new(Cursor::new(bytes)).unwrap(),
+            &ChannelOutput { tx },
+            ReplayTiming::Accelerated(10.0),
+        )
+        .unwrap();
+
+        assert_eq!(count, 3);
+        assert!(started_at.elapsed() >= Duration::from_millis(20));
+        let types: Vec<SCStreamOutputType> = rx.try_iter().map(|(_, of_type)| of_type).collect();
+        assert_eq!(
+            types,
+            [
+                SCStreamOutputType::Screen,
+                SCStreamOutputType::Screen,
+                SCStreamOutputType::Audio
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let error = SessionReader::new(Cursor::new(b"RIFF\0\0\0\0\x01\0\0\0".to_vec()))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let mut bytes = record(vec![(video_sample(0), SCStreamOutputType::Screen)]);
+        bytes.truncate(bytes.len() - 1);
+        let mut reader = SessionReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            reader.read_sample().err().unwrap().kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -1,35 +1,109 @@
+use screencapturekit_sys::{
+    audio_buffer::CopiedAudioBuffer, cm_format_description_ref::AudioStreamBasicDescription,
+    os_types::base::CMTime, sc_stream_frame_info::SCFrameStatus,
+};
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::{
     cm_sample_buffer_ref::CMSampleBufferRef,
     cv_image_buffer_ref::CVImageBufferRef,
     os_types::rc::{Id, ShareId},
-    sc_stream_frame_info::SCFrameStatus,
 };
 
 use crate::cv_pixel_buffer::CVPixelBuffer;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CMSampleBuffer {
-    pub sys_ref: Id<CMSampleBufferRef>,
+    #[cfg(target_os = "macos")]
+    sys_ref: Option<ShareId<CMSampleBufferRef>>,
+    #[cfg(target_os = "macos")]
     pub image_buf_ref: Option<ShareId<CVImageBufferRef>>,
     pub pixel_buffer: Option<CVPixelBuffer>,
     pub frame_status: SCFrameStatus,
+    pub presentation_timestamp: CMTime,
+    pub audio_description: Option<AudioStreamBasicDescription>,
+    audio_buffers: Vec<CopiedAudioBuffer>,
 }
 
 impl CMSampleBuffer {
+    #[cfg(target_os = "macos")]
     pub fn new(sys_ref: Id<CMSampleBufferRef>) -> Self {
         let frame_status = sys_ref
             .get_frame_info()
             .map(|info| info.status())
             .unwrap_or(SCFrameStatus::Idle);
+        let presentation_timestamp = sys_ref.get_presentation_timestamp();
+        let audio_description = sys_ref.get_format_description().and_then(|description| {
+            description
+                .audio_format_description_get_stream_basic_description()
+                .copied()
+        });
         let image_buf_ref = sys_ref.get_image_buffer();
         let pixel_buffer = image_buf_ref
             .as_ref()
             .map(|i| CVPixelBuffer::new(i.clone().as_pixel_buffer()));
         Self {
-            sys_ref,
+            sys_ref: Some(sys_ref.share()),
             pixel_buffer,
             image_buf_ref,
             frame_status,
+            presentation_timestamp,
+            audio_description,
+            audio_buffers: Vec::new(),
+        }
+    }
+
+    /// The native sample buffer, `None` for samples that were not produced by ScreenCaptureKit.
+    #[cfg(target_os = "macos")]
+    pub fn get_sys_ref(&self) -> Option<&CMSampleBufferRef> {
+        self.sys_ref.as_deref()
+    }
+
+    /// Creates a video sample that is not backed by a native `CMSampleBuffer`.
+    pub fn from_pixel_buffer(
+        pixel_buffer: Option<CVPixelBuffer>,
+        frame_status: SCFrameStatus,
+        presentation_timestamp: CMTime,
+    ) -> Self {
+        Self {
+            #[cfg(target_os = "macos")]
+            sys_ref: None,
+            #[cfg(target_os = "macos")]
+            image_buf_ref: None,
+            pixel_buffer,
+            frame_status,
+            presentation_timestamp,
+            audio_description: None,
+            audio_buffers: Vec::new(),
+        }
+    }
+
+    /// Creates an audio sample that is not backed by a native `CMSampleBuffer`.
+    pub fn from_audio_buffers(
+        audio_description: AudioStreamBasicDescription,
+        audio_buffers: Vec<CopiedAudioBuffer>,
+        presentation_timestamp: CMTime,
+    ) -> Self {
+        Self {
+            #[cfg(target_os = "macos")]
+            sys_ref: None,
+            #[cfg(target_os = "macos")]
+            image_buf_ref: None,
+            pixel_buffer: None,
+            frame_status: SCFrameStatus::Complete,
+            presentation_timestamp,
+            audio_description: Some(audio_description),
+            audio_buffers,
+        }
+    }
+
+    /// Returns a copy of the audio buffers of this sample, empty for video samples.
+    pub fn get_audio_buffers(&self) -> Vec<CopiedAudioBuffer> {
+        #[cfg(target_os = "macos")]
+        if let Some(sys_ref) = &self.sys_ref {
+            if self.audio_description.is_some() {
+                return sys_ref.get_av_audio_buffer_list();
+            }
         }
+        self.audio_buffers.clone()
     }
 }
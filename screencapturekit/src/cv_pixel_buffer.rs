@@ -1,34 +1,385 @@
 use std::ffi::c_void;
 
+use screencapturekit_sys::os_types::four_char_code::FourCharCode;
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::{cv_pixel_buffer_ref::CVPixelBufferRef, os_types::rc::ShareId};
 
+/// A single plane of pixel data that lives in ordinary Rust memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedPlane {
+    pub width: usize,
+    pub height: usize,
+    pub bytes_per_row: usize,
+    pub data: Vec<u8>,
+}
+
+impl OwnedPlane {
+    /// Fails like [`PlaneView::new`] if `data` is shorter than `height` rows of `bytes_per_row`.
+    pub fn as_view(&self, pixel_format: FourCharCode) -> Result<PlaneView<'_>, String> {
+        PlaneView::new(
+            pixel_format,
+            self.width,
+            self.height,
+            self.bytes_per_row,
+            &self.data,
+        )
+    }
+}
+
+/// A borrowed plane of pixel data, from a locked [`CVPixelBuffer`] or from any other memory.
+///
+/// Rows are `bytes_per_row` apart, which can be more than the bytes of `width` pixels, and
+/// the data always holds `height` rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaneView<'a> {
+    pub(crate) pixel_format: FourCharCode,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) bytes_per_row: usize,
+    pub(crate) data: &'a [u8],
+}
+
+impl<'a> PlaneView<'a> {
+    /// Fails if `data` is shorter than `height` rows of `bytes_per_row`.
+    pub fn new(
+        pixel_format: FourCharCode,
+        width: usize,
+        height: usize,
+        bytes_per_row: usize,
+        data: &'a [u8],
+    ) -> Result<Self, String> {
+        if data.len() < bytes_per_row * height {
+            return Err(format!(
+                "A plane of {height} rows of {bytes_per_row} bytes needs {} bytes, got {}",
+                bytes_per_row * height,
+                data.len()
+            ));
+        }
+        Ok(Self {
+            pixel_format,
+            width,
+            height,
+            bytes_per_row,
+            data,
+        })
+    }
+    pub fn get_pixel_format(&self) -> FourCharCode {
+        self.pixel_format
+    }
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+    pub fn get_bytes_per_row(&self) -> usize {
+        self.bytes_per_row
+    }
+    pub fn get_data(&self) -> &'a [u8] {
+        self.data
+    }
+    /// Returns the bytes of row `y`, including any padding at its end.
+    pub fn row(&self, y: usize) -> Option<&'a [u8]> {
+        if y >= self.height {
+            return None;
+        }
+        let start = y * self.bytes_per_row;
+        self.data.get(start..start + self.bytes_per_row)
+    }
+    pub fn rows(&self) -> impl Iterator<Item = &'a [u8]> {
+        let view = *self;
+        (0..self.height).map_while(move |y| view.row(y))
+    }
+    pub fn to_owned_plane(&self) -> OwnedPlane {
+        OwnedPlane {
+            width: self.width,
+            height: self.height,
+            bytes_per_row: self.bytes_per_row,
+            data: self.data[..self.bytes_per_row * self.height].to_vec(),
+        }
+    }
+}
+
+/// Keeps the base address of a [`CVPixelBuffer`] locked until it is dropped.
 #[derive(Debug)]
+pub struct CVPixelBufferLockGuard<'a> {
+    buffer: &'a CVPixelBuffer,
+}
+
+impl CVPixelBufferLockGuard<'_> {
+    pub fn get_pixel_format(&self) -> FourCharCode {
+        self.buffer.get_pixel_format()
+    }
+    pub fn get_width(&self) -> usize {
+        self.buffer.get_width()
+    }
+    pub fn get_height(&self) -> usize {
+        self.buffer.get_height()
+    }
+    /// The number of planes, 1 for non-planar buffers.
+    pub fn plane_count(&self) -> usize {
+        if self.buffer.is_planar {
+            self.buffer.plane_count as usize
+        } else {
+            1
+        }
+    }
+    pub fn plane(&self, plane_index: usize) -> Option<PlaneView<'_>> {
+        if plane_index >= self.plane_count() {
+            return None;
+        }
+        let pixel_format = self.get_pixel_format();
+        match &self.buffer.storage {
+            #[cfg(target_os = "macos")]
+            PixelBufferStorage::Native(unsafe_ref) => {
+                let (width, height, bytes_per_row, base_address) = if self.buffer.is_planar {
+                    let i = plane_index as u64;
+                    (
+                        unsafe_ref.get_width_of_plane(i),
+                        unsafe_ref.get_height_of_plane(i),
+                        unsafe_ref.get_bytes_per_row_of_plane(i),
+                        unsafe_ref.get_base_address_of_plane(i),
+                    )
+                } else {
+                    (
+                        unsafe_ref.get_width(),
+                        unsafe_ref.get_height(),
+                        unsafe_ref.get_bytes_per_row(),
+                        unsafe_ref.get_base_address(),
+                    )
+                };
+                if base_address.is_null() {
+                    return None;
+                }
+                let (width, height, bytes_per_row) =
+                    (width as usize, height as usize, bytes_per_row as usize);
+                // The base address stays valid while this guard keeps the buffer locked.
+                let data = unsafe {
+                    std::slice::from_raw_parts(base_address as *const u8, bytes_per_row * height)
+                };
+                Some(PlaneView {
+                    pixel_format,
+                    width,
+                    height,
+                    bytes_per_row,
+                    data,
+                })
+            }
+            PixelBufferStorage::Owned { planes, .. } => planes
+                .get(plane_index)
+                .and_then(|plane| plane.as_view(pixel_format).ok()),
+        }
+    }
+    pub fn planes(&self) -> Vec<PlaneView<'_>> {
+        (0..self.plane_count())
+            .filter_map(|i| self.plane(i))
+            .collect()
+    }
+}
+
+impl Drop for CVPixelBufferLockGuard<'_> {
+    fn drop(&mut self) {
+        self.buffer.unlock();
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PixelBufferStorage {
+    #[cfg(target_os = "macos")]
+    Native(ShareId<CVPixelBufferRef>),
+    Owned {
+        pixel_format: FourCharCode,
+        planes: Vec<OwnedPlane>,
+    },
+}
+
+#[derive(Debug, Clone)]
 pub struct CVPixelBuffer {
     pub is_planar: bool,
     pub plane_count: u64,
-    unsafe_ref: ShareId<CVPixelBufferRef>,
+    storage: PixelBufferStorage,
 }
 
 impl CVPixelBuffer {
+    #[cfg(target_os = "macos")]
     pub fn new(unsafe_ref: ShareId<CVPixelBufferRef>) -> Self {
         let is_planar = unsafe_ref.is_planar();
         let plane_count = unsafe_ref.plane_count();
         Self {
-            unsafe_ref,
+            storage: PixelBufferStorage::Native(unsafe_ref),
+            plane_count,
+            is_planar,
+        }
+    }
+    /// Creates a pixel buffer backed by owned memory instead of a CoreVideo buffer.
+    ///
+    /// A single plane is treated as a chunky (non-planar) buffer, which like CoreVideo
+    /// reports a `plane_count` of 0.
+    pub fn from_planes(pixel_format: FourCharCode, planes: Vec<OwnedPlane>) -> Self {
+        let is_planar = planes.len() > 1;
+        let plane_count = if is_planar { planes.len() as u64 } else { 0 };
+        Self {
+            storage: PixelBufferStorage::Owned {
+                pixel_format,
+                planes,
+            },
             plane_count,
             is_planar,
         }
     }
-    pub fn lock(&self) -> bool {
-        self.unsafe_ref.lock_base_address(0) == 0
+    // Only a lock guard locks and unlocks, so the base address stays valid while its plane
+    // views are alive.
+    pub(crate) fn lock(&self) -> bool {
+        match &self.storage {
+            #[cfg(target_os = "macos")]
+            PixelBufferStorage::Native(unsafe_ref) => unsafe_ref.lock_base_address(0) == 0,
+            PixelBufferStorage::Owned { .. } => true,
+        }
     }
-    pub fn unlock(&self) -> bool {
-        self.unsafe_ref.unlock_base_address(0) == 0
+    pub(crate) fn unlock(&self) -> bool {
+        match &self.storage {
+            #[cfg(target_os = "macos")]
+            PixelBufferStorage::Native(unsafe_ref) => unsafe_ref.unlock_base_address(0) == 0,
+            PixelBufferStorage::Owned { .. } => true,
+        }
+    }
+    pub fn get_pixel_format(&self) -> FourCharCode {
+        match &self.storage {
+            #[cfg(target_os = "macos")]
+            PixelBufferStorage::Native(unsafe_ref) => unsafe_ref.get_pixel_format_type(),
+            PixelBufferStorage::Owned { pixel_format, .. } => *pixel_format,
+        }
     }
     pub fn get_base_adress(&self) -> *mut c_void {
-        self.unsafe_ref.get_base_address()
+        match &self.storage {
+            #[cfg(target_os = "macos")]
+            PixelBufferStorage::Native(unsafe_ref) => unsafe_ref.get_base_address(),
+            PixelBufferStorage::Owned { planes, .. } => planes
+                .first()
+                .map_or(std::ptr::null_mut(), |p| p.data.as_ptr() as *mut c_void),
+        }
     }
     pub fn get_base_adress_of_plane(&self, plane_index: u64) -> *mut c_void {
-        self.unsafe_ref.get_base_address_of_plane(plane_index)
+        match &self.storage {
+            #[cfg(target_os = "macos")]
+            PixelBufferStorage::Native(unsafe_ref) => {
+                unsafe_ref.get_base_address_of_plane(plane_index)
+            }
+            PixelBufferStorage::Owned { planes, .. } => planes
+                .get(plane_index as usize)
+                .map_or(std::ptr::null_mut(), |p| p.data.as_ptr() as *mut c_void),
+        }
+    }
+    pub fn get_width(&self) -> usize {
+        match &self.storage {
+            #[cfg(target_os = "macos")]
+            PixelBufferStorage::Native(unsafe_ref) => unsafe_ref.get_width() as usize,
+            PixelBufferStorage::Owned { planes, .. } => planes.first().map_or(0, |p| p.width),
+        }
+    }
+    pub fn get_height(&self) -> usize {
+        match &self.storage {
+            #[cfg(target_os = "macos")]
+            PixelBufferStorage::Native(unsafe_ref) => unsafe_ref.get_height() as usize,
+            PixelBufferStorage::Owned { planes, .. } => planes.first().map_or(0, |p| p.height),
+        }
+    }
+    /// Locks the base address until the returned guard is dropped.
+    pub fn lock_guard(&self) -> Result<CVPixelBufferLockGuard<'_>, String> {
+        if self.lock() {
+            Ok(CVPixelBufferLockGuard { buffer: self })
+        } else {
+            Err("Could not lock the base address of the pixel buffer".to_string())
+        }
+    }
+    /// Copies the pixel data out of the buffer, one entry per plane, or a single entry for
+    /// non-planar buffers. Returns `None` if the base address could not be locked.
+    pub fn copy_planes(&self) -> Option<Vec<OwnedPlane>> {
+        let guard = self.lock_guard().ok()?;
+        let planes = guard.planes();
+        Some(planes.iter().map(PlaneView::to_owned_plane).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bi_planar_buffer() -> CVPixelBuffer {
+        CVPixelBuffer::from_planes(
+            FourCharCode::from_chars(*b"420v"),
+            vec![
+                OwnedPlane {
+                    width: 2,
+                    height: 2,
+                    bytes_per_row: 4,
+                    data: vec![16, 17, 0, 0, 18, 19, 0, 0],
+                },
+                OwnedPlane {
+                    width: 1,
+                    height: 1,
+                    bytes_per_row: 2,
+                    data: vec![128, 129],
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn test_lock_guard_planes() {
+        let buffer = bi_planar_buffer();
+        let guard = buffer.lock_guard().unwrap();
+        assert_eq!((guard.get_width(), guard.get_height()), (2, 2));
+        assert_eq!(guard.plane_count(), 2);
+
+        let luma = guard.plane(0).unwrap();
+        assert_eq!(luma.pixel_format.to_string(), "420v");
+        assert_eq!(luma.bytes_per_row, 4);
+        let rows: Vec<&[u8]> = luma.rows().map(|row| &row[..luma.width]).collect();
+        assert_eq!(rows, [[16, 17], [18, 19]]);
+
+        let chroma = guard.plane(1).unwrap();
+        assert_eq!(chroma.row(0), Some(&[128, 129][..]));
+        assert_eq!(chroma.row(1), None);
+        assert!(guard.plane(2).is_none());
+    }
+
+    #[test]
+    fn test_single_plane_buffer() {
+        let buffer = CVPixelBuffer::from_planes(
+            FourCharCode::from_chars(*b"BGRA"),
+            vec![OwnedPlane {
+                width: 1,
+                height: 1,
+                bytes_per_row: 4,
+                data: vec![1, 2, 3, 255],
+            }],
+        );
+        assert!(!buffer.is_planar);
+        let guard = buffer.lock_guard().unwrap();
+        assert_eq!(guard.plane_count(), 1);
+        assert_eq!(guard.planes()[0].data, [1, 2, 3, 255]);
+        drop(guard);
+        assert_eq!(buffer.copy_planes().unwrap()[0].data, [1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn test_plane_view_from_memory() {
+        let data = [0u8; 12];
+        let format = FourCharCode::from_chars(*b"BGRA");
+        let view = PlaneView::new(format, 1, 3, 4, &data).unwrap();
+        assert_eq!(view.rows().count(), 3);
+        assert_eq!(view.to_owned_plane().data.len(), 12);
+        assert!(PlaneView::new(format, 1, 4, 4, &data).is_err());
+
+        let short = OwnedPlane {
+            width: 1,
+            height: 4,
+            bytes_per_row: 4,
+            data: data.to_vec(),
+        };
+        assert!(short.as_view(format).is_err());
+        let buffer = CVPixelBuffer::from_planes(format, vec![short]);
+        assert!(buffer.lock_guard().unwrap().plane(0).is_none());
     }
 }
@@ -0,0 +1,168 @@
+use screencapturekit_sys::{os_types::base::CMTime, sc_stream_frame_info::SCFrameStatus};
+
+use crate::{
+    cm_sample_buffer::CMSampleBuffer,
+    cv_pixel_buffer::{OwnedPlane, PlaneView},
+    sc_stream_configuration::PixelFormat,
+};
+
+/// A video frame whose pixels were copied out of the sample buffer.
+///
+/// Unlike [`CMSampleBuffer`], a frame holds on to no ScreenCaptureKit surface, so it can be
+/// kept or sent to another thread without stalling the capture queue.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub pixel_format: PixelFormat,
+    pub width: usize,
+    pub height: usize,
+    /// One plane for BGRA and l10r frames, a luma and a chroma plane for the 420 formats.
+    pub planes: Vec<OwnedPlane>,
+    pub presentation_timestamp: CMTime,
+    pub frame_status: SCFrameStatus,
+}
+
+impl Frame {
+    /// Creates a complete frame with the size of its first plane.
+    pub fn new(
+        pixel_format: PixelFormat,
+        planes: Vec<OwnedPlane>,
+        presentation_timestamp: CMTime,
+    ) -> Self {
+        let (width, height) = planes.first().map_or((0, 0), |p| (p.width, p.height));
+        Self {
+            pixel_format,
+            width,
+            height,
+            planes,
+            presentation_timestamp,
+            frame_status: SCFrameStatus::Complete,
+        }
+    }
+    pub fn plane(&self, plane_index: usize) -> Option<PlaneView<'_>> {
+        let pixel_format = self.pixel_format.into();
+        self.planes
+            .get(plane_index)
+            .and_then(|plane| plane.as_view(pixel_format).ok())
+    }
+    pub fn planes(&self) -> Vec<PlaneView<'_>> {
+        let pixel_format = self.pixel_format.into();
+        self.planes
+            .iter()
+            .filter_map(|plane| plane.as_view(pixel_format).ok())
+            .collect()
+    }
+}
+
+impl TryFrom<&CMSampleBuffer> for Frame {
+    type Error = String;
+
+    /// Copies the pixels of a video sample, failing for samples without a pixel buffer, such as
+    /// idle frames and audio. Pixel formats without a variant become [`PixelFormat::Other`].
+    fn try_from(sample: &CMSampleBuffer) -> Result<Self, Self::Error> {
+        let pixel_buffer = sample
+            .pixel_buffer
+            .as_ref()
+            .ok_or_else(|| "The sample buffer has no pixel buffer".to_string())?;
+        let pixel_format = PixelFormat::from_code(pixel_buffer.get_pixel_format());
+        let planes = pixel_buffer
+            .copy_planes()
+            .ok_or_else(|| "Could not lock the base address of the pixel buffer".to_string())?;
+        Ok(Self {
+            pixel_format,
+            width: pixel_buffer.get_width(),
+            height: pixel_buffer.get_height(),
+            planes,
+            presentation_timestamp: sample.presentation_timestamp,
+            frame_status: sample.frame_status,
+        })
+    }
+}
+
+impl TryFrom<CMSampleBuffer> for Frame {
+    type Error = String;
+
+    fn try_from(sample: CMSampleBuffer) -> Result<Self, Self::Error> {
+        Frame::try_from(&sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc::channel, thread};
+
+    use screencapturekit_sys::os_types::four_char_code::FourCharCode;
+
+    use crate::cv_pixel_buffer::CVPixelBuffer;
+
+    use super::*;
+
+    fn plane(width: usize, height: usize, bytes_per_row: usize) -> OwnedPlane {
+        OwnedPlane {
+            width,
+            height,
+            bytes_per_row,
+            data: (0..bytes_per_row * height).map(|i| i as u8).collect(),
+        }
+    }
+
+    #[test]
+    fn test_from_sample_buffer() {
+        let timestamp = CMTime {
+            value: 5,
+            timescale: 60,
+            ..Default::default()
+        };
+        let sample = CMSampleBuffer::from_pixel_buffer(
+            Some(CVPixelBuffer::from_planes(
+                PixelFormat::YCbCr420f.into(),
+                vec![plane(4, 2, 8), plane(2, 1, 8)],
+            )),
+            SCFrameStatus::Started,
+            timestamp,
+        );
+        let frame = Frame::try_from(sample).unwrap();
+        assert_eq!(frame.pixel_format, PixelFormat::YCbCr420f);
+        assert_eq!((frame.width, frame.height), (4, 2));
+        assert_eq!(frame.frame_status, SCFrameStatus::Started);
+        assert_eq!(frame.presentation_timestamp.value, 5);
+        assert_eq!(frame.planes[1], plane(2, 1, 8));
+        assert_eq!(frame.plane(0).unwrap().row(1).unwrap()[0], 8);
+    }
+
+    #[test]
+    fn test_samples_without_pixels_and_unknown_formats() {
+        let idle = CMSampleBuffer::from_pixel_buffer(None, SCFrameStatus::Idle, CMTime::default());
+        assert!(Frame::try_from(&idle).is_err());
+
+        let unknown = CMSampleBuffer::from_pixel_buffer(
+            Some(CVPixelBuffer::from_planes(
+                FourCharCode::from_chars(*b"2vuy"),
+                vec![plane(2, 2, 4)],
+            )),
+            SCFrameStatus::Complete,
+            CMTime::default(),
+        );
+        let frame = Frame::try_from(&unknown).unwrap();
+        assert_eq!(
+            frame.pixel_format,
+            PixelFormat::Other(FourCharCode::from_chars(*b"2vuy"))
+        );
+        assert_eq!(frame.planes[0], plane(2, 2, 4));
+    }
+
+    #[test]
+    fn test_send_to_another_thread() {
+        let frame = Frame::new(
+            PixelFormat::ARGB8888,
+            vec![plane(2, 2, 8)],
+            CMTime::default(),
+        );
+        let (tx, rx) = channel();
+        thread::spawn(move || tx.send(frame).unwrap())
+            .join()
+            .unwrap();
+        let frame = rx.recv().unwrap();
+        assert_eq!((frame.width, frame.height), (2, 2));
+        assert_eq!(frame.planes().len(), 1);
+    }
+}
@@ -0,0 +1,673 @@
+//! PNG and JPEG encoding of captured pixels in plain Rust.
+//!
+//! The encoders read `BGRA`, `420v`, `420f` and `l10r` planes one row at a time, straight from a
+//! locked [`CVPixelBuffer`](crate::cv_pixel_buffer::CVPixelBuffer) or a
+//! [`Frame`](crate::frame::Frame):
+//!
+//! ```no_run
+//! # use screencapturekit::{frame::Frame, image_encoding::{encode_png, PngOptions}};
+//! # fn save(frame: &Frame) -> Result<(), String> {
+//! let file = std::fs::File::create("screenshot.png").map_err(|e| e.to_string())?;
+//! encode_png(&frame.planes(), &PngOptions::default(), std::io::BufWriter::new(file))
+//! # }
+//! ```
+
+use std::io::Write;
+
+use jpeg_encoder::{rgb_to_ycbcr, ImageBuffer, JpegColorType, SamplingFactor};
+
+pub use crate::sc_stream_configuration::ChromaSubsampling;
+use crate::{
+    cv_pixel_buffer::PlaneView,
+    pixel_conversion::{
+        check_l10r, check_ycbcr420, l10r_to_rgb, l10r_to_rgb16, ycbcr420_to_rgb, Dither, RgbLayout,
+        YCbCrMatrix, YCbCrRange,
+    },
+};
+
+/// How hard the PNG encoder compresses the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+impl From<PngCompression> for png::Compression {
+    fn from(compression: PngCompression) -> Self {
+        match compression {
+            PngCompression::Fast => png::Compression::Fast,
+            PngCompression::Default => png::Compression::Default,
+            PngCompression::Best => png::Compression::Best,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PngOptions {
+    pub compression: PngCompression,
+    /// The matrix of `420v` and `420f` planes.
+    pub matrix: YCbCrMatrix,
+}
+
+impl From<ChromaSubsampling> for SamplingFactor {
+    fn from(subsampling: ChromaSubsampling) -> Self {
+        match subsampling {
+            ChromaSubsampling::Yuv444 => SamplingFactor::R_4_4_4,
+            ChromaSubsampling::Yuv422 => SamplingFactor::R_4_2_2,
+            ChromaSubsampling::Yuv420 => SamplingFactor::R_4_2_0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegOptions {
+    /// From 1 to 100.
+    pub quality: u8,
+    pub chroma_subsampling: ChromaSubsampling,
+    /// The matrix of `420v` and `420f` planes.
+    pub matrix: YCbCrMatrix,
+}
+
+impl Default for JpegOptions {
+    fn default() -> Self {
+        Self {
+            quality: 90,
+            chroma_subsampling: ChromaSubsampling::default(),
+            matrix: YCbCrMatrix::default(),
+        }
+    }
+}
+
+/// The planes of an image in one of the supported pixel formats.
+enum Source<'a> {
+    Bgra(PlaneView<'a>),
+    YCbCr {
+        luma: PlaneView<'a>,
+        chroma: PlaneView<'a>,
+        range: YCbCrRange,
+    },
+    L10r(PlaneView<'a>),
+}
+
+impl<'a> Source<'a> {
+    fn new(planes: &[PlaneView<'a>]) -> Result<Self, String> {
+        let first = planes
+            .first()
+            .copied()
+            .ok_or_else(|| "An image needs at least one plane".to_string())?;
+        let source = match &first.pixel_format.into_chars() {
+            b"BGRA" => {
+                if first.bytes_per_row < first.width * 4
+                    || first.data.len() < first.bytes_per_row * first.height
+                {
+                    return Err("The BGRA plane is smaller than its width and height".to_string());
+                }
+                Source::Bgra(first)
+            }
+            b"l10r" => {
+                check_l10r(&first)?;
+                Source::L10r(first)
+            }
+            _ => {
+                let range = YCbCrRange::from_pixel_format(first.pixel_format)
+                    .ok_or_else(|| format!("Can't encode {} planes", first.pixel_format))?;
+                let chroma = planes
+                    .get(1)
+                    .copied()
+                    .ok_or_else(|| "A YCbCr image needs a chroma plane".to_string())?;
+                check_ycbcr420(&first, &chroma)?;
+                Source::YCbCr {
+                    luma: first,
+                    chroma,
+                    range,
+                }
+            }
+        };
+        Ok(source)
+    }
+    fn size(&self) -> (usize, usize) {
+        match self {
+            Source::Bgra(plane) | Source::L10r(plane) | Source::YCbCr { luma: plane, .. } => {
+                (plane.width, plane.height)
+            }
+        }
+    }
+    /// Converts row `y` to 8-bit samples.
+    fn rgb_row(&self, y: usize, matrix: YCbCrMatrix, layout: RgbLayout, row: &mut [u8]) {
+        let converted = match self {
+            Source::Bgra(plane) => {
+                let source = &plane.data[y * plane.bytes_per_row..][..plane.width * 4];
+                for (pixel, bgra) in row
+                    .chunks_exact_mut(layout.bytes_per_pixel())
+                    .zip(source.chunks_exact(4))
+                {
+                    layout.write(pixel, bgra[2], bgra[1], bgra[0], bgra[3]);
+                }
+                Ok(())
+            }
+            Source::YCbCr {
+                luma,
+                chroma,
+                range,
+            } => ycbcr420_to_rgb(
+                &row_view(luma, y),
+                &row_view(chroma, y / 2),
+                *range,
+                matrix,
+                layout,
+                row,
+                row.len(),
+            ),
+            Source::L10r(plane) => {
+                l10r_to_rgb(&row_view(plane, y), layout, Dither::None, row, row.len())
+            }
+        };
+        converted.expect("the planes and the row were checked to be large enough");
+    }
+}
+
+/// Returns row `y` of a checked plane as a plane of its own.
+fn row_view<'a>(plane: &PlaneView<'a>, y: usize) -> PlaneView<'a> {
+    PlaneView {
+        height: 1,
+        data: &plane.data[y * plane.bytes_per_row..][..plane.bytes_per_row],
+        ..*plane
+    }
+}
+
+/// Encodes the planes of an image losslessly as PNG.
+///
+/// BGRA planes keep their alpha, `420v` and `420f` planes are converted to RGB and `l10r`
+/// planes to 16-bit RGBA.
+pub fn encode_png(
+    planes: &[PlaneView],
+    options: &PngOptions,
+    writer: impl Write,
+) -> Result<(), String> {
+    let source = Source::new(planes)?;
+    let (width, height) = source.size();
+    let (color, depth, channels, bytes_per_sample) = match source {
+        Source::Bgra(_) => (png::ColorType::Rgba, png::BitDepth::Eight, 4, 1),
+        Source::YCbCr { .. } => (png::ColorType::Rgb, png::BitDepth::Eight, 3, 1),
+        Source::L10r(_) => (png::ColorType::Rgba, png::BitDepth::Sixteen, 4, 2),
+    };
+    let (Ok(png_width), Ok(png_height)) = (u32::try_from(width), u32::try_from(height)) else {
+        return Err(format!("A {width}x{height} image is too large for PNG"));
+    };
+
+    let png_error = |e: png::EncodingError| format!("Could not encode the PNG: {e}");
+    let mut encoder = png::Encoder::new(writer, png_width, png_height);
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    encoder.set_compression(options.compression.into());
+    let mut png_writer = encoder.write_header().map_err(png_error)?;
+    let mut stream = png_writer.stream_writer().map_err(png_error)?;
+    let mut row = vec![0u8; width * channels * bytes_per_sample];
+    let mut samples = vec![0u16; width * channels];
+    for y in 0..height {
+        if let Source::L10r(plane) = &source {
+            l10r_to_rgb16(
+                &row_view(plane, y),
+                RgbLayout::Rgba,
+                &mut samples,
+                width * 4,
+            )?;
+            // PNG stores 16-bit samples big-endian
+            for (bytes, sample) in row.chunks_exact_mut(2).zip(&samples) {
+                bytes.copy_from_slice(&sample.to_be_bytes());
+            }
+        } else {
+            let layout = if channels == 4 {
+                RgbLayout::Rgba
+            } else {
+                RgbLayout::Rgb
+            };
+            source.rgb_row(y, options.matrix, layout, &mut row);
+        }
+        stream
+            .write_all(&row)
+            .map_err(|e| format!("Could not encode the PNG: {e}"))?;
+    }
+    stream.finish().map_err(png_error)?;
+    png_writer.finish().map_err(png_error)
+}
+
+const FIXED_SHIFT: u32 = 16;
+const FIXED_HALF: i32 = 1 << (FIXED_SHIFT - 1);
+
+/// Re-expresses YCbCr samples in the full range BT.601 YCbCr of JFIF, as a 16.16 fixed-point
+/// matrix of the offset samples. Full range BT.601 samples are kept as they are.
+struct JfifTransform {
+    luma_offset: i32,
+    rows: [[i32; 3]; 3],
+}
+
+impl JfifTransform {
+    fn new(range: YCbCrRange, matrix: YCbCrMatrix) -> Self {
+        let (luma_offset, luma_scale, chroma_scale) = match range {
+            YCbCrRange::Video => (16, 255.0 / 219.0, 255.0 / 224.0),
+            YCbCrRange::Full => (0, 1.0, 1.0),
+        };
+        let (kr, kb) = matrix.coefficients();
+        let kg = 1.0 - kr - kb;
+        let to_rgb = [
+            [luma_scale, 0.0, chroma_scale * 2.0 * (1.0 - kr)],
+            [
+                luma_scale,
+                -chroma_scale * 2.0 * kb * (1.0 - kb) / kg,
+                -chroma_scale * 2.0 * kr * (1.0 - kr) / kg,
+            ],
+            [luma_scale, chroma_scale * 2.0 * (1.0 - kb), 0.0],
+        ];
+        let (kr, kb) = YCbCrMatrix::Bt601.coefficients();
+        let kg = 1.0 - kr - kb;
+        let from_rgb = [
+            [kr, kg, kb],
+            [-kr / (2.0 * (1.0 - kb)), -kg / (2.0 * (1.0 - kb)), 0.5],
+            [0.5, -kg / (2.0 * (1.0 - kr)), -kb / (2.0 * (1.0 - kr))],
+        ];
+        let fixed = |value: f64| (value * f64::from(1 << FIXED_SHIFT)).round() as i32;
+        let mut rows = [[0; 3]; 3];
+        for (row, from) in rows.iter_mut().zip(from_rgb) {
+            for (column, value) in row.iter_mut().enumerate() {
+                *value = fixed((0..3).map(|k| from[k] * to_rgb[k][column]).sum());
+            }
+        }
+        Self { luma_offset, rows }
+    }
+    #[inline]
+    fn convert(&self, y: u8, cb: u8, cr: u8) -> [u8; 3] {
+        let input = [
+            i32::from(y) - self.luma_offset,
+            i32::from(cb) - 128,
+            i32::from(cr) - 128,
+        ];
+        let mut output = [0, 128, 128];
+        for (sample, row) in output.iter_mut().zip(&self.rows) {
+            let value = row.iter().zip(input).map(|(m, v)| m * v).sum::<i32>() + FIXED_HALF;
+            *sample = ((value >> FIXED_SHIFT) + *sample).clamp(0, 255);
+        }
+        output.map(|sample| sample as u8)
+    }
+}
+
+/// Feeds the rows of a [`Source`] to the JPEG encoder.
+///
+/// YCbCr planes are passed on as YCbCr, converting only the range and the matrix, so they are
+/// not converted to RGB and back.
+struct JpegImage<'a> {
+    source: Source<'a>,
+    matrix: YCbCrMatrix,
+    transform: Option<JfifTransform>,
+    width: u16,
+    height: u16,
+}
+
+impl ImageBuffer for JpegImage<'_> {
+    fn get_jpeg_color_type(&self) -> JpegColorType {
+        JpegColorType::Ycbcr
+    }
+    fn width(&self) -> u16 {
+        self.width
+    }
+    fn height(&self) -> u16 {
+        self.height
+    }
+    fn fill_buffers(&self, y: u16, buffers: &mut [Vec<u8>; 4]) {
+        if let (Source::YCbCr { luma, chroma, .. }, Some(transform)) =
+            (&self.source, &self.transform)
+        {
+            let y = usize::from(y);
+            let luma = &luma.data[y * luma.bytes_per_row..][..usize::from(self.width)];
+            let chroma = &chroma.data[y / 2 * chroma.bytes_per_row..];
+            // The chroma of each 2x2 block is repeated, and the encoder subsamples it again.
+            for (x, &luma) in luma.iter().enumerate() {
+                let [y, cb, cr] = transform.convert(luma, chroma[x / 2 * 2], chroma[x / 2 * 2 + 1]);
+                buffers[0].push(y);
+                buffers[1].push(cb);
+                buffers[2].push(cr);
+            }
+            return;
+        }
+        let mut row = vec![0u8; usize::from(self.width) * 3];
+        self.source
+            .rgb_row(usize::from(y), self.matrix, RgbLayout::Rgb, &mut row);
+        for rgb in row.chunks_exact(3) {
+            let (y, cb, cr) = rgb_to_ycbcr(rgb[0], rgb[1], rgb[2]);
+            buffers[0].push(y);
+            buffers[1].push(cb);
+            buffers[2].push(cr);
+        }
+    }
+}
+
+/// Encodes the planes of an image as JPEG, dropping any alpha.
+///
+/// `420v` and `420f` planes are encoded from their YCbCr samples, converted to the full range
+/// BT.601 YCbCr of JPEG, and `l10r` planes are rounded to 8 bits per sample.
+pub fn encode_jpeg(
+    planes: &[PlaneView],
+    options: &JpegOptions,
+    writer: impl Write,
+) -> Result<(), String> {
+    if !(1..=100).contains(&options.quality) {
+        return Err(format!(
+            "The JPEG quality must be from 1 to 100, got {}",
+            options.quality
+        ));
+    }
+    let source = Source::new(planes)?;
+    let (width, height) = source.size();
+    let (Ok(jpeg_width), Ok(jpeg_height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(format!("A {width}x{height} image is too large for JPEG"));
+    };
+    if width == 0 || height == 0 {
+        return Err("Can't encode an empty image as JPEG".to_string());
+    }
+
+    let transform = match source {
+        Source::YCbCr { range, .. } => Some(JfifTransform::new(range, options.matrix)),
+        _ => None,
+    };
+    let mut encoder = jpeg_encoder::Encoder::new(writer, options.quality);
+    encoder.set_sampling_factor(options.chroma_subsampling.into());
+    encoder
+        .encode_image(JpegImage {
+            source,
+            matrix: options.matrix,
+            transform,
+            width: jpeg_width,
+            height: jpeg_height,
+        })
+        .map_err(|e| format!("Could not encode the JPEG: {e}"))
+}
+
+/// Encodes the planes of an image as PNG in memory.
+pub fn png_bytes(planes: &[PlaneView], options: &PngOptions) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    encode_png(planes, options, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Encodes the planes of an image as JPEG in memory.
+pub fn jpeg_bytes(planes: &[PlaneView], options: &JpegOptions) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    encode_jpeg(planes, options, &mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use screencapturekit_sys::os_types::{base::CMTime, four_char_code::FourCharCode};
+
+    use super::*;
+    use crate::{
+        cv_pixel_buffer::OwnedPlane, frame::Frame, pixel_conversion::frame_to_rgb,
+        sc_stream_configuration::PixelFormat,
+    };
+
+    fn decode_png(bytes: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+        let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        data.truncate(info.buffer_size());
+        (info, data)
+    }
+
+    /// The horizontal and vertical sampling factors of the components of a baseline JPEG.
+    fn jpeg_sampling_factors(bytes: &[u8]) -> Vec<u8> {
+        let start = bytes
+            .windows(2)
+            .position(|marker| marker == [0xff, 0xc0])
+            .unwrap();
+        let components = usize::from(bytes[start + 9]);
+        (0..components)
+            .map(|i| bytes[start + 10 + i * 3 + 1])
+            .collect()
+    }
+
+    fn bgra_plane(width: usize, height: usize, bytes_per_row: usize, pixel: [u8; 4]) -> OwnedPlane {
+        let mut data = vec![0xee; bytes_per_row * height];
+        for row in data.chunks_exact_mut(bytes_per_row) {
+            for target in row[..width * 4].chunks_exact_mut(4) {
+                target.copy_from_slice(&pixel);
+            }
+        }
+        OwnedPlane {
+            width,
+            height,
+            bytes_per_row,
+            data,
+        }
+    }
+
+    #[test]
+    fn test_bgra_png() {
+        let plane = bgra_plane(3, 2, 16, [10, 20, 30, 40]);
+        let format = FourCharCode::from_chars(*b"BGRA");
+        let bytes = png_bytes(&[plane.as_view(format).unwrap()], &PngOptions::default()).unwrap();
+        let (info, data) = decode_png(&bytes);
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(data, [30, 20, 10, 40].repeat(6));
+    }
+
+    #[test]
+    fn test_ycbcr_png_is_lossless() {
+        let luma = OwnedPlane {
+            width: 4,
+            height: 3,
+            bytes_per_row: 6,
+            data: (0..18).map(|i| 16 + i * 12).collect(),
+        };
+        let chroma = OwnedPlane {
+            width: 2,
+            height: 2,
+            bytes_per_row: 4,
+            data: vec![60, 200, 128, 128, 220, 40, 90, 90],
+        };
+        let frame = Frame::new(
+            PixelFormat::YCbCr420v,
+            vec![luma, chroma],
+            CMTime::default(),
+        );
+        let options = PngOptions {
+            compression: PngCompression::Best,
+            matrix: YCbCrMatrix::Bt601,
+        };
+        let (info, data) = decode_png(&png_bytes(&frame.planes(), &options).unwrap());
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        let rgb = frame_to_rgb(&frame, YCbCrMatrix::Bt601, RgbLayout::Rgb).unwrap();
+        assert_eq!(data, rgb.data);
+    }
+
+    #[test]
+    fn test_l10r_png() {
+        let mut data = vec![0; 8];
+        data[..4].copy_from_slice(&0xbff8_0001u32.to_le_bytes());
+        data[4..].copy_from_slice(&0x3fff_ffffu32.to_le_bytes());
+        let plane = OwnedPlane {
+            width: 2,
+            height: 1,
+            bytes_per_row: 8,
+            data,
+        };
+        let format = PixelFormat::ARGB2101010.into();
+        let bytes = png_bytes(&[plane.as_view(format).unwrap()], &PngOptions::default()).unwrap();
+        let (info, data) = decode_png(&bytes);
+        assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+        let samples: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .collect();
+        assert_eq!(
+            samples,
+            [0xffff, 0x8020, 0x0040, 0xaaaa, 0xffff, 0xffff, 0xffff, 0]
+        );
+    }
+
+    #[test]
+    fn test_jpeg() {
+        let plane = bgra_plane(16, 16, 64, [200, 100, 50, 255]);
+        let planes = [plane.as_view(FourCharCode::from_chars(*b"BGRA")).unwrap()];
+        for (subsampling, luma_factors) in [
+            (ChromaSubsampling::Yuv444, 0x11),
+            (ChromaSubsampling::Yuv422, 0x21),
+            (ChromaSubsampling::Yuv420, 0x22),
+        ] {
+            let options = JpegOptions {
+                quality: 95,
+                chroma_subsampling: subsampling,
+                ..Default::default()
+            };
+            let bytes = jpeg_bytes(&planes, &options).unwrap();
+            assert_eq!(jpeg_sampling_factors(&bytes), [luma_factors, 0x11, 0x11]);
+
+            let mut decoder = jpeg_decoder::Decoder::new(bytes.as_slice());
+            let pixels = decoder.decode().unwrap();
+            let info = decoder.info().unwrap();
+            assert_eq!((info.width, info.height), (16, 16));
+            for rgb in pixels.chunks_exact(3) {
+                for (actual, expected) in rgb.iter().zip([50u8, 100, 200]) {
+                    assert!(actual.abs_diff(expected) <= 3, "{rgb:?}");
+                }
+            }
+        }
+
+        let low = JpegOptions {
+            quality: 10,
+            ..Default::default()
+        };
+        let high = JpegOptions {
+            quality: 100,
+            ..Default::default()
+        };
+        let gradient = OwnedPlane {
+            data: (0..64 * 16).map(|i| (i * 7 % 256) as u8).collect(),
+            ..plane
+        };
+        let planes = [gradient
+            .as_view(FourCharCode::from_chars(*b"BGRA"))
+            .unwrap()];
+        assert!(
+            jpeg_bytes(&planes, &low).unwrap().len() < jpeg_bytes(&planes, &high).unwrap().len()
+        );
+    }
+
+    fn ycbcr_frame(pixel_format: PixelFormat, luma: Vec<u8>, chroma: Vec<u8>) -> Frame {
+        let luma = OwnedPlane {
+            width: 4,
+            height: 2,
+            bytes_per_row: 4,
+            data: luma,
+        };
+        let chroma = OwnedPlane {
+            width: 2,
+            height: 1,
+            bytes_per_row: 4,
+            data: chroma,
+        };
+        Frame::new(pixel_format, vec![luma, chroma], CMTime::default())
+    }
+
+    fn jpeg_samples(frame: &Frame, matrix: YCbCrMatrix) -> [Vec<u8>; 4] {
+        let source = Source::new(&frame.planes()).unwrap();
+        let Source::YCbCr { range, .. } = source else {
+            panic!("not a YCbCr source");
+        };
+        let image = JpegImage {
+            source,
+            matrix,
+            transform: Some(JfifTransform::new(range, matrix)),
+            width: 4,
+            height: 2,
+        };
+        let mut buffers = Default::default();
+        image.fill_buffers(1, &mut buffers);
+        buffers
+    }
+
+    #[test]
+    fn test_ycbcr_jpeg_samples() {
+        let luma: Vec<u8> = vec![0, 50, 100, 150, 16, 128, 235, 255];
+        let chroma = vec![60, 200, 240, 16];
+        let full = ycbcr_frame(PixelFormat::YCbCr420f, luma.clone(), chroma.clone());
+        let samples = jpeg_samples(&full, YCbCrMatrix::Bt601);
+        assert_eq!(samples[0], luma[4..]);
+        assert_eq!(samples[1], [60, 60, 240, 240]);
+        assert_eq!(samples[2], [200, 200, 16, 16]);
+
+        let video = ycbcr_frame(PixelFormat::YCbCr420v, luma, chroma);
+        let samples = jpeg_samples(&video, YCbCrMatrix::Bt601);
+        assert_eq!(samples[0], [0, 130, 255, 255]);
+        assert_eq!(samples[1][2..], [255, 255]);
+        assert_eq!(samples[2][2..], [0, 0]);
+
+        // Gray has no chroma in any matrix.
+        let gray = ycbcr_frame(PixelFormat::YCbCr420f, vec![90; 8], vec![128; 4]);
+        let samples = jpeg_samples(&gray, YCbCrMatrix::Bt709);
+        assert_eq!(samples[..3], [vec![90; 4], vec![128; 4], vec![128; 4]]);
+    }
+
+    #[test]
+    fn test_ycbcr_jpeg_colors() {
+        let luma = OwnedPlane {
+            width: 16,
+            height: 16,
+            bytes_per_row: 16,
+            data: vec![120; 256],
+        };
+        let chroma = OwnedPlane {
+            width: 8,
+            height: 8,
+            bytes_per_row: 16,
+            data: [90, 180].repeat(64),
+        };
+        let frame = Frame::new(
+            PixelFormat::YCbCr420v,
+            vec![luma, chroma],
+            CMTime::default(),
+        );
+        let rgb = frame_to_rgb(&frame, YCbCrMatrix::Bt709, RgbLayout::Rgb).unwrap();
+        let options = JpegOptions {
+            quality: 100,
+            matrix: YCbCrMatrix::Bt709,
+            ..Default::default()
+        };
+        let bytes = jpeg_bytes(&frame.planes(), &options).unwrap();
+        let pixels = jpeg_decoder::Decoder::new(bytes.as_slice())
+            .decode()
+            .unwrap();
+        for (actual, expected) in pixels.iter().zip(&rgb.data) {
+            assert!(actual.abs_diff(*expected) <= 2, "{actual} {expected}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_images() {
+        let options = JpegOptions::default();
+        assert!(jpeg_bytes(&[], &options).is_err());
+
+        let plane = bgra_plane(2, 2, 8, [0; 4]);
+        let unknown = [plane.as_view(FourCharCode::from_chars(*b"2vuy")).unwrap()];
+        assert!(png_bytes(&unknown, &PngOptions::default()).is_err());
+
+        let bgra = [plane.as_view(FourCharCode::from_chars(*b"BGRA")).unwrap()];
+        let no_quality = JpegOptions {
+            quality: 0,
+            ..options
+        };
+        assert!(jpeg_bytes(&bgra, &no_quality).is_err());
+
+        let luma_only = [plane.as_view(FourCharCode::from_chars(*b"420f")).unwrap()];
+        assert!(jpeg_bytes(&luma_only, &options).is_err());
+
+        let short = PlaneView {
+            height: 3,
+            ..bgra[0]
+        };
+        assert!(png_bytes(&[short], &PngOptions::default()).is_err());
+    }
+}
@@ -1,5 +1,18 @@
+pub mod audio_conversion;
+pub mod audio_frame;
+pub mod audio_ring_buffer;
+pub mod capture_backend;
+pub mod capture_session;
 pub mod cm_sample_buffer;
 pub mod cv_pixel_buffer;
+pub mod frame;
+#[cfg(feature = "image")]
+pub mod image_encoding;
+pub mod metering;
+pub mod output_geometry;
+pub mod pixel_conversion;
+pub mod platform;
+pub mod resampler;
 pub mod sc_content_filter;
 pub mod sc_display;
 pub mod sc_error_handler;
@@ -10,3 +23,6 @@ pub mod sc_stream;
 pub mod sc_stream_configuration;
 pub mod sc_types;
 pub mod sc_window;
+pub mod shareable_content_provider;
+pub mod voice_activity;
+pub mod wav_writer;
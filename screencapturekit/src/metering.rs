@@ -0,0 +1,554 @@
+//! Level and loudness metering of captured audio.
+//!
+//! An [`AudioMeter`] reports the peak, RMS and true peak of each channel of every frame it is
+//! given, and the momentary, short-term and integrated loudness of the stream as measured by
+//! ITU-R BS.1770-4 and EBU R128.
+
+use std::{collections::VecDeque, f64::consts::PI};
+
+use screencapturekit_sys::os_types::base::CMTime;
+
+use crate::audio_frame::AudioFrame;
+
+/// The length of the sub-blocks that loudness is summed over, in seconds.
+const SUB_BLOCK_SECONDS: f64 = 0.1;
+/// The number of sub-blocks in the momentary window of 400 ms, which is also the gating block.
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+/// The number of sub-blocks in the short-term window of 3 s.
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+/// Blocks quieter than this are left out of the integrated loudness.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Blocks this much quieter than the ungated loudness are left out of the integrated loudness.
+const RELATIVE_GATE_LU: f64 = -10.0;
+/// The number of taps of each phase of the true-peak interpolation filter.
+const TRUE_PEAK_TAPS_PER_PHASE: usize = 12;
+
+/// Converts a linear amplitude to decibels relative to full scale.
+pub fn amplitude_to_dbfs(amplitude: f32) -> f32 {
+    20.0 * amplitude.log10()
+}
+
+/// Returns the loudness of a weighted mean square.
+fn loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// The levels of one channel of a frame, as linear amplitudes where 1.0 is full scale.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ChannelLevels {
+    /// The largest absolute sample.
+    pub peak: f32,
+    pub rms: f32,
+    /// The largest absolute value of the signal between the samples, estimated by oversampling.
+    pub true_peak: f32,
+}
+
+/// The levels of one frame and the loudness of the stream up to the end of it.
+///
+/// Loudness is in LUFS. The momentary and short-term loudness are `None` until 400 ms and 3 s
+/// of audio have been measured, and are updated every 100 ms. The integrated loudness is `None`
+/// as long as every block is below the absolute gate of -70 LUFS.
+#[derive(Debug, Clone)]
+pub struct MeterReading {
+    /// The presentation timestamp of the frame.
+    pub presentation_timestamp: CMTime,
+    pub channels: Vec<ChannelLevels>,
+    pub momentary_loudness: Option<f64>,
+    pub short_term_loudness: Option<f64>,
+    pub integrated_loudness: Option<f64>,
+}
+
+/// A biquad filter in transposed direct form II.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            state: [0.0; 2],
+        }
+    }
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.state[0];
+        self.state[0] = self.b[1] * input - self.a[0] * output + self.state[1];
+        self.state[1] = self.b[2] * input - self.a[1] * output;
+        output
+    }
+}
+
+/// Returns the high shelf and high pass filters of the K-weighting curve at `sample_rate`.
+///
+/// The analog prototypes are fitted to the 48 kHz coefficients of BS.1770, so that other
+/// sample rates get the same curve.
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    let k = (PI * 1681.974450955533 / sample_rate).tan();
+    let q = 0.7071752369554196;
+    let high_gain = 10f64.powf(3.999843853973347 / 20.0);
+    let band_gain = high_gain.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (high_gain + band_gain * k / q + k * k) / a0,
+            2.0 * (k * k - high_gain) / a0,
+            (high_gain - band_gain * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+    let k = (PI * 38.13547087602444 / sample_rate).tan();
+    let q = 0.5003270373238773;
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+    [shelf, high_pass]
+}
+
+/// Returns the BS.1770 weight of a channel, leaving out the LFE and boosting the surround
+/// channels of 5.1 in the order L, R, C, LFE, Ls, Rs.
+fn channel_weight(channel_count: usize, channel: usize) -> f64 {
+    match (channel_count, channel) {
+        (6, 3) => 0.0,
+        (6, 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+/// Returns the phases of a Hann-windowed sinc filter that interpolates `factor` samples for
+/// every input sample.
+fn interpolation_filter(factor: usize) -> Vec<Vec<f64>> {
+    let length = TRUE_PEAK_TAPS_PER_PHASE * factor;
+    let center = (length - 1) as f64 / 2.0;
+    let taps: Vec<f64> = (0..length)
+        .map(|tap| {
+            let x = (tap as f64 - center) / factor as f64;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            let window = 0.5 - 0.5 * (2.0 * PI * (tap + 1) as f64 / (length + 1) as f64).cos();
+            sinc * window
+        })
+        .collect();
+    (0..factor)
+        .map(|phase| {
+            let phase_taps: Vec<f64> = taps[phase..].iter().step_by(factor).copied().collect();
+            // every phase passes DC unchanged
+            let sum: f64 = phase_taps.iter().sum();
+            phase_taps.iter().map(|tap| tap / sum).collect()
+        })
+        .collect()
+}
+
+/// The running state of one channel.
+#[derive(Debug, Clone)]
+struct ChannelState {
+    k_weighting: [Biquad; 2],
+    /// The recent samples for the true-peak filter, written twice so that the newest
+    /// `TRUE_PEAK_TAPS_PER_PHASE` are always one contiguous slice ending at `position`.
+    history: Vec<f64>,
+    position: usize,
+    max_true_peak: f32,
+}
+
+/// Measures the levels and loudness of a stream of audio frames.
+pub struct AudioMeter {
+    sample_rate: f64,
+    channel_count: usize,
+    weights: Vec<f64>,
+    interpolation: Vec<Vec<f64>>,
+    channels: Vec<ChannelState>,
+    sub_block_length: usize,
+    /// The weighted sum of squares of the current sub-block, and its number of frames.
+    current_sum: f64,
+    current_length: usize,
+    /// The weighted sums of squares of the last complete sub-blocks, newest last.
+    sub_blocks: VecDeque<f64>,
+    /// The mean squares of the gating blocks above the absolute gate.
+    gated_blocks: Vec<f64>,
+    integrated_loudness: Option<f64>,
+}
+
+impl AudioMeter {
+    pub fn new(sample_rate: f64, channel_count: usize) -> Result<Self, String> {
+        if !(sample_rate >= 8000.0 && sample_rate.is_finite()) {
+            return Err(format!("Can't meter audio at {sample_rate} Hz"));
+        }
+        if channel_count == 0 {
+            return Err("Can't meter audio without channels".to_string());
+        }
+        // oversample to at least 192 kHz, as BS.1770 asks for true peaks
+        let factor = if sample_rate < 96000.0 {
+            4
+        } else if sample_rate < 192000.0 {
+            2
+        } else {
+            1
+        };
+        Ok(Self {
+            sample_rate,
+            channel_count,
+            weights: (0..channel_count)
+                .map(|channel| channel_weight(channel_count, channel))
+                .collect(),
+            interpolation: interpolation_filter(factor),
+            channels: vec![
+                ChannelState {
+                    k_weighting: k_weighting(sample_rate),
+                    history: vec![0.0; 2 * TRUE_PEAK_TAPS_PER_PHASE],
+                    position: 0,
+                    max_true_peak: 0.0,
+                };
+                channel_count
+            ],
+            sub_block_length: (sample_rate * SUB_BLOCK_SECONDS).round() as usize,
+            current_sum: 0.0,
+            current_length: 0,
+            sub_blocks: VecDeque::with_capacity(SHORT_TERM_SUB_BLOCKS),
+            gated_blocks: Vec::new(),
+            integrated_loudness: None,
+        })
+    }
+
+    pub fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+    pub fn get_channel_count(&self) -> usize {
+        self.channel_count
+    }
+
+    /// Measures the next frame of the stream.
+    pub fn process(&mut self, frame: &AudioFrame) -> Result<MeterReading, String> {
+        if frame.get_sample_rate() != self.sample_rate
+            || frame.get_channel_count() != self.channel_count
+        {
+            return Err(format!(
+                "Expected {} channels at {} Hz, got {} channels at {} Hz",
+                self.channel_count,
+                self.sample_rate,
+                frame.get_channel_count(),
+                frame.get_sample_rate()
+            ));
+        }
+        let mut levels = vec![ChannelLevels::default(); self.channel_count];
+        let mut squares = vec![0.0; self.channel_count];
+        for index in 0..frame.get_frame_count() {
+            let mut weighted_square = 0.0;
+            for (channel, state) in self.channels.iter_mut().enumerate() {
+                let sample = frame.get_sample_f64(channel, index).unwrap_or_default();
+                squares[channel] += sample * sample;
+                levels[channel].peak = levels[channel].peak.max(sample.abs() as f32);
+
+                state.history[state.position] = sample;
+                state.history[state.position + TRUE_PEAK_TAPS_PER_PHASE] = sample;
+                state.position = (state.position + 1) % TRUE_PEAK_TAPS_PER_PHASE;
+                let recent = &state.history[state.position..][..TRUE_PEAK_TAPS_PER_PHASE];
+                for phase in &self.interpolation {
+                    // the taps apply to the newest sample first
+                    let interpolated: f64 = phase
+                        .iter()
+                        .zip(recent.iter().rev())
+                        .map(|(tap, sample)| tap * sample)
+                        .sum();
+                    levels[channel].true_peak =
+                        levels[channel].true_peak.max(interpolated.abs() as f32);
+                }
+
+                if self.weights[channel] != 0.0 {
+                    let filtered = state
+                        .k_weighting
+                        .iter_mut()
+                        .fold(sample, |sample, filter| filter.process(sample));
+                    weighted_square += self.weights[channel] * filtered * filtered;
+                }
+            }
+            self.add_weighted_square(weighted_square);
+        }
+
+        let frame_count = frame.get_frame_count().max(1) as f64;
+        for ((levels, state), square) in levels.iter_mut().zip(&mut self.channels).zip(squares) {
+            levels.rms = (square / frame_count).sqrt() as f32;
+            // the interpolation can miss a peak that falls on a sample
+            levels.true_peak = levels.true_peak.max(levels.peak);
+            state.max_true_peak = state.max_true_peak.max(levels.true_peak);
+        }
+        Ok(MeterReading {
+            presentation_timestamp: frame.get_presentation_timestamp(),
+            channels: levels,
+            momentary_loudness: self.get_momentary_loudness(),
+            short_term_loudness: self.get_short_term_loudness(),
+            integrated_loudness: self.integrated_loudness,
+        })
+    }
+
+    fn add_weighted_square(&mut self, weighted_square: f64) {
+        self.current_sum += weighted_square;
+        self.current_length += 1;
+        if self.current_length < self.sub_block_length {
+            return;
+        }
+        if self.sub_blocks.len() == SHORT_TERM_SUB_BLOCKS {
+            self.sub_blocks.pop_front();
+        }
+        self.sub_blocks.push_back(self.current_sum);
+        self.current_sum = 0.0;
+        self.current_length = 0;
+
+        // gating blocks of 400 ms overlap by 75%, so one ends with every sub-block
+        if let Some(block) = self.window_mean_square(MOMENTARY_SUB_BLOCKS) {
+            if loudness(block) > ABSOLUTE_GATE_LUFS {
+                self.gated_blocks.push(block);
+                self.integrated_loudness = Some(self.gate_blocks());
+            }
+        }
+    }
+
+    /// Returns the loudness of the blocks above the relative gate.
+    fn gate_blocks(&self) -> f64 {
+        let mean = |blocks: &mut dyn Iterator<Item = &f64>| {
+            let (sum, count) =
+                blocks.fold((0.0, 0), |(sum, count), block| (sum + block, count + 1));
+            sum / count as f64
+        };
+        let threshold = mean(&mut self.gated_blocks.iter()) * 10f64.powf(RELATIVE_GATE_LU / 10.0);
+        loudness(mean(
+            &mut self.gated_blocks.iter().filter(|&&block| block > threshold),
+        ))
+    }
+
+    /// Returns the weighted mean square of the last `sub_blocks` sub-blocks.
+    fn window_mean_square(&self, sub_blocks: usize) -> Option<f64> {
+        if self.sub_blocks.len() < sub_blocks {
+            return None;
+        }
+        let sum: f64 = self.sub_blocks.iter().rev().take(sub_blocks).sum();
+        Some(sum / (sub_blocks * self.sub_block_length) as f64)
+    }
+
+    /// The loudness of the last 400 ms, in LUFS.
+    pub fn get_momentary_loudness(&self) -> Option<f64> {
+        self.window_mean_square(MOMENTARY_SUB_BLOCKS).map(loudness)
+    }
+    /// The loudness of the last 3 s, in LUFS.
+    pub fn get_short_term_loudness(&self) -> Option<f64> {
+        self.window_mean_square(SHORT_TERM_SUB_BLOCKS).map(loudness)
+    }
+    /// The gated loudness of everything measured since the start or the last reset, in LUFS.
+    pub fn get_integrated_loudness(&self) -> Option<f64> {
+        self.integrated_loudness
+    }
+    /// The largest true peak of each channel since the start or the last reset.
+    pub fn get_max_true_peaks(&self) -> Vec<f32> {
+        self.channels
+            .iter()
+            .map(|channel| channel.max_true_peak)
+            .collect()
+    }
+
+    /// Forgets all audio, for example to start measuring a new recording.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate, self.channel_count)
+            .expect("the rate and channel count were valid before");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 48000.0;
+
+    fn sine(frequency: f64, amplitude: f64, phase: f64, count: usize) -> Vec<f32> {
+        (0..count)
+            .map(|index| {
+                (amplitude * (2.0 * PI * frequency * index as f64 / RATE + phase).sin()) as f32
+            })
+            .collect()
+    }
+
+    /// Uniform noise between -0.5 and 0.5 from a fixed seed.
+    fn noise(count: usize) -> Vec<f32> {
+        let mut state = 0x2545_f491_u32;
+        (0..count)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as f32 / u32::MAX as f32 - 0.5
+            })
+            .collect()
+    }
+
+    fn frame(channels: Vec<Vec<f32>>) -> AudioFrame {
+        AudioFrame::from_planar(RATE, channels, CMTime::default()).unwrap()
+    }
+
+    /// Feeds `channels` to `meter` in buffers of 10 ms and returns the last reading.
+    fn measure(meter: &mut AudioMeter, channels: &[Vec<f32>]) -> MeterReading {
+        let mut reading = None;
+        for start in (0..channels[0].len()).step_by(480) {
+            let end = (start + 480).min(channels[0].len());
+            let chunk = channels
+                .iter()
+                .map(|channel| channel[start..end].to_vec())
+                .collect();
+            reading = Some(meter.process(&frame(chunk)).unwrap());
+        }
+        reading.unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn test_sine_levels() {
+        let mut meter = AudioMeter::new(RATE, 2).unwrap();
+        let reading = meter
+            .process(&frame(vec![sine(1000.0, 0.5, 0.0, 4800), vec![0.0; 4800]]))
+            .unwrap();
+        let left = reading.channels[0];
+        assert_close(f64::from(left.peak), 0.5, 1e-6);
+        assert_close(f64::from(left.rms), 0.5 / 2f64.sqrt(), 1e-6);
+        assert_close(f64::from(left.true_peak), 0.5, 0.005);
+        assert_eq!(reading.channels[1], ChannelLevels::default());
+        assert_close(f64::from(amplitude_to_dbfs(left.peak)), -6.0206, 1e-3);
+        assert_eq!(amplitude_to_dbfs(0.0), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_noise_levels() {
+        let mut meter = AudioMeter::new(RATE, 1).unwrap();
+        let reading = meter.process(&frame(vec![noise(48000)])).unwrap();
+        let levels = reading.channels[0];
+        assert!(levels.peak <= 0.5 && levels.peak > 0.499);
+        assert_close(f64::from(levels.rms), 0.5 / 3f64.sqrt(), 0.003);
+        assert!(levels.true_peak >= levels.peak);
+        assert_eq!(meter.get_max_true_peaks(), [levels.true_peak]);
+    }
+
+    #[test]
+    fn test_true_peak() {
+        // a quarter of the sample rate, sampled 45 degrees away from its peaks
+        let samples = sine(12000.0, 1.0, PI / 4.0, 4800);
+        let mut meter = AudioMeter::new(RATE, 1).unwrap();
+        let levels = meter.process(&frame(vec![samples])).unwrap().channels[0];
+        assert_close(f64::from(levels.peak), 0.5f64.sqrt(), 1e-6);
+        assert!(
+            levels.true_peak > 0.97 && levels.true_peak < 1.01,
+            "{}",
+            levels.true_peak
+        );
+    }
+
+    #[test]
+    fn test_loudness_of_sine() {
+        // EBU Tech 3341 case 1: a stereo 1 kHz sine at -23 dBFS reads -23 LUFS
+        let amplitude = 10f64.powf(-23.0 / 20.0);
+        let tone = sine(1000.0, amplitude, 0.0, 48000 * 4);
+        let mut meter = AudioMeter::new(RATE, 2).unwrap();
+        let reading = measure(&mut meter, &[tone.clone(), tone.clone()]);
+        assert_close(reading.momentary_loudness.unwrap(), -23.0, 0.1);
+        assert_close(reading.short_term_loudness.unwrap(), -23.0, 0.1);
+        assert_close(reading.integrated_loudness.unwrap(), -23.0, 0.1);
+
+        // one channel of the same tone is half the power
+        let mut meter = AudioMeter::new(RATE, 1).unwrap();
+        let reading = measure(&mut meter, &[tone]);
+        assert_close(reading.integrated_loudness.unwrap(), -26.0, 0.1);
+    }
+
+    #[test]
+    fn test_windows_fill_up() {
+        let tone = sine(1000.0, 0.1, 0.0, 48000 * 3);
+        let mut meter = AudioMeter::new(RATE, 1).unwrap();
+        let reading = measure(&mut meter, &[tone[..19200 - 1].to_vec()]);
+        assert_eq!(reading.momentary_loudness, None);
+        assert_eq!(reading.integrated_loudness, None);
+        let reading = measure(&mut meter, &[tone[19200 - 1..19200].to_vec()]);
+        assert!(reading.momentary_loudness.is_some());
+        assert!(reading.integrated_loudness.is_some());
+        assert_eq!(reading.short_term_loudness, None);
+        let reading = measure(&mut meter, &[tone[19200..].to_vec()]);
+        assert!(reading.short_term_loudness.is_some());
+
+        meter.reset();
+        assert_eq!(meter.get_momentary_loudness(), None);
+        assert_eq!(meter.get_integrated_loudness(), None);
+        assert_eq!(meter.get_max_true_peaks(), [0.0]);
+    }
+
+    #[test]
+    fn test_gating() {
+        let amplitude = 10f64.powf(-23.0 / 20.0);
+        let quiet = 10f64.powf(-50.0 / 20.0);
+        let mut signal = sine(1000.0, quiet, 0.0, 48000 * 2);
+        signal.extend(sine(1000.0, amplitude, 0.0, 48000 * 10));
+        signal.extend(vec![0.0; 48000 * 2]);
+        let mut meter = AudioMeter::new(RATE, 1).unwrap();
+        let reading = measure(&mut meter, &[signal]);
+        // silence and the quiet tone are gated out, but the 6 blocks overlapping the start and
+        // end of the 97 blocks of the tone hold the energy of 3 blocks of it
+        let expected = -26.0 + 10.0 * (100.0f64 / 103.0).log10();
+        assert_close(reading.integrated_loudness.unwrap(), expected, 0.02);
+        assert_eq!(reading.momentary_loudness, Some(f64::NEG_INFINITY));
+
+        let mut meter = AudioMeter::new(RATE, 1).unwrap();
+        let reading = measure(&mut meter, &[vec![0.0; 48000]]);
+        assert_eq!(reading.integrated_loudness, None);
+    }
+
+    #[test]
+    fn test_surround_weights() {
+        let tone = sine(1000.0, 0.1, 0.0, 24000);
+        let silence = vec![0.0; 24000];
+        let in_channel = |channel: usize| {
+            let mut channels = vec![silence.clone(); 6];
+            channels[channel] = tone.clone();
+            let mut meter = AudioMeter::new(RATE, 6).unwrap();
+            measure(&mut meter, &channels).momentary_loudness.unwrap()
+        };
+        let front = in_channel(0);
+        assert_close(in_channel(2), front, 1e-9);
+        assert_eq!(in_channel(3), f64::NEG_INFINITY);
+        assert_close(in_channel(5) - front, 10.0 * 1.41f64.log10(), 1e-9);
+    }
+
+    #[test]
+    fn test_sample_rates() {
+        // the K-weighting curve is the same at other rates
+        for rate in [44100.0, 96000.0] {
+            let count = rate as usize;
+            let tone: Vec<f32> = (0..count)
+                .map(|index| (0.1 * (2.0 * PI * 1000.0 * index as f64 / rate).sin()) as f32)
+                .collect();
+            let mut meter = AudioMeter::new(rate, 1).unwrap();
+            let reading = meter
+                .process(&AudioFrame::from_planar(rate, vec![tone], CMTime::default()).unwrap())
+                .unwrap();
+            assert_close(
+                reading.momentary_loudness.unwrap(),
+                loudness(0.005) + 0.691,
+                0.1,
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(AudioMeter::new(0.0, 2).is_err());
+        assert!(AudioMeter::new(RATE, 0).is_err());
+        let mut meter = AudioMeter::new(RATE, 2).unwrap();
+        assert!(meter.process(&frame(vec![vec![0.0; 10]])).is_err());
+    }
+}
@@ -0,0 +1,416 @@
+//! Where captured content ends up in the output buffer.
+//!
+//! ScreenCaptureKit measures the frame of a display or window and the `source_rect` in points,
+//! and the output buffer in pixels. [`OutputGeometry`] works out the rectangle the content
+//! covers in the buffer for a configuration, and [`suggest_output`] picks the configuration
+//! values for a wanted [`OutputSize`].
+//!
+//! The calculations follow ScreenCaptureKit's rules: an empty `source_rect` is the whole
+//! content and an empty `destination_rect` is the whole output; with `scales_to_fit` the
+//! source is scaled into the destination, letterboxed when `preserves_aspect_ratio` is set and
+//! stretched otherwise; without it every point covers `scale_factor` pixels from the top-left
+//! corner of the destination.
+
+use screencapturekit_sys::os_types::geometry::{CGPoint, CGRect, CGSize};
+
+use crate::sc_stream_configuration::{SCStreamConfiguration, SCStreamConfigurationBuilder};
+
+/// The placement of captured content in the output buffer of a stream.
+#[derive(Debug, Clone, Copy)]
+pub struct OutputGeometry {
+    /// The captured area, in points relative to the top-left corner of the content.
+    pub source_rect: CGRect,
+    /// The area the source covers, in pixels of the output buffer. It can extend past the
+    /// output when the content is not scaled to fit.
+    pub content_rect: CGRect,
+    /// The part of `content_rect` inside the destination, where pixels are actually written.
+    pub visible_rect: CGRect,
+    /// Horizontal pixels per point.
+    pub scale_x: f64,
+    /// Vertical pixels per point.
+    pub scale_y: f64,
+}
+
+impl OutputGeometry {
+    /// The geometry of capturing content with `frame` on a display with `scale_factor` pixels
+    /// per point, such as 2 for Retina displays, using `config`.
+    pub fn new(
+        frame: CGRect,
+        scale_factor: f64,
+        config: &SCStreamConfiguration,
+    ) -> Result<Self, String> {
+        if !(scale_factor.is_finite() && scale_factor > 0.0) {
+            return Err(format!("Invalid scale factor {scale_factor}"));
+        }
+        if config.width == 0 || config.height == 0 {
+            return Err(format!(
+                "The output size {}x{} is empty",
+                config.width, config.height
+            ));
+        }
+        let content = CGRect::from_xywh(0.0, 0.0, frame.size.width, frame.size.height);
+        let source_rect = if config.source_rect.is_empty() {
+            content
+        } else {
+            config.source_rect
+        };
+        if !has_area(source_rect) {
+            return Err(format!("The source {source_rect:?} has no area"));
+        }
+        let output = CGRect::from_xywh(0.0, 0.0, config.width.into(), config.height.into());
+        let destination = if config.destination_rect.is_empty() {
+            output
+        } else {
+            config.destination_rect
+        };
+        if !has_area(destination) {
+            return Err(format!("The destination {destination:?} has no area"));
+        }
+
+        let (source, target) = (source_rect.size, destination.size);
+        let (scale_x, scale_y) = match (config.scales_to_fit, config.preserves_aspect_ratio) {
+            (false, _) => (scale_factor, scale_factor),
+            (true, true) => {
+                let scale = (target.width / source.width).min(target.height / source.height);
+                (scale, scale)
+            }
+            (true, false) => (target.width / source.width, target.height / source.height),
+        };
+        let size = CGSize::new(source.width * scale_x, source.height * scale_y);
+        let origin = if config.scales_to_fit {
+            // Letterboxing centers the content in the destination.
+            CGPoint::new(
+                destination.origin.x + (target.width - size.width) / 2.0,
+                destination.origin.y + (target.height - size.height) / 2.0,
+            )
+        } else {
+            destination.origin
+        };
+        let content_rect = CGRect::new(&origin, &size);
+        let visible_rect = content_rect
+            .intersection(&destination)
+            .and_then(|rect| rect.intersection(&output))
+            .unwrap_or_default();
+        Ok(Self {
+            source_rect,
+            content_rect,
+            visible_rect,
+            scale_x,
+            scale_y,
+        })
+    }
+
+    /// Maps a point of the content, relative to its top-left corner, to the output buffer.
+    pub fn point_to_pixel(&self, point: CGPoint) -> CGPoint {
+        CGPoint::new(
+            self.content_rect.origin.x + (point.x - self.source_rect.origin.x) * self.scale_x,
+            self.content_rect.origin.y + (point.y - self.source_rect.origin.y) * self.scale_y,
+        )
+    }
+
+    /// Maps a pixel of the output buffer back to a point of the content.
+    pub fn pixel_to_point(&self, pixel: CGPoint) -> CGPoint {
+        CGPoint::new(
+            self.source_rect.origin.x + (pixel.x - self.content_rect.origin.x) / self.scale_x,
+            self.source_rect.origin.y + (pixel.y - self.content_rect.origin.y) / self.scale_y,
+        )
+    }
+
+    /// Whether the content fills the whole output without borders.
+    pub fn fills_output(&self, config: &SCStreamConfiguration) -> bool {
+        let output = CGRect::from_xywh(0.0, 0.0, config.width.into(), config.height.into());
+        self.visible_rect == output
+    }
+}
+
+/// The output size to suggest a configuration for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputSize {
+    /// One pixel per pixel of the display.
+    NativePixels,
+    /// The largest size with the aspect ratio of the source that fits in `width` by `height`.
+    FitIn { width: u32, height: u32 },
+    /// `height` pixels high, with the width that keeps the aspect ratio of the source.
+    FixedHeight(u32),
+}
+
+/// Configuration values that capture a source at an [`OutputSize`].
+#[derive(Debug, Clone, Copy)]
+pub struct OutputSuggestion {
+    pub width: u32,
+    pub height: u32,
+    pub scales_to_fit: bool,
+    pub preserves_aspect_ratio: bool,
+    pub source_rect: CGRect,
+    pub destination_rect: CGRect,
+}
+
+impl OutputSuggestion {
+    /// Sets the suggested values on `builder`.
+    pub fn apply(&self, builder: SCStreamConfigurationBuilder) -> SCStreamConfigurationBuilder {
+        builder
+            .width(self.width)
+            .height(self.height)
+            .scales_to_fit(self.scales_to_fit)
+            .preserves_aspect_ratio(self.preserves_aspect_ratio)
+            .source_rect(self.source_rect)
+            .destination_rect(self.destination_rect)
+    }
+}
+
+/// Suggests the configuration that captures `source_rect` of content with `frame`, or all of it,
+/// at `size` on a display with `scale_factor` pixels per point.
+///
+/// Computed widths and heights are rounded to even numbers, down when fitting in a size, since
+/// the `420v` and `420f` formats and most video encoders need them. The aspect ratio can then
+/// differ from the source by a pixel or two, which letterboxes the content by at most a pixel.
+///
+/// ```
+/// use screencapturekit::{
+///     output_geometry::{suggest_output, OutputSize},
+///     sc_stream_configuration::SCStreamConfiguration,
+///     sc_types::geometry::{CGPoint, CGRect, CGSize},
+/// };
+///
+/// let frame = CGRect::new(&CGPoint::new(0.0, 0.0), &CGSize::new(1440.0, 900.0));
+/// let size = OutputSize::FitIn { width: 1920, height: 1080 };
+/// let suggestion = suggest_output(frame, 2.0, None, size).unwrap();
+/// assert_eq!((suggestion.width, suggestion.height), (1728, 1080));
+/// let config = suggestion.apply(SCStreamConfiguration::builder()).build().unwrap();
+/// assert!(config.scales_to_fit);
+/// ```
+pub fn suggest_output(
+    frame: CGRect,
+    scale_factor: f64,
+    source_rect: Option<CGRect>,
+    size: OutputSize,
+) -> Result<OutputSuggestion, String> {
+    if !(scale_factor.is_finite() && scale_factor > 0.0) {
+        return Err(format!("Invalid scale factor {scale_factor}"));
+    }
+    let source = source_rect.unwrap_or(CGRect::from_xywh(
+        0.0,
+        0.0,
+        frame.size.width,
+        frame.size.height,
+    ));
+    if source.size.is_empty() {
+        return Err(format!("The source {source:?} is empty"));
+    }
+    let (width, height) = (source.size.width, source.size.height);
+    let scale = match size {
+        OutputSize::NativePixels => scale_factor,
+        OutputSize::FitIn {
+            width: max_width,
+            height: max_height,
+        } => {
+            if max_width < 2 || max_height < 2 {
+                return Err(format!("Can't fit in {max_width}x{max_height}"));
+            }
+            (f64::from(max_width) / width).min(f64::from(max_height) / height)
+        }
+        OutputSize::FixedHeight(fixed) => {
+            if fixed < 2 {
+                return Err(format!("Can't scale to a height of {fixed}"));
+            }
+            f64::from(fixed) / height
+        }
+    };
+    // Fitting rounds down so the output stays within the bounds.
+    let even = |value: f64| {
+        let half = value / 2.0;
+        let half = match size {
+            OutputSize::FitIn { .. } => half.floor(),
+            _ => half.round(),
+        };
+        (half as u32).max(1) * 2
+    };
+    let output_height = match size {
+        OutputSize::FixedHeight(fixed) => fixed,
+        _ => even(height * scale),
+    };
+    Ok(OutputSuggestion {
+        width: even(width * scale),
+        height: output_height,
+        scales_to_fit: true,
+        preserves_aspect_ratio: true,
+        source_rect: source_rect.unwrap_or_default(),
+        destination_rect: CGRect::default(),
+    })
+}
+
+// ScreenCaptureKit does not standardize rectangles, so a negative width or height is as unusable
+// as a zero one.
+fn has_area(rect: CGRect) -> bool {
+    rect.size.width > 0.0 && rect.size.height > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(
+        width: u32,
+        height: u32,
+        scales_to_fit: bool,
+        preserves: bool,
+    ) -> SCStreamConfiguration {
+        SCStreamConfiguration {
+            width,
+            height,
+            scales_to_fit,
+            preserves_aspect_ratio: preserves,
+            ..Default::default()
+        }
+    }
+
+    fn assert_rect(actual: CGRect, expected: (f64, f64, f64, f64)) {
+        assert_eq!(<(f64, f64, f64, f64)>::from(actual), expected);
+    }
+
+    const RETINA: (f64, f64) = (1440.0, 900.0);
+
+    fn retina_frame() -> CGRect {
+        CGRect::from_xywh(0.0, 0.0, RETINA.0, RETINA.1)
+    }
+
+    #[test]
+    fn test_letterboxed() {
+        let config = config(1920, 1080, true, true);
+        let geometry = OutputGeometry::new(retina_frame(), 2.0, &config).unwrap();
+        assert_eq!((geometry.scale_x, geometry.scale_y), (1.2, 1.2));
+        assert_rect(geometry.content_rect, (96.0, 0.0, 1728.0, 1080.0));
+        assert_rect(geometry.visible_rect, (96.0, 0.0, 1728.0, 1080.0));
+        assert!(!geometry.fills_output(&config));
+
+        let pixel = geometry.point_to_pixel(CGPoint::new(720.0, 450.0));
+        assert_eq!((pixel.x, pixel.y), (960.0, 540.0));
+        let point = geometry.pixel_to_point(CGPoint::new(96.0, 1080.0));
+        assert_eq!((point.x, point.y), (0.0, 900.0));
+    }
+
+    #[test]
+    fn test_stretched() {
+        let config = config(1920, 1080, true, false);
+        let geometry = OutputGeometry::new(retina_frame(), 2.0, &config).unwrap();
+        assert_eq!(geometry.scale_y, 1.2);
+        assert_eq!(geometry.scale_x, 1920.0 / 1440.0);
+        assert_rect(geometry.content_rect, (0.0, 0.0, 1920.0, 1080.0));
+        assert!(geometry.fills_output(&config));
+    }
+
+    #[test]
+    fn test_unscaled_is_cropped() {
+        let config = config(1920, 1080, false, true);
+        let geometry = OutputGeometry::new(retina_frame(), 2.0, &config).unwrap();
+        assert_eq!((geometry.scale_x, geometry.scale_y), (2.0, 2.0));
+        assert_rect(geometry.content_rect, (0.0, 0.0, 2880.0, 1800.0));
+        assert_rect(geometry.visible_rect, (0.0, 0.0, 1920.0, 1080.0));
+        let pixel = geometry.point_to_pixel(CGPoint::new(100.0, 10.0));
+        assert_eq!((pixel.x, pixel.y), (200.0, 20.0));
+    }
+
+    #[test]
+    fn test_source_and_destination() {
+        let config = SCStreamConfiguration {
+            source_rect: CGRect::from_xywh(100.0, 50.0, 400.0, 300.0),
+            destination_rect: CGRect::from_xywh(10.0, 20.0, 800.0, 300.0),
+            ..config(1000, 500, true, true)
+        };
+        let geometry = OutputGeometry::new(retina_frame(), 2.0, &config).unwrap();
+        assert_eq!(geometry.scale_x, 1.0);
+        assert_rect(geometry.content_rect, (210.0, 20.0, 400.0, 300.0));
+        let pixel = geometry.point_to_pixel(CGPoint::new(100.0, 50.0));
+        assert_eq!((pixel.x, pixel.y), (210.0, 20.0));
+
+        let unscaled = SCStreamConfiguration {
+            scales_to_fit: false,
+            ..config
+        };
+        let geometry = OutputGeometry::new(retina_frame(), 2.0, &unscaled).unwrap();
+        assert_rect(geometry.content_rect, (10.0, 20.0, 800.0, 600.0));
+        // Clipped to the destination, not just the output.
+        assert_rect(geometry.visible_rect, (10.0, 20.0, 800.0, 300.0));
+    }
+
+    #[test]
+    fn test_empty_rects_are_the_whole_content() {
+        let config = SCStreamConfiguration {
+            source_rect: CGRect::from_xywh(10.0, 10.0, 0.0, 0.0),
+            destination_rect: CGRect::from_xywh(20.0, 20.0, 0.0, 5.0),
+            ..config(1920, 1080, true, true)
+        };
+        let geometry = OutputGeometry::new(retina_frame(), 2.0, &config).unwrap();
+        assert_eq!(geometry.source_rect, retina_frame());
+        assert_rect(geometry.content_rect, (96.0, 0.0, 1728.0, 1080.0));
+    }
+
+    #[test]
+    fn test_invalid_geometry() {
+        let frame = retina_frame();
+        assert!(OutputGeometry::new(frame, 0.0, &config(100, 100, true, true)).is_err());
+        assert!(OutputGeometry::new(frame, f64::NAN, &config(100, 100, true, true)).is_err());
+        assert!(OutputGeometry::new(frame, 1.0, &config(0, 100, true, true)).is_err());
+        let negative_source = SCStreamConfiguration {
+            source_rect: CGRect::from_xywh(10.0, 10.0, -10.0, 5.0),
+            ..config(100, 100, true, true)
+        };
+        assert!(OutputGeometry::new(frame, 1.0, &negative_source).is_err());
+        assert!(OutputGeometry::new(
+            CGRect::from_xywh(0.0, 0.0, 0.0, 0.0),
+            1.0,
+            &config(100, 100, true, true)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let frame = retina_frame();
+        let native = suggest_output(frame, 2.0, None, OutputSize::NativePixels).unwrap();
+        assert_eq!((native.width, native.height), (2880, 1800));
+
+        let window = CGRect::from_xywh(300.0, 200.0, 1001.0, 601.0);
+        let fixed = suggest_output(window, 1.0, None, OutputSize::FixedHeight(720)).unwrap();
+        assert_eq!((fixed.width, fixed.height), (1200, 720));
+
+        let crop = Some(CGRect::from_xywh(0.0, 0.0, 1000.0, 1000.0));
+        let fit = OutputSize::FitIn {
+            width: 1280,
+            height: 721,
+        };
+        let square = suggest_output(frame, 2.0, crop, fit).unwrap();
+        assert_eq!((square.width, square.height), (720, 720));
+        assert_eq!(square.source_rect.size.width, 1000.0);
+
+        for size in [
+            OutputSize::NativePixels,
+            OutputSize::FitIn {
+                width: 641,
+                height: 479,
+            },
+            OutputSize::FixedHeight(360),
+        ] {
+            let suggestion = suggest_output(window, 2.0, None, size).unwrap();
+            let config = suggestion
+                .apply(SCStreamConfiguration::builder())
+                .build()
+                .unwrap();
+            assert!(config.width % 2 == 0 && config.height % 2 == 0);
+            let geometry = OutputGeometry::new(window, 2.0, &config).unwrap();
+            // Even rounding letterboxes by at most a pixel on each side.
+            assert!(geometry.content_rect.origin.x.abs() <= 1.0);
+            assert!(geometry.content_rect.origin.y.abs() <= 1.0);
+        }
+
+        assert!(suggest_output(frame, 2.0, None, OutputSize::FixedHeight(1)).is_err());
+        assert!(suggest_output(frame, -1.0, None, OutputSize::NativePixels).is_err());
+        assert!(suggest_output(
+            frame,
+            1.0,
+            Some(CGRect::default()),
+            OutputSize::NativePixels
+        )
+        .is_err());
+    }
+}
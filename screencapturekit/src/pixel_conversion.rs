@@ -0,0 +1,743 @@
+//! Conversion of captured pixels to RGB in plain Rust.
+//!
+//! `420v` and `420f` frames are converted from YCbCr with a BT.601 or BT.709 matrix, and the
+//! packed 10-bit `l10r` frames are unpacked to 16 or 8 bits per sample.
+//!
+//! The conversions use fixed-point integer arithmetic, so they produce the same bytes on every
+//! platform.
+
+use screencapturekit_sys::os_types::four_char_code::FourCharCode;
+
+use crate::{
+    cv_pixel_buffer::{OwnedPlane, PlaneView},
+    frame::Frame,
+    sc_stream_configuration::{color::ColorMatrix, PixelFormat},
+};
+
+/// The matrix that relates YCbCr to RGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum YCbCrMatrix {
+    /// ITU-R BT.601, used for standard definition video.
+    Bt601,
+    /// ITU-R BT.709, used for HD video and what ScreenCaptureKit produces by default.
+    #[default]
+    Bt709,
+}
+
+impl YCbCrMatrix {
+    /// The red and blue luma coefficients `Kr` and `Kb`.
+    pub(crate) fn coefficients(self) -> (f64, f64) {
+        match self {
+            YCbCrMatrix::Bt601 => (0.299, 0.114),
+            YCbCrMatrix::Bt709 => (0.2126, 0.0722),
+        }
+    }
+}
+
+/// The matrix of a stream configured with [`ColorMatrix`], so frames are converted back with the
+/// matrix they were captured with. ITU-R BT.2020 and SMPTE 240M are not supported.
+impl TryFrom<ColorMatrix> for YCbCrMatrix {
+    type Error = String;
+
+    fn try_from(matrix: ColorMatrix) -> Result<Self, Self::Error> {
+        match matrix {
+            ColorMatrix::ItuR601 => Ok(YCbCrMatrix::Bt601),
+            ColorMatrix::ItuR709 => Ok(YCbCrMatrix::Bt709),
+            ColorMatrix::ItuR2020 | ColorMatrix::Smpte240M => Err(format!(
+                "Converting {} YCbCr to RGB is not supported",
+                matrix.get_name()
+            )),
+        }
+    }
+}
+
+impl From<YCbCrMatrix> for ColorMatrix {
+    fn from(matrix: YCbCrMatrix) -> Self {
+        match matrix {
+            YCbCrMatrix::Bt601 => ColorMatrix::ItuR601,
+            YCbCrMatrix::Bt709 => ColorMatrix::ItuR709,
+        }
+    }
+}
+
+/// The range of the YCbCr samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YCbCrRange {
+    /// Luma in 16..=235 and chroma in 16..=240, the `420v` format.
+    Video,
+    /// Luma and chroma in 0..=255, the `420f` format.
+    Full,
+}
+
+impl YCbCrRange {
+    pub fn from_pixel_format(pixel_format: FourCharCode) -> Option<Self> {
+        match &pixel_format.into_chars() {
+            b"420v" => Some(YCbCrRange::Video),
+            b"420f" => Some(YCbCrRange::Full),
+            _ => None,
+        }
+    }
+}
+
+/// The byte order of converted pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RgbLayout {
+    Rgb,
+    Rgba,
+    Bgra,
+}
+
+impl RgbLayout {
+    pub const fn bytes_per_pixel(self) -> usize {
+        match self {
+            RgbLayout::Rgb => 3,
+            RgbLayout::Rgba | RgbLayout::Bgra => 4,
+        }
+    }
+    /// The number of samples of a pixel, which is also its size in 16-bit words for the 16-bit
+    /// conversions.
+    pub const fn channels(self) -> usize {
+        self.bytes_per_pixel()
+    }
+    #[inline]
+    pub(crate) fn write<T: Copy>(self, pixel: &mut [T], r: T, g: T, b: T, a: T) {
+        match self {
+            RgbLayout::Rgb => pixel.copy_from_slice(&[r, g, b]),
+            RgbLayout::Rgba => pixel.copy_from_slice(&[r, g, b, a]),
+            RgbLayout::Bgra => pixel.copy_from_slice(&[b, g, r, a]),
+        }
+    }
+}
+
+const FIXED_SHIFT: u32 = 16;
+const FIXED_HALF: i32 = 1 << (FIXED_SHIFT - 1);
+
+/// The conversion as 16.16 fixed-point multipliers of the offset luma and chroma samples.
+struct YCbCrCoefficients {
+    luma_offset: i32,
+    luma: i32,
+    r_cr: i32,
+    g_cb: i32,
+    g_cr: i32,
+    b_cb: i32,
+}
+
+impl YCbCrCoefficients {
+    fn new(range: YCbCrRange, matrix: YCbCrMatrix) -> Self {
+        let (kr, kb) = matrix.coefficients();
+        let kg = 1.0 - kr - kb;
+        let (luma_offset, luma_scale, chroma_scale) = match range {
+            YCbCrRange::Video => (16, 255.0 / 219.0, 255.0 / 224.0),
+            YCbCrRange::Full => (0, 1.0, 1.0),
+        };
+        let fixed = |value: f64| (value * f64::from(1 << FIXED_SHIFT)).round() as i32;
+        Self {
+            luma_offset,
+            luma: fixed(luma_scale),
+            r_cr: fixed(chroma_scale * 2.0 * (1.0 - kr)),
+            g_cb: fixed(chroma_scale * 2.0 * kb * (1.0 - kb) / kg),
+            g_cr: fixed(chroma_scale * 2.0 * kr * (1.0 - kr) / kg),
+            b_cb: fixed(chroma_scale * 2.0 * (1.0 - kb)),
+        }
+    }
+    #[inline]
+    fn convert(&self, y: u8, cb: u8, cr: u8) -> (u8, u8, u8) {
+        let y = (i32::from(y) - self.luma_offset) * self.luma + FIXED_HALF;
+        let cb = i32::from(cb) - 128;
+        let cr = i32::from(cr) - 128;
+        let clamp = |value: i32| (value >> FIXED_SHIFT).clamp(0, 255) as u8;
+        (
+            clamp(y + self.r_cr * cr),
+            clamp(y - self.g_cb * cb - self.g_cr * cr),
+            clamp(y + self.b_cb * cb),
+        )
+    }
+}
+
+/// Checks that `destination`, with rows `destination_row` elements apart, can hold the image.
+fn check_destination<T>(
+    width: usize,
+    height: usize,
+    layout: RgbLayout,
+    destination: &[T],
+    destination_row: usize,
+) -> Result<(), String> {
+    let row = width * layout.channels();
+    let element_size = std::mem::size_of::<T>();
+    if destination_row < row {
+        return Err(format!(
+            "A destination row of {} bytes can't hold {width} pixels",
+            destination_row * element_size
+        ));
+    }
+    if height > 0 && destination.len() < destination_row * (height - 1) + row {
+        return Err(format!(
+            "A destination of {} bytes can't hold {height} rows of {} bytes",
+            std::mem::size_of_val(destination),
+            destination_row * element_size
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_ycbcr420(luma: &PlaneView, chroma: &PlaneView) -> Result<(), String> {
+    let (width, height) = (luma.width, luma.height);
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    if luma.bytes_per_row < width || luma.data.len() < luma.bytes_per_row * height {
+        return Err("The luma plane is smaller than its width and height".to_string());
+    }
+    if chroma.width < chroma_width
+        || chroma.height < chroma_height
+        || chroma.bytes_per_row < chroma_width * 2
+        || chroma.data.len() < chroma.bytes_per_row * chroma_height
+    {
+        return Err(format!(
+            "The chroma plane must hold {chroma_width}x{chroma_height} samples for a {width}x{height} image"
+        ));
+    }
+    Ok(())
+}
+
+/// Converts a bi-planar YCbCr 4:2:0 image to RGB.
+///
+/// `luma` holds one byte per pixel and `chroma` interleaved Cb and Cr bytes for every 2x2 block
+/// of pixels. The converted rows are written `destination_bytes_per_row` apart, leaving the
+/// padding at the end of each row untouched.
+pub fn ycbcr420_to_rgb(
+    luma: &PlaneView,
+    chroma: &PlaneView,
+    range: YCbCrRange,
+    matrix: YCbCrMatrix,
+    layout: RgbLayout,
+    destination: &mut [u8],
+    destination_bytes_per_row: usize,
+) -> Result<(), String> {
+    check_ycbcr420(luma, chroma)?;
+    let (width, height) = (luma.width, luma.height);
+    let chroma_width = width.div_ceil(2);
+    check_destination(
+        width,
+        height,
+        layout,
+        destination,
+        destination_bytes_per_row,
+    )?;
+
+    let coefficients = YCbCrCoefficients::new(range, matrix);
+    let bytes_per_pixel = layout.bytes_per_pixel();
+    for y in 0..height {
+        let luma_row = &luma.data[y * luma.bytes_per_row..][..width];
+        let chroma_row = &chroma.data[(y / 2) * chroma.bytes_per_row..][..chroma_width * 2];
+        let destination_row =
+            &mut destination[y * destination_bytes_per_row..][..width * bytes_per_pixel];
+        for (x, pixel) in destination_row
+            .chunks_exact_mut(bytes_per_pixel)
+            .enumerate()
+        {
+            let cb = chroma_row[(x / 2) * 2];
+            let cr = chroma_row[(x / 2) * 2 + 1];
+            let (r, g, b) = coefficients.convert(luma_row[x], cb, cr);
+            layout.write(pixel, r, g, b, 255);
+        }
+    }
+    Ok(())
+}
+
+/// Splits a little-endian `l10r` word into its 10-bit red, green and blue and 2-bit alpha.
+///
+/// The alpha occupies the two most significant bits, followed by red, green and blue.
+pub const fn unpack_argb2101010(word: u32) -> [u16; 4] {
+    [
+        ((word >> 20) & 0x3ff) as u16,
+        ((word >> 10) & 0x3ff) as u16,
+        (word & 0x3ff) as u16,
+        (word >> 30) as u16,
+    ]
+}
+
+/// How 10-bit samples are reduced to 8 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dither {
+    /// Rounds every sample to the nearest 8-bit value.
+    #[default]
+    None,
+    /// Adds a 4x4 ordered (Bayer) pattern before truncating, which trades banding in smooth
+    /// gradients for a fine, deterministic noise.
+    Ordered,
+}
+
+const BAYER_4X4: [[u32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+impl Dither {
+    #[inline]
+    fn reduce(self, sample: u16, x: usize, y: usize) -> u8 {
+        let sample = u32::from(sample);
+        let reduced = match self {
+            Dither::None => (sample * 255 + 511) / 1023,
+            // floor(sample * 255 / 1023 + (threshold + 0.5) / 16)
+            Dither::Ordered => {
+                let threshold = BAYER_4X4[y % 4][x % 4];
+                (sample * 255 * 32 + (2 * threshold + 1) * 1023) / (1023 * 32)
+            }
+        };
+        reduced as u8
+    }
+}
+
+/// Widens a 10-bit sample to 16 bits by repeating its high bits, so 0x3ff becomes 0xffff.
+#[inline]
+const fn widen_10_bit(sample: u16) -> u16 {
+    (sample << 6) | (sample >> 4)
+}
+
+pub(crate) fn check_l10r(plane: &PlaneView) -> Result<(), String> {
+    if plane.bytes_per_row < plane.width * 4
+        || plane.data.len() < plane.bytes_per_row * plane.height
+    {
+        return Err("The l10r plane is smaller than its width and height".to_string());
+    }
+    Ok(())
+}
+
+fn l10r_row<'a>(plane: &'a PlaneView, y: usize) -> impl Iterator<Item = [u16; 4]> + 'a {
+    plane.data[y * plane.bytes_per_row..][..plane.width * 4]
+        .chunks_exact(4)
+        .map(|word| unpack_argb2101010(u32::from_le_bytes([word[0], word[1], word[2], word[3]])))
+}
+
+/// Converts a packed `l10r` image to 16 bits per sample.
+///
+/// The 10-bit samples are scaled to the full 16-bit range and the 2-bit alpha to 0, 0x5555,
+/// 0xaaaa or 0xffff. The converted rows are written `destination_samples_per_row` apart.
+pub fn l10r_to_rgb16(
+    plane: &PlaneView,
+    layout: RgbLayout,
+    destination: &mut [u16],
+    destination_samples_per_row: usize,
+) -> Result<(), String> {
+    check_l10r(plane)?;
+    check_destination(
+        plane.width,
+        plane.height,
+        layout,
+        destination,
+        destination_samples_per_row,
+    )?;
+
+    let channels = layout.channels();
+    for y in 0..plane.height {
+        let destination_row =
+            &mut destination[y * destination_samples_per_row..][..plane.width * channels];
+        for (pixel, [r, g, b, a]) in destination_row
+            .chunks_exact_mut(channels)
+            .zip(l10r_row(plane, y))
+        {
+            layout.write(
+                pixel,
+                widen_10_bit(r),
+                widen_10_bit(g),
+                widen_10_bit(b),
+                a * 0x5555,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Converts a packed `l10r` image to 8 bits per sample, optionally dithering the color.
+///
+/// The alpha is scaled to 0, 85, 170 or 255 and never dithered. The converted rows are written
+/// `destination_bytes_per_row` apart.
+pub fn l10r_to_rgb(
+    plane: &PlaneView,
+    layout: RgbLayout,
+    dither: Dither,
+    destination: &mut [u8],
+    destination_bytes_per_row: usize,
+) -> Result<(), String> {
+    check_l10r(plane)?;
+    check_destination(
+        plane.width,
+        plane.height,
+        layout,
+        destination,
+        destination_bytes_per_row,
+    )?;
+
+    let bytes_per_pixel = layout.bytes_per_pixel();
+    for y in 0..plane.height {
+        let destination_row =
+            &mut destination[y * destination_bytes_per_row..][..plane.width * bytes_per_pixel];
+        for (x, (pixel, [r, g, b, a])) in destination_row
+            .chunks_exact_mut(bytes_per_pixel)
+            .zip(l10r_row(plane, y))
+            .enumerate()
+        {
+            layout.write(
+                pixel,
+                dither.reduce(r, x, y),
+                dither.reduce(g, x, y),
+                dither.reduce(b, x, y),
+                a as u8 * 85,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Converts a `420v`, `420f` or `l10r` frame to tightly packed RGB pixels.
+///
+/// The matrix only applies to the YCbCr formats, and `l10r` samples are rounded without
+/// dithering.
+pub fn frame_to_rgb(
+    frame: &Frame,
+    matrix: YCbCrMatrix,
+    layout: RgbLayout,
+) -> Result<OwnedPlane, String> {
+    let range = match frame.pixel_format {
+        PixelFormat::YCbCr420v => YCbCrRange::Video,
+        PixelFormat::YCbCr420f => YCbCrRange::Full,
+        PixelFormat::ARGB2101010 => {
+            let plane = frame
+                .plane(0)
+                .ok_or_else(|| "An l10r frame needs a plane".to_string())?;
+            let bytes_per_row = plane.width * layout.bytes_per_pixel();
+            let mut data = vec![0; bytes_per_row * plane.height];
+            l10r_to_rgb(&plane, layout, Dither::None, &mut data, bytes_per_row)?;
+            return Ok(OwnedPlane {
+                width: plane.width,
+                height: plane.height,
+                bytes_per_row,
+                data,
+            });
+        }
+        other => return Err(format!("Can't convert {other:?} frames to RGB")),
+    };
+    let (Some(luma), Some(chroma)) = (frame.plane(0), frame.plane(1)) else {
+        return Err("A YCbCr frame needs a luma and a chroma plane".to_string());
+    };
+    let bytes_per_row = luma.width * layout.bytes_per_pixel();
+    let mut data = vec![0; bytes_per_row * luma.height];
+    ycbcr420_to_rgb(
+        &luma,
+        &chroma,
+        range,
+        matrix,
+        layout,
+        &mut data,
+        bytes_per_row,
+    )?;
+    Ok(OwnedPlane {
+        width: luma.width,
+        height: luma.height,
+        bytes_per_row,
+        data,
+    })
+}
+
+/// Converts an `l10r` frame to tightly packed pixels of 16 bits per sample.
+pub fn frame_to_rgb16(frame: &Frame, layout: RgbLayout) -> Result<Vec<u16>, String> {
+    if frame.pixel_format != PixelFormat::ARGB2101010 {
+        return Err(format!(
+            "Can't convert {:?} frames to 16-bit RGB",
+            frame.pixel_format
+        ));
+    }
+    let plane = frame
+        .plane(0)
+        .ok_or_else(|| "An l10r frame needs a plane".to_string())?;
+    let samples_per_row = plane.width * layout.channels();
+    let mut data = vec![0; samples_per_row * plane.height];
+    l10r_to_rgb16(&plane, layout, &mut data, samples_per_row)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use screencapturekit_sys::os_types::base::CMTime;
+
+    use super::*;
+
+    fn convert_one(y: u8, cb: u8, cr: u8, range: YCbCrRange, matrix: YCbCrMatrix) -> [u8; 3] {
+        let format = FourCharCode::from_chars(*b"420v");
+        let luma = [y];
+        let chroma = [cb, cr];
+        let mut rgb = [0u8; 3];
+        ycbcr420_to_rgb(
+            &PlaneView::new(format, 1, 1, 1, &luma).unwrap(),
+            &PlaneView::new(format, 1, 1, 2, &chroma).unwrap(),
+            range,
+            matrix,
+            RgbLayout::Rgb,
+            &mut rgb,
+            3,
+        )
+        .unwrap();
+        rgb
+    }
+
+    /// The conversion in floating point, as defined by the standards.
+    fn reference(y: u8, cb: u8, cr: u8, range: YCbCrRange, matrix: YCbCrMatrix) -> [f64; 3] {
+        let (kr, kb) = matrix.coefficients();
+        let (y, cb, cr) = match range {
+            YCbCrRange::Video => (
+                (f64::from(y) - 16.0) / 219.0,
+                (f64::from(cb) - 128.0) / 224.0,
+                (f64::from(cr) - 128.0) / 224.0,
+            ),
+            YCbCrRange::Full => (
+                f64::from(y) / 255.0,
+                (f64::from(cb) - 128.0) / 255.0,
+                (f64::from(cr) - 128.0) / 255.0,
+            ),
+        };
+        let r = y + 2.0 * (1.0 - kr) * cr;
+        let b = y + 2.0 * (1.0 - kb) * cb;
+        let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+        [r, g, b].map(|value| (value * 255.0).clamp(0.0, 255.0))
+    }
+
+    #[test]
+    fn test_reference_values() {
+        use YCbCrMatrix::*;
+        use YCbCrRange::*;
+        assert_eq!(convert_one(16, 128, 128, Video, Bt709), [0, 0, 0]);
+        assert_eq!(convert_one(235, 128, 128, Video, Bt709), [255, 255, 255]);
+        assert_eq!(convert_one(126, 128, 128, Video, Bt601), [128, 128, 128]);
+        assert_eq!(convert_one(0, 128, 128, Full, Bt601), [0, 0, 0]);
+        assert_eq!(convert_one(255, 128, 128, Full, Bt709), [255, 255, 255]);
+        // 75% red, green and blue bars of BT.709 video range, off by the 8-bit rounding
+        assert_eq!(convert_one(51, 109, 212, Video, Bt709), [191, 0, 1]);
+        assert_eq!(convert_one(133, 63, 52, Video, Bt709), [0, 191, 0]);
+        assert_eq!(convert_one(28, 212, 120, Video, Bt709), [0, 0, 191]);
+        // and of BT.601 video range
+        assert_eq!(convert_one(65, 100, 212, Video, Bt601), [191, 0, 1]);
+        assert_eq!(convert_one(112, 72, 58, Video, Bt601), [0, 191, 0]);
+    }
+
+    #[test]
+    fn test_color_matrices() {
+        for matrix in [YCbCrMatrix::Bt601, YCbCrMatrix::Bt709] {
+            assert_eq!(YCbCrMatrix::try_from(ColorMatrix::from(matrix)), Ok(matrix));
+        }
+        assert!(YCbCrMatrix::try_from(ColorMatrix::ItuR2020).is_err());
+        assert!(YCbCrMatrix::try_from(ColorMatrix::Smpte240M).is_err());
+    }
+
+    #[test]
+    fn test_matches_floating_point() {
+        for range in [YCbCrRange::Video, YCbCrRange::Full] {
+            for matrix in [YCbCrMatrix::Bt601, YCbCrMatrix::Bt709] {
+                for y in (0..=255).step_by(15) {
+                    for cb in (0..=255).step_by(17) {
+                        for cr in (0..=255).step_by(17) {
+                            let actual = convert_one(y, cb, cr, range, matrix);
+                            let expected = reference(y, cb, cr, range, matrix);
+                            for (actual, expected) in actual.iter().zip(expected) {
+                                assert!(
+                                    (f64::from(*actual) - expected).abs() <= 1.0,
+                                    "{y} {cb} {cr} {range:?} {matrix:?}: {actual} != {expected}"
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_strides_and_layouts() {
+        // 3x3 pixels with padded rows, the chroma of the last column and row is shared
+        let format = FourCharCode::from_chars(*b"420f");
+        let luma = [
+            0, 128, 255, 9, //
+            0, 128, 255, 9, //
+            255, 255, 255, 9,
+        ];
+        let chroma = [
+            128, 128, 128, 128, 9, 9, //
+            128, 128, 128, 128, 9, 9,
+        ];
+        let luma = PlaneView::new(format, 3, 3, 4, &luma).unwrap();
+        let chroma = PlaneView::new(format, 2, 2, 6, &chroma).unwrap();
+        let mut bgra = [7u8; 2 * 16 + 12];
+        ycbcr420_to_rgb(
+            &luma,
+            &chroma,
+            YCbCrRange::Full,
+            YCbCrMatrix::Bt709,
+            RgbLayout::Bgra,
+            &mut bgra,
+            16,
+        )
+        .unwrap();
+        assert_eq!(
+            bgra[..12],
+            [0, 0, 0, 255, 128, 128, 128, 255, 255, 255, 255, 255]
+        );
+        assert_eq!(bgra[12..16], [7; 4]);
+        assert_eq!(bgra[32..36], [255; 4]);
+
+        let mut too_small = [0u8; 16];
+        assert!(ycbcr420_to_rgb(
+            &luma,
+            &chroma,
+            YCbCrRange::Full,
+            YCbCrMatrix::Bt709,
+            RgbLayout::Rgba,
+            &mut too_small,
+            12,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_frame_to_rgb() {
+        let planes = vec![
+            OwnedPlane {
+                width: 2,
+                height: 2,
+                bytes_per_row: 2,
+                data: vec![16, 235, 235, 16],
+            },
+            OwnedPlane {
+                width: 1,
+                height: 1,
+                bytes_per_row: 2,
+                data: vec![128, 128],
+            },
+        ];
+        let frame = Frame::new(PixelFormat::YCbCr420v, planes, CMTime::default());
+        let rgba = frame_to_rgb(&frame, YCbCrMatrix::Bt709, RgbLayout::Rgba).unwrap();
+        assert_eq!(rgba.bytes_per_row, 8);
+        assert_eq!(
+            rgba.data,
+            [0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0, 255]
+        );
+
+        let bgra = Frame::new(PixelFormat::ARGB8888, vec![], CMTime::default());
+        assert!(frame_to_rgb(&bgra, YCbCrMatrix::Bt709, RgbLayout::Rgb).is_err());
+    }
+
+    fn l10r_plane(width: usize, height: usize, bytes_per_row: usize, words: &[u32]) -> OwnedPlane {
+        let mut data = vec![0; bytes_per_row * height];
+        for (index, word) in words.iter().enumerate() {
+            let offset = (index / width) * bytes_per_row + (index % width) * 4;
+            data[offset..offset + 4].copy_from_slice(&word.to_le_bytes());
+        }
+        OwnedPlane {
+            width,
+            height,
+            bytes_per_row,
+            data,
+        }
+    }
+
+    #[test]
+    fn test_unpack_argb2101010() {
+        assert_eq!(unpack_argb2101010(0xbff8_0001), [0x3ff, 0x200, 0x001, 0b10]);
+        assert_eq!(unpack_argb2101010(0x3fff_ffff), [0x3ff, 0x3ff, 0x3ff, 0]);
+        assert_eq!(unpack_argb2101010(0xc000_0000), [0, 0, 0, 0b11]);
+        assert_eq!(unpack_argb2101010(0x0010_0400), [1, 1, 0, 0]);
+        // the bytes as they are stored in the plane
+        let word = u32::from_le_bytes([0x01, 0x00, 0xf8, 0xbf]);
+        assert_eq!(unpack_argb2101010(word), [0x3ff, 0x200, 0x001, 0b10]);
+    }
+
+    #[test]
+    fn test_l10r_to_rgb16() {
+        let format = PixelFormat::ARGB2101010.into();
+        let plane = l10r_plane(
+            2,
+            2,
+            12,
+            &[0xbff8_0001, 0x3fff_ffff, 0xc000_0000, 0x4010_0400],
+        );
+        let view = plane.as_view(format).unwrap();
+
+        let mut rgba = [7u16; 8 + 8];
+        l10r_to_rgb16(&view, RgbLayout::Rgba, &mut rgba, 8).unwrap();
+        assert_eq!(
+            rgba,
+            [
+                0xffff, 0x8020, 0x0040, 0xaaaa, 0xffff, 0xffff, 0xffff, 0, //
+                0, 0, 0, 0xffff, 0x0040, 0x0040, 0, 0x5555,
+            ]
+        );
+
+        let mut rgb = [7u16; 8 + 6];
+        l10r_to_rgb16(&view, RgbLayout::Rgb, &mut rgb, 8).unwrap();
+        assert_eq!(rgb[..6], [0xffff, 0x8020, 0x0040, 0xffff, 0xffff, 0xffff]);
+        assert_eq!(rgb[6..8], [7, 7]);
+        let mut bgra = [0u16; 8];
+        assert!(l10r_to_rgb16(&view, RgbLayout::Bgra, &mut bgra, 4).is_err());
+        let mut bgra = [0u16; 16];
+        l10r_to_rgb16(&view, RgbLayout::Bgra, &mut bgra, 8).unwrap();
+        assert_eq!(bgra[..4], [0x0040, 0x8020, 0xffff, 0xaaaa]);
+
+        let short = PlaneView {
+            data: &plane.data[..16],
+            ..view
+        };
+        assert!(l10r_to_rgb16(&short, RgbLayout::Rgba, &mut rgba, 8).is_err());
+    }
+
+    #[test]
+    fn test_l10r_to_rgb() {
+        let format = PixelFormat::ARGB2101010.into();
+        let plane = l10r_plane(2, 1, 8, &[0xbff8_0001, 0x4010_0400]);
+        let mut rgba = [0u8; 8];
+        l10r_to_rgb(
+            &plane.as_view(format).unwrap(),
+            RgbLayout::Rgba,
+            Dither::None,
+            &mut rgba,
+            8,
+        )
+        .unwrap();
+        assert_eq!(rgba, [255, 128, 0, 170, 0, 0, 0, 85]);
+    }
+
+    #[test]
+    fn test_ordered_dither() {
+        // every 4x4 block of a flat color averages to the exact 8-bit value
+        let format = PixelFormat::ARGB2101010.into();
+        for sample in (0..1024).step_by(3) {
+            let word = (0b11 << 30) | sample;
+            let plane = l10r_plane(4, 4, 16, &[word; 16]);
+            let mut rgb = [0u8; 48];
+            l10r_to_rgb(
+                &plane.as_view(format).unwrap(),
+                RgbLayout::Rgb,
+                Dither::Ordered,
+                &mut rgb,
+                12,
+            )
+            .unwrap();
+            let blue: Vec<u8> = rgb.iter().skip(2).step_by(3).copied().collect();
+            let exact = f64::from(sample) * 255.0 / 1023.0;
+            let average = blue.iter().map(|b| f64::from(*b)).sum::<f64>() / 16.0;
+            assert!((average - exact).abs() <= 1.0 / 32.0, "{sample}: {average}");
+            let (min, max) = (blue.iter().min().unwrap(), blue.iter().max().unwrap());
+            assert!(max - min <= 1);
+            assert!(rgb.chunks(3).all(|pixel| pixel[..2] == [0, 0]));
+        }
+    }
+
+    #[test]
+    fn test_l10r_frames() {
+        let plane = l10r_plane(2, 1, 8, &[0xbff8_0001, 0x4010_0400]);
+        let frame = Frame::new(PixelFormat::ARGB2101010, vec![plane], CMTime::default());
+        let rgb = frame_to_rgb(&frame, YCbCrMatrix::default(), RgbLayout::Rgb).unwrap();
+        assert_eq!(rgb.bytes_per_row, 6);
+        assert_eq!(rgb.data, [255, 128, 0, 0, 0, 0]);
+        assert_eq!(
+            frame_to_rgb16(&frame, RgbLayout::Rgb).unwrap(),
+            [0xffff, 0x8020, 0x0040, 0x0040, 0x0040, 0]
+        );
+
+        let bgra = Frame::new(PixelFormat::ARGB8888, vec![], CMTime::default());
+        assert!(frame_to_rgb16(&bgra, RgbLayout::Rgb).is_err());
+    }
+}
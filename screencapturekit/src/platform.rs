@@ -0,0 +1,109 @@
+use std::{error::Error, fmt};
+
+/// Returns whether ScreenCaptureKit is available on the target platform.
+///
+/// On other platforms the pure data types still compile, but everything that talks to
+/// ScreenCaptureKit fails with [`UnsupportedPlatformError`].
+pub const fn is_supported() -> bool {
+    cfg!(target_os = "macos")
+}
+
+/// The error for operations that need ScreenCaptureKit on a platform that does not have it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UnsupportedPlatformError;
+
+impl fmt::Display for UnsupportedPlatformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ScreenCaptureKit is only available on macOS")
+    }
+}
+
+impl Error for UnsupportedPlatformError {}
+
+impl From<UnsupportedPlatformError> for String {
+    fn from(value: UnsupportedPlatformError) -> Self {
+        value.to_string()
+    }
+}
+
+/// The error of operations that go through ScreenCaptureKit or a
+/// [`CaptureBackend`](crate::capture_backend::CaptureBackend).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+    /// ScreenCaptureKit is not available on this platform.
+    UnsupportedPlatform(UnsupportedPlatformError),
+    /// The operation failed, with the description of ScreenCaptureKit or the backend.
+    Failed(String),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::UnsupportedPlatform(error) => error.fmt(f),
+            CaptureError::Failed(description) => f.write_str(description),
+        }
+    }
+}
+
+impl Error for CaptureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CaptureError::UnsupportedPlatform(error) => Some(error),
+            CaptureError::Failed(_) => None,
+        }
+    }
+}
+
+impl From<UnsupportedPlatformError> for CaptureError {
+    fn from(value: UnsupportedPlatformError) -> Self {
+        CaptureError::UnsupportedPlatform(value)
+    }
+}
+
+impl From<String> for CaptureError {
+    fn from(value: String) -> Self {
+        CaptureError::Failed(value)
+    }
+}
+
+impl From<&str> for CaptureError {
+    fn from(value: &str) -> Self {
+        CaptureError::Failed(value.to_owned())
+    }
+}
+
+impl From<CaptureError> for String {
+    fn from(value: CaptureError) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(all(test, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+    use crate::{
+        sc_content_filter::{InitParams, SCContentFilter},
+        sc_display::SCDisplay,
+    };
+
+    #[test]
+    fn test_native_calls_are_unsupported() {
+        assert!(!is_supported());
+        let display = SCDisplay {
+            display_id: 1,
+            frame: Default::default(),
+            width: 100,
+            height: 100,
+        };
+        let error = SCContentFilter::new(InitParams::Display(display)).unwrap_err();
+        assert_eq!(
+            error,
+            CaptureError::UnsupportedPlatform(UnsupportedPlatformError)
+        );
+        assert!(error.source().unwrap().is::<UnsupportedPlatformError>());
+        assert_eq!(
+            String::from(error),
+            "ScreenCaptureKit is only available on macOS"
+        );
+    }
+}
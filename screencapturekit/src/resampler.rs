@@ -0,0 +1,420 @@
+//! Sample rate conversion of captured audio.
+//!
+//! A [`Resampler`] keeps the end of the audio it was given, so consecutive buffers of a stream
+//! convert as if they were one signal.
+
+use std::f64::consts::PI;
+
+use screencapturekit_sys::os_types::base::CMTime;
+
+use crate::audio_frame::AudioFrame;
+
+/// The number of zero crossings of the sinc kernel on each side of its center.
+const SINC_ZERO_CROSSINGS: usize = 32;
+/// The number of kernel values stored between two zero crossings.
+const SINC_TABLE_RESOLUTION: usize = 256;
+/// The shape parameter of the Kaiser window, for about 90 dB of stopband attenuation.
+const KAISER_BETA: f64 = 9.0;
+/// The cutoff as a fraction of the lower Nyquist frequency, leaving room for the transition band.
+const CUTOFF: f64 = 0.95;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResamplerQuality {
+    /// Linear interpolation, cheap but without filtering, so downsampling aliases.
+    Linear,
+    /// A Kaiser-windowed sinc filter.
+    #[default]
+    Sinc,
+}
+
+/// The modified Bessel function of the first kind of order zero.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    for k in 1..50 {
+        term *= half / k as f64;
+        sum += term * term;
+        if term * term < sum * 1e-16 {
+            break;
+        }
+    }
+    sum
+}
+
+/// One half of a windowed sinc kernel, sampled `SINC_TABLE_RESOLUTION` times per zero crossing.
+fn sinc_table() -> Vec<f32> {
+    let length = SINC_ZERO_CROSSINGS * SINC_TABLE_RESOLUTION;
+    (0..=length)
+        .map(|i| {
+            let x = i as f64 / SINC_TABLE_RESOLUTION as f64;
+            let sinc = if i == 0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            let ratio = x / SINC_ZERO_CROSSINGS as f64;
+            let window = bessel_i0(KAISER_BETA * (1.0 - ratio * ratio).max(0.0).sqrt())
+                / bessel_i0(KAISER_BETA);
+            (sinc * window) as f32
+        })
+        .collect()
+}
+
+enum Kernel {
+    Linear,
+    Sinc {
+        table: Vec<f32>,
+        /// The cutoff as a fraction of the input Nyquist frequency.
+        cutoff: f64,
+    },
+}
+
+impl Kernel {
+    /// How many input samples on each side of an output sample contribute to it.
+    fn half_width(&self) -> usize {
+        match self {
+            Kernel::Linear => 1,
+            Kernel::Sinc { cutoff, .. } => (SINC_ZERO_CROSSINGS as f64 / cutoff).ceil() as usize,
+        }
+    }
+    /// The weight of an input sample `distance` input samples away from an output sample.
+    fn weight(&self, distance: f64) -> f32 {
+        match self {
+            Kernel::Linear => (1.0 - distance.abs()).max(0.0) as f32,
+            Kernel::Sinc { table, cutoff } => {
+                let position = distance.abs() * cutoff * SINC_TABLE_RESOLUTION as f64;
+                let index = position as usize;
+                if index + 1 >= table.len() {
+                    return 0.0;
+                }
+                let fraction = (position - index as f64) as f32;
+                let value = table[index] + (table[index + 1] - table[index]) * fraction;
+                value * *cutoff as f32
+            }
+        }
+    }
+}
+
+/// Converts planar audio from one sample rate to another, one buffer at a time.
+///
+/// The output is planar `f32` audio. The timestamp of each output buffer is derived from the
+/// timestamp of the first input buffer and the number of frames produced so far, so it does
+/// not drift across buffers. The sinc filter needs some input after each output frame, which
+/// makes the output lag the input; [`Resampler::flush`] returns the rest at the end.
+pub struct Resampler {
+    input_rate: f64,
+    output_rate: f64,
+    channel_count: usize,
+    kernel: Kernel,
+    /// The recent input of each channel, starting at input frame `history_start`.
+    history: Vec<Vec<f32>>,
+    history_start: u64,
+    input_frames: u64,
+    output_frames: u64,
+    first_timestamp: Option<CMTime>,
+}
+
+impl Resampler {
+    pub fn new(
+        input_rate: f64,
+        output_rate: f64,
+        channel_count: usize,
+        quality: ResamplerQuality,
+    ) -> Result<Self, String> {
+        if !(input_rate > 0.0 && output_rate > 0.0) {
+            return Err(format!(
+                "Can't resample from {input_rate} Hz to {output_rate} Hz"
+            ));
+        }
+        if channel_count == 0 {
+            return Err("Can't resample audio without channels".to_string());
+        }
+        let kernel = match quality {
+            ResamplerQuality::Linear => Kernel::Linear,
+            ResamplerQuality::Sinc => Kernel::Sinc {
+                table: sinc_table(),
+                cutoff: CUTOFF * (output_rate / input_rate).min(1.0),
+            },
+        };
+        Ok(Self {
+            input_rate,
+            output_rate,
+            channel_count,
+            kernel,
+            history: vec![Vec::new(); channel_count],
+            history_start: 0,
+            input_frames: 0,
+            output_frames: 0,
+            first_timestamp: None,
+        })
+    }
+
+    pub fn get_input_rate(&self) -> f64 {
+        self.input_rate
+    }
+    pub fn get_output_rate(&self) -> f64 {
+        self.output_rate
+    }
+    /// The number of input frames the output lags behind.
+    pub fn get_latency(&self) -> usize {
+        self.kernel.half_width()
+    }
+
+    /// Resamples the next buffer of the stream.
+    pub fn process(&mut self, frame: &AudioFrame) -> Result<AudioFrame, String> {
+        if frame.get_sample_rate() != self.input_rate
+            || frame.get_channel_count() != self.channel_count
+        {
+            return Err(format!(
+                "Expected {} channels at {} Hz, got {} channels at {} Hz",
+                self.channel_count,
+                self.input_rate,
+                frame.get_channel_count(),
+                frame.get_sample_rate()
+            ));
+        }
+        self.first_timestamp
+            .get_or_insert(frame.get_presentation_timestamp());
+        for (history, samples) in self.history.iter_mut().zip(frame.to_planar_f32()) {
+            history.extend(samples);
+        }
+        self.input_frames += frame.get_frame_count() as u64;
+        let available = self.input_frames.saturating_sub(self.get_latency() as u64);
+        Ok(self.resample_until(available))
+    }
+
+    /// Returns the output that was held back for the latency of the filter, as if the stream
+    /// ended in silence, and starts over.
+    pub fn flush(&mut self) -> AudioFrame {
+        let frame = self.resample_until(self.input_frames);
+        self.reset();
+        frame
+    }
+
+    /// Forgets all input, for example after a gap in the stream.
+    pub fn reset(&mut self) {
+        self.history.iter_mut().for_each(Vec::clear);
+        self.history_start = 0;
+        self.input_frames = 0;
+        self.output_frames = 0;
+        self.first_timestamp = None;
+    }
+
+    /// The input position of output frame `index`.
+    fn input_position(&self, index: u64) -> f64 {
+        index as f64 * self.input_rate / self.output_rate
+    }
+
+    /// Produces every output frame before input position `end`.
+    fn resample_until(&mut self, end: u64) -> AudioFrame {
+        let timestamp = self.output_timestamp();
+        let half_width = self.get_latency() as i64;
+        let mut channels = vec![Vec::new(); self.channel_count];
+        loop {
+            let position = self.input_position(self.output_frames);
+            if position >= end as f64 {
+                break;
+            }
+            let center = position.floor() as i64;
+            for (history, output) in self.history.iter().zip(channels.iter_mut()) {
+                let mut sum = 0.0;
+                for input in center - half_width + 1..=center + half_width {
+                    let Some(sample) = input
+                        .checked_sub(self.history_start as i64)
+                        .and_then(|index| usize::try_from(index).ok())
+                        .and_then(|index| history.get(index))
+                    else {
+                        // before the first or after the last input frame
+                        continue;
+                    };
+                    sum += sample * self.kernel.weight(position - input as f64);
+                }
+                output.push(sum);
+            }
+            self.output_frames += 1;
+        }
+
+        // keep what the next output frame needs
+        let next = self.input_position(self.output_frames).floor() as i64;
+        let keep_from = (next - half_width + 1).max(self.history_start as i64) as u64;
+        let drop = ((keep_from - self.history_start) as usize).min(self.history[0].len());
+        for history in &mut self.history {
+            history.drain(..drop);
+        }
+        self.history_start += drop as u64;
+
+        AudioFrame::from_planar(self.output_rate, channels, timestamp)
+            .expect("every channel has the same number of frames")
+    }
+
+    /// The timestamp of the next output frame.
+    fn output_timestamp(&self) -> CMTime {
+        let first = self.first_timestamp.unwrap_or_default();
+        if first.timescale <= 0 {
+            return first;
+        }
+        let offset = self.output_frames as f64 * f64::from(first.timescale) / self.output_rate;
+        CMTime {
+            value: first.value + offset.round() as i64,
+            ..first
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f64, rate: f64, start: usize, count: usize) -> Vec<f32> {
+        (start..start + count)
+            .map(|n| (2.0 * PI * frequency * n as f64 / rate).sin() as f32 * 0.5)
+            .collect()
+    }
+
+    fn timestamp(value: i64) -> CMTime {
+        CMTime {
+            value,
+            timescale: 48000,
+            ..Default::default()
+        }
+    }
+
+    /// Resamples `channels` in chunks of the given sizes and returns the concatenated output.
+    fn resample_in_chunks(
+        resampler: &mut Resampler,
+        channels: &[Vec<f32>],
+        chunk_sizes: &[usize],
+    ) -> Vec<Vec<f32>> {
+        let mut output = vec![Vec::new(); channels.len()];
+        let mut start = 0;
+        for size in chunk_sizes.iter().cycle() {
+            if start >= channels[0].len() {
+                break;
+            }
+            let end = (start + size).min(channels[0].len());
+            let chunk = channels
+                .iter()
+                .map(|channel| channel[start..end].to_vec())
+                .collect();
+            let input =
+                AudioFrame::from_planar(resampler.get_input_rate(), chunk, timestamp(start as i64))
+                    .unwrap();
+            let resampled = resampler.process(&input).unwrap();
+            for (output, samples) in output.iter_mut().zip(resampled.to_planar_f32()) {
+                output.extend(samples);
+            }
+            start = end;
+        }
+        for (output, samples) in output.iter_mut().zip(resampler.flush().to_planar_f32()) {
+            output.extend(samples);
+        }
+        output
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_downsample_sine() {
+        for quality in [ResamplerQuality::Linear, ResamplerQuality::Sinc] {
+            let mut resampler = Resampler::new(48000.0, 16000.0, 2, quality).unwrap();
+            let input = vec![sine(1000.0, 48000.0, 0, 48000); 2];
+            let output = resample_in_chunks(&mut resampler, &input, &[1024]);
+            assert_eq!(output[0].len(), 16000);
+            let expected = sine(1000.0, 16000.0, 0, 16000);
+            // away from the edges, where the input starts and ends abruptly
+            for (actual, expected) in output[1][200..15800].iter().zip(&expected[200..15800]) {
+                assert!((actual - expected).abs() < 2e-3, "{quality:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_upsample_sine() {
+        let mut resampler = Resampler::new(16000.0, 44100.0, 1, ResamplerQuality::Sinc).unwrap();
+        let input = vec![sine(440.0, 16000.0, 0, 16000)];
+        let output = resample_in_chunks(&mut resampler, &input, &[160]);
+        assert_eq!(output[0].len(), 44100);
+        let expected = sine(440.0, 44100.0, 0, 44100);
+        for (actual, expected) in output[0][500..43600].iter().zip(&expected[500..43600]) {
+            assert!((actual - expected).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_sinc_filters_aliases() {
+        // 12 kHz is above the 8 kHz Nyquist frequency of the output
+        let input = vec![sine(12000.0, 48000.0, 0, 48000)];
+        let mut sinc = Resampler::new(48000.0, 16000.0, 1, ResamplerQuality::Sinc).unwrap();
+        let filtered = resample_in_chunks(&mut sinc, &input, &[480]);
+        assert!(rms(&filtered[0][200..15800]) < 1e-3);
+
+        let mut linear = Resampler::new(48000.0, 16000.0, 1, ResamplerQuality::Linear).unwrap();
+        let aliased = resample_in_chunks(&mut linear, &input, &[480]);
+        assert!(rms(&aliased[0][200..15800]) > 0.1);
+    }
+
+    #[test]
+    fn test_chunk_boundaries_are_seamless() {
+        let input = vec![
+            sine(997.0, 48000.0, 0, 9000),
+            sine(3001.0, 48000.0, 0, 9000),
+        ];
+        let mut resampler = Resampler::new(48000.0, 16000.0, 2, ResamplerQuality::Sinc).unwrap();
+        let whole = resample_in_chunks(&mut resampler, &input, &[9000]);
+        let chunked = resample_in_chunks(&mut resampler, &input, &[1, 1024, 7, 333]);
+        assert_eq!(whole, chunked);
+
+        let mut resampler = Resampler::new(48000.0, 44100.0, 2, ResamplerQuality::Linear).unwrap();
+        let whole = resample_in_chunks(&mut resampler, &input, &[9000]);
+        let chunked = resample_in_chunks(&mut resampler, &input, &[480, 13]);
+        assert_eq!(whole, chunked);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let mut resampler = Resampler::new(48000.0, 16000.0, 1, ResamplerQuality::Sinc).unwrap();
+        let latency = resampler.get_latency();
+        let mut produced = 0;
+        for chunk in 0..5 {
+            let input = AudioFrame::from_planar(
+                48000.0,
+                vec![vec![0.0f32; 1024]],
+                timestamp(1_000 + chunk * 1024),
+            )
+            .unwrap();
+            let output = resampler.process(&input).unwrap();
+            // one output frame is three input frames, or three units of the 48 kHz timescale
+            assert_eq!(
+                output.get_presentation_timestamp().value,
+                1_000 + produced * 3
+            );
+            produced += output.get_frame_count() as i64;
+        }
+        assert_eq!(produced as usize, (5 * 1024 - latency).div_ceil(3));
+        let rest = resampler.flush();
+        assert_eq!(
+            rest.get_presentation_timestamp().value,
+            1_000 + produced * 3
+        );
+        assert_eq!(
+            produced as usize + rest.get_frame_count(),
+            (5 * 1024usize).div_ceil(3)
+        );
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(Resampler::new(0.0, 16000.0, 1, ResamplerQuality::Sinc).is_err());
+        assert!(Resampler::new(48000.0, 16000.0, 0, ResamplerQuality::Sinc).is_err());
+
+        let mut resampler = Resampler::new(48000.0, 16000.0, 2, ResamplerQuality::Sinc).unwrap();
+        let mono = AudioFrame::from_planar(48000.0, vec![vec![0.0f32; 8]], CMTime::default());
+        assert!(resampler.process(&mono.unwrap()).is_err());
+        let other_rate =
+            AudioFrame::from_planar(44100.0, vec![vec![0.0f32; 8]; 2], CMTime::default());
+        assert!(resampler.process(&other_rate.unwrap()).is_err());
+    }
+}
@@ -1,15 +1,20 @@
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::{
     content_filter::{UnsafeContentFilter, UnsafeInitParams::*},
     os_types::rc::{Id, ShareId},
     shareable_content::{UnsafeSCRunningApplication, UnsafeSCWindow},
 };
 
+#[cfg(not(target_os = "macos"))]
+use crate::platform::UnsupportedPlatformError;
 use crate::{
-    sc_display::SCDisplay, sc_running_application::SCRunningApplication, sc_window::SCWindow,
+    platform::CaptureError, sc_display::SCDisplay, sc_running_application::SCRunningApplication,
+    sc_window::SCWindow,
 };
 
 #[derive(Debug)]
 pub struct SCContentFilter {
+    #[cfg(target_os = "macos")]
     pub(crate) _unsafe_ref: Id<UnsafeContentFilter>,
 }
 
@@ -30,54 +35,76 @@ pub enum InitParams {
     ),
 }
 
-fn windows_to_unsafe(w: Vec<SCWindow>) -> Vec<ShareId<UnsafeSCWindow>> {
-    w.into_iter().map(|w| w._unsafe_ref).collect()
+#[cfg(target_os = "macos")]
+fn native_ref<T>(unsafe_ref: Option<T>) -> Result<T, String> {
+    unsafe_ref.ok_or_else(|| {
+        "Content filters can only be created from content returned by ScreenCaptureKit".to_string()
+    })
 }
 
+#[cfg(target_os = "macos")]
+fn windows_to_unsafe(w: Vec<SCWindow>) -> Result<Vec<ShareId<UnsafeSCWindow>>, String> {
+    w.into_iter().map(|w| native_ref(w._unsafe_ref)).collect()
+}
+
+#[cfg(target_os = "macos")]
 fn applications_to_unsafe(
     a: Vec<SCRunningApplication>,
-) -> Vec<ShareId<UnsafeSCRunningApplication>> {
-    a.into_iter().map(|a| a._unsafe_ref).collect()
+) -> Result<Vec<ShareId<UnsafeSCRunningApplication>>, String> {
+    a.into_iter().map(|a| native_ref(a._unsafe_ref)).collect()
 }
 
-impl From<InitParams> for screencapturekit_sys::content_filter::UnsafeInitParams {
-    fn from(value: InitParams) -> Self {
-        match value {
-            InitParams::DesktopIndependentWindow(w) => DesktopIndependentWindow(w._unsafe_ref),
-            InitParams::Display(d) => Display(d._unsafe_ref),
+#[cfg(target_os = "macos")]
+impl TryFrom<InitParams> for screencapturekit_sys::content_filter::UnsafeInitParams {
+    type Error = String;
+
+    fn try_from(value: InitParams) -> Result<Self, Self::Error> {
+        Ok(match value {
+            InitParams::DesktopIndependentWindow(w) => {
+                DesktopIndependentWindow(native_ref(w._unsafe_ref)?)
+            }
+            InitParams::Display(d) => Display(native_ref(d._unsafe_ref)?),
             InitParams::DisplayIncludingWindows(d, w) => {
-                DisplayIncludingWindows(d._unsafe_ref, windows_to_unsafe(w))
+                DisplayIncludingWindows(native_ref(d._unsafe_ref)?, windows_to_unsafe(w)?)
             }
             InitParams::DisplayExcludingWindows(d, w) => {
-                DisplayExcludingWindows(d._unsafe_ref, windows_to_unsafe(w))
+                DisplayExcludingWindows(native_ref(d._unsafe_ref)?, windows_to_unsafe(w)?)
             }
             InitParams::DisplayIncludingApplicationsExceptingWindows(d, a, w) => {
                 DisplayIncludingApplicationsExceptingWindows(
-                    d._unsafe_ref,
-                    applications_to_unsafe(a),
-                    windows_to_unsafe(w),
+                    native_ref(d._unsafe_ref)?,
+                    applications_to_unsafe(a)?,
+                    windows_to_unsafe(w)?,
                 )
             }
 
             InitParams::DisplayExcludingApplicationsExceptingWindows(d, a, w) => {
                 DisplayExcludingApplicationsExceptingWindows(
-                    d._unsafe_ref,
-                    applications_to_unsafe(a),
-                    windows_to_unsafe(w),
+                    native_ref(d._unsafe_ref)?,
+                    applications_to_unsafe(a)?,
+                    windows_to_unsafe(w)?,
                 )
             }
-        }
+        })
     }
 }
 impl SCContentFilter {
-    pub fn new(params: InitParams) -> Self {
-        Self {
-            _unsafe_ref: UnsafeContentFilter::init(params.into()),
-        }
+    /// Fails for displays, windows or applications that were not returned by ScreenCaptureKit,
+    /// such as the ones of a fixture provider, and with [`CaptureError::UnsupportedPlatform`] on
+    /// platforms without ScreenCaptureKit.
+    #[cfg(target_os = "macos")]
+    pub fn new(params: InitParams) -> Result<Self, CaptureError> {
+        Ok(Self {
+            _unsafe_ref: UnsafeContentFilter::init(params.try_into()?),
+        })
+    }
+    #[cfg(not(target_os = "macos"))]
+    pub fn new(_params: InitParams) -> Result<Self, CaptureError> {
+        Err(UnsupportedPlatformError.into())
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, target_os = "macos"))]
 mod tests {
     use crate::sc_shareable_content::SCShareableContent;
 
@@ -87,6 +114,6 @@ mod tests {
     #[test]
     fn test_sc_filter() {
         let display = SCShareableContent::current().displays.pop().unwrap();
-        SCContentFilter::new(Display(display));
+        SCContentFilter::new(Display(display)).unwrap();
     }
 }
@@ -1,17 +1,18 @@
-use screencapturekit_sys::{
-    os_types::{geometry::CGRect, rc::ShareId},
-    shareable_content::UnsafeSCDisplay,
-};
+use screencapturekit_sys::os_types::geometry::CGRect;
+#[cfg(target_os = "macos")]
+use screencapturekit_sys::{os_types::rc::ShareId, shareable_content::UnsafeSCDisplay};
 
 #[derive(Debug, Clone)]
 pub struct SCDisplay {
-    pub(crate) _unsafe_ref: ShareId<UnsafeSCDisplay>,
+    #[cfg(target_os = "macos")]
+    pub(crate) _unsafe_ref: Option<ShareId<UnsafeSCDisplay>>,
     pub display_id: u32,
     pub frame: CGRect,
     pub width: u32,
     pub height: u32,
 }
 
+#[cfg(target_os = "macos")]
 impl From<ShareId<UnsafeSCDisplay>> for SCDisplay {
     fn from(unsafe_ref: ShareId<UnsafeSCDisplay>) -> Self {
         SCDisplay {
@@ -19,7 +20,7 @@ impl From<ShareId<UnsafeSCDisplay>> for SCDisplay {
             frame: unsafe_ref.get_frame(),
             width: unsafe_ref.get_width(),
             height: unsafe_ref.get_height(),
-            _unsafe_ref: unsafe_ref,
+            _unsafe_ref: Some(unsafe_ref),
         }
     }
 }
@@ -1,3 +1,4 @@
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::stream_error_handler::UnsafeSCStreamError;
 
 // TODO: It might make sense to be a little more precise with lifetimes, than 'static.
@@ -9,14 +10,17 @@ pub trait StreamErrorHandler: Send + Sync + 'static {
     fn on_error(&self);
 }
 
+#[cfg(target_os = "macos")]
 pub(crate) struct StreamErrorHandlerWrapper<T: StreamErrorHandler>(T);
 
+#[cfg(target_os = "macos")]
 impl<T: StreamErrorHandler> StreamErrorHandlerWrapper<T> {
     pub fn new(error_handler: T) -> Self {
         StreamErrorHandlerWrapper(error_handler)
     }
 }
 
+#[cfg(target_os = "macos")]
 impl<T: StreamErrorHandler> UnsafeSCStreamError for StreamErrorHandlerWrapper<T> {
     fn handle_error(&self) {
         self.0.on_error();
@@ -1,3 +1,4 @@
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::{
     cm_sample_buffer_ref::CMSampleBufferRef, os_types::rc::Id,
     stream_output_handler::UnsafeSCStreamOutput,
@@ -5,7 +6,7 @@ use screencapturekit_sys::{
 
 use crate::cm_sample_buffer::CMSampleBuffer;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SCStreamOutputType {
     Screen,
     Audio,
@@ -14,14 +15,17 @@ pub trait StreamOutput: Sync + Send + 'static {
     fn did_output_sample_buffer(&self, sample_buffer: CMSampleBuffer, of_type: SCStreamOutputType);
 }
 
+#[cfg(target_os = "macos")]
 pub(crate) struct StreamOutputWrapper<T: StreamOutput>(T);
 
+#[cfg(target_os = "macos")]
 impl<T: StreamOutput> StreamOutputWrapper<T> {
     pub fn new(output: T) -> Self {
         Self(output)
     }
 }
 
+#[cfg(target_os = "macos")]
 impl<TOutput: StreamOutput> UnsafeSCStreamOutput for StreamOutputWrapper<TOutput> {
     fn did_output_sample_buffer(&self, sample_buffer_ref: Id<CMSampleBufferRef>, of_type: u8) {
         self.0.did_output_sample_buffer(
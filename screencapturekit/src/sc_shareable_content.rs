@@ -1,12 +1,17 @@
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::{os_types::rc::Id, shareable_content::UnsafeSCShareableContent};
 
+#[cfg(not(target_os = "macos"))]
+use crate::platform::UnsupportedPlatformError;
 use crate::{
-    sc_display::SCDisplay, sc_running_application::SCRunningApplication, sc_window::SCWindow,
+    platform::CaptureError, sc_display::SCDisplay, sc_running_application::SCRunningApplication,
+    sc_window::SCWindow,
 };
 
 #[derive(Debug)]
 pub struct SCShareableContent {
-    _unsafe_ref: Id<UnsafeSCShareableContent>,
+    #[cfg(target_os = "macos")]
+    pub(crate) _unsafe_ref: Option<Id<UnsafeSCShareableContent>>,
     pub windows: Vec<SCWindow>,
     pub applications: Vec<SCRunningApplication>,
     pub displays: Vec<SCDisplay>,
@@ -17,7 +22,8 @@ impl SCShareableContent {
         SCShareableContent::try_current().unwrap()
     }
 
-    pub fn try_current() -> Result<Self, String> {
+    #[cfg(target_os = "macos")]
+    pub fn try_current() -> Result<Self, CaptureError> {
         let unsafe_ref = UnsafeSCShareableContent::get()?;
 
         let windows: Vec<SCWindow> = unsafe_ref
@@ -42,9 +48,14 @@ impl SCShareableContent {
             windows,
             applications,
             displays,
-            _unsafe_ref: unsafe_ref,
+            _unsafe_ref: Some(unsafe_ref),
         })
     }
+
+    #[cfg(not(target_os = "macos"))]
+    pub fn try_current() -> Result<Self, CaptureError> {
+        Err(UnsupportedPlatformError.into())
+    }
 }
 
 #[cfg(test)]
@@ -52,8 +63,18 @@ mod tests {
     use super::*;
 
     #[test]
+    #[cfg(target_os = "macos")]
     #[cfg_attr(feature = "ci", ignore)]
     fn test_sc_shareable_content() {
         SCShareableContent::current();
     }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_unsupported_platform() {
+        assert_eq!(
+            SCShareableContent::try_current().err(),
+            Some(CaptureError::UnsupportedPlatform(UnsupportedPlatformError))
+        );
+    }
 }
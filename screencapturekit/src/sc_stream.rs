@@ -1,47 +1,58 @@
 use crate::{
+    capture_backend::{CaptureBackend, NativeBackend},
+    platform::CaptureError,
     sc_content_filter::SCContentFilter,
-    sc_error_handler::{StreamErrorHandler, StreamErrorHandlerWrapper},
-    sc_output_handler::{SCStreamOutputType, StreamOutput, StreamOutputWrapper},
+    sc_error_handler::StreamErrorHandler,
+    sc_output_handler::{SCStreamOutputType, StreamOutput},
     sc_stream_configuration::SCStreamConfiguration,
 };
-use screencapturekit_sys::{os_types::rc::Id, stream::UnsafeSCStream};
 
-pub struct SCStream {
-    pub(crate) _unsafe_ref: Id<UnsafeSCStream>,
+pub struct SCStream<B: CaptureBackend = NativeBackend> {
+    backend: B,
 }
 
 impl SCStream {
+    /// Fails with [`CaptureError::UnsupportedPlatform`] on platforms without ScreenCaptureKit.
     pub fn new(
         filter: SCContentFilter,
         config: SCStreamConfiguration,
         handler: impl StreamErrorHandler,
-    ) -> Self {
-        Self {
-            _unsafe_ref: UnsafeSCStream::init(
-                filter._unsafe_ref,
-                config.into(),
-                StreamErrorHandlerWrapper::new(handler),
-            ),
-        }
+    ) -> Result<Self, CaptureError> {
+        Ok(Self::with_backend(NativeBackend::new(
+            filter, config, handler,
+        )?))
+    }
+}
+
+impl<B: CaptureBackend> SCStream<B> {
+    pub fn with_backend(backend: B) -> Self {
+        Self { backend }
+    }
+    pub fn backend(&self) -> &B {
+        &self.backend
     }
     pub fn add_output(&mut self, output: impl StreamOutput, output_type: SCStreamOutputType) {
-        self._unsafe_ref.add_stream_output(
-            StreamOutputWrapper::new(output),
-            match output_type {
-                SCStreamOutputType::Screen => 0,
-                SCStreamOutputType::Audio => 1,
-            },
-        );
+        self.backend.add_output(output, output_type);
+    }
+    pub fn start_capture(&self) -> Result<(), CaptureError> {
+        self.backend.start_capture()
+    }
+    pub fn stop_capture(&self) -> Result<(), CaptureError> {
+        self.backend.stop_capture()
     }
-    pub fn start_capture(&self) -> Result<(), String> {
-        self._unsafe_ref.start_capture()
+    /// Changes the configuration without restarting the capture. Use
+    /// [`SCStreamConfiguration::diff`] to skip updates that change nothing.
+    pub fn update_configuration(&self, config: SCStreamConfiguration) -> Result<(), CaptureError> {
+        self.backend.update_configuration(config)
     }
-    pub fn stop_capture(&self) -> Result<(), String> {
-        self._unsafe_ref.stop_capture()
+    /// Changes the captured content, for example the excluded windows, without restarting the
+    /// capture.
+    pub fn update_content_filter(&self, filter: SCContentFilter) -> Result<(), CaptureError> {
+        self.backend.update_content_filter(filter)
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, target_os = "macos"))]
 mod tests {
 
     use std::sync::mpsc::{sync_channel, SyncSender};
@@ -93,14 +104,14 @@ mod tests {
     fn test_screen_output() {
         let mut content = SCShareableContent::current();
         let display = content.displays.pop().unwrap();
-        let filter = SCContentFilter::new(Display(display));
+        let filter = SCContentFilter::new(Display(display)).unwrap();
         let config = SCStreamConfiguration {
             width: 100,
             height: 100,
             ..Default::default()
         };
         let (video_tx, video_rx) = sync_channel(1);
-        let mut stream = SCStream::new(filter, config, SomeErrorHandler {});
+        let mut stream = SCStream::new(filter, config, SomeErrorHandler {}).unwrap();
         let w = ScreenOutput { video_tx };
         stream.add_output(w, SCStreamOutputType::Screen);
         stream.start_capture().ok();
@@ -112,7 +123,7 @@ mod tests {
     fn test_audio_output() {
         let mut content = SCShareableContent::current();
         let display = content.displays.pop().unwrap();
-        let filter = SCContentFilter::new(Display(display));
+        let filter = SCContentFilter::new(Display(display)).unwrap();
         let config = SCStreamConfiguration {
             width: 100,
             height: 100,
@@ -120,7 +131,7 @@ mod tests {
             ..Default::default()
         };
         let (audio_tx, audio_rx) = sync_channel(1);
-        let mut stream = SCStream::new(filter, config, SomeErrorHandler {});
+        let mut stream = SCStream::new(filter, config, SomeErrorHandler {}).unwrap();
         let w = AudioOutput { audio_tx };
         stream.add_output(w, SCStreamOutputType::Audio);
         stream.start_capture().ok();
@@ -1,48 +1,179 @@
+pub mod color;
+pub mod settings;
+
+use std::{error::Error, fmt};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use self::color::{Color, ColorMatrix, ColorSpace};
 use crate::sc_types::base::CMTime;
 use crate::sc_types::four_char_code::FourCharCode;
 use crate::sc_types::geometry::CGRect;
-use crate::sc_types::graphics::CGColor;
-use screencapturekit_sys::{
-    os_types::rc::Id,
-    stream_configuration::{UnsafeStreamConfiguration, UnsafeStreamConfigurationRef},
-};
+use screencapturekit_sys::stream_configuration::UnsafeStreamConfiguration;
+#[cfg(target_os = "macos")]
+use screencapturekit_sys::{os_types::rc::Id, stream_configuration::UnsafeStreamConfigurationRef};
 
-pub static PIXEL_FORMATS: [PixelFormat; 4] = [
+/// The pixel formats with a variant of their own, in the order of [`PixelFormat`].
+pub static PIXEL_FORMATS: [PixelFormat; 8] = [
     PixelFormat::ARGB8888,
     PixelFormat::ARGB2101010,
-    PixelFormat::YCbCr420f,
     PixelFormat::YCbCr420v,
+    PixelFormat::YCbCr420f,
+    PixelFormat::YCbCr10Bit420v,
+    PixelFormat::YCbCr10Bit420f,
+    PixelFormat::YCbCr10Bit444f,
+    PixelFormat::RGBAHalf,
 ];
 
-#[derive(Copy, Clone, Debug, Default)]
+/// The CoreVideo pixel format of frames.
+///
+/// Serialized as the four character code, for example `"BGRA"`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
 pub enum PixelFormat {
+    /// `BGRA`, 8-bit blue, green, red and alpha.
     ARGB8888,
+    /// `l10r`, 10-bit red, green and blue and 2-bit alpha packed in a little-endian word.
     ARGB2101010,
+    /// `420v`, 8-bit video range luma and interleaved chroma at half resolution.
     #[default]
     YCbCr420v,
+    /// `420f`, like `420v` in full range.
     YCbCr420f,
+    /// `x420`, 10-bit video range luma and interleaved chroma at half resolution, in the high
+    /// bits of 16-bit samples.
+    YCbCr10Bit420v,
+    /// `xf20`, like `x420` in full range.
+    YCbCr10Bit420f,
+    /// `xf44`, 10-bit full range luma and interleaved chroma at full resolution.
+    YCbCr10Bit444f,
+    /// `RGhA`, 16-bit floating point red, green, blue and alpha.
+    RGBAHalf,
+    /// A pixel format without a variant of its own.
+    Other(FourCharCode),
+}
+
+/// The resolution of the chroma of a JPEG image or a YCbCr pixel format, relative to the luma.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChromaSubsampling {
+    /// Full chroma resolution, the sharpest colored text.
+    Yuv444,
+    /// Half the horizontal chroma resolution.
+    Yuv422,
+    /// Half the horizontal and vertical chroma resolution, the smallest files.
+    #[default]
+    Yuv420,
+}
+
+/// What a [`PixelFormat`] stores per plane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelFormatInfo {
+    pub plane_count: usize,
+    pub bits_per_component: u32,
+    /// The chroma resolution of YCbCr formats, `None` for RGB formats.
+    pub chroma_subsampling: Option<ChromaSubsampling>,
+    /// The bytes of one pixel of each plane, where a chroma pixel holds both Cb and Cr.
+    pub bytes_per_pixel: &'static [usize],
 }
 
-impl From<FourCharCode> for PixelFormat {
-    fn from(val: FourCharCode) -> Self {
-        let code_str = val.to_string();
-        match code_str.as_str() {
-            "BGRA" => PixelFormat::ARGB8888,
-            "l10r" => PixelFormat::ARGB2101010,
-            "420v" => PixelFormat::YCbCr420v,
-            "420f" => PixelFormat::YCbCr420f,
-            _ => unreachable!(),
+impl PixelFormat {
+    /// The format of `code`, or [`PixelFormat::Other`] if it has no variant.
+    pub fn from_code(code: FourCharCode) -> Self {
+        PixelFormat::try_from(code).unwrap_or(PixelFormat::Other(code))
+    }
+    pub fn get_code(&self) -> FourCharCode {
+        FourCharCode::from_chars(match self {
+            PixelFormat::ARGB8888 => *b"BGRA",
+            PixelFormat::ARGB2101010 => *b"l10r",
+            PixelFormat::YCbCr420v => *b"420v",
+            PixelFormat::YCbCr420f => *b"420f",
+            PixelFormat::YCbCr10Bit420v => *b"x420",
+            PixelFormat::YCbCr10Bit420f => *b"xf20",
+            PixelFormat::YCbCr10Bit444f => *b"xf44",
+            PixelFormat::RGBAHalf => *b"RGhA",
+            PixelFormat::Other(code) => return *code,
+        })
+    }
+    /// The layout of the format, `None` for [`PixelFormat::Other`].
+    pub fn get_info(&self) -> Option<PixelFormatInfo> {
+        let info = |plane_count, bits_per_component, chroma_subsampling, bytes_per_pixel| {
+            Some(PixelFormatInfo {
+                plane_count,
+                bits_per_component,
+                chroma_subsampling,
+                bytes_per_pixel,
+            })
+        };
+        match self {
+            PixelFormat::ARGB8888 => info(1, 8, None, &[4]),
+            PixelFormat::ARGB2101010 => info(1, 10, None, &[4]),
+            PixelFormat::YCbCr420v | PixelFormat::YCbCr420f => {
+                info(2, 8, Some(ChromaSubsampling::Yuv420), &[1, 2])
+            }
+            PixelFormat::YCbCr10Bit420v | PixelFormat::YCbCr10Bit420f => {
+                info(2, 10, Some(ChromaSubsampling::Yuv420), &[2, 4])
+            }
+            PixelFormat::YCbCr10Bit444f => info(2, 10, Some(ChromaSubsampling::Yuv444), &[2, 4]),
+            PixelFormat::RGBAHalf => info(1, 16, None, &[8]),
+            PixelFormat::Other(_) => None,
         }
     }
 }
+
+/// The error for a four character code that is not one of the [`PIXEL_FORMATS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedPixelFormatError(pub FourCharCode);
+
+impl fmt::Display for UnsupportedPixelFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unsupported pixel format {}", self.0)
+    }
+}
+
+impl Error for UnsupportedPixelFormatError {}
+
+impl From<UnsupportedPixelFormatError> for String {
+    fn from(value: UnsupportedPixelFormatError) -> Self {
+        value.to_string()
+    }
+}
+
+impl TryFrom<FourCharCode> for PixelFormat {
+    type Error = UnsupportedPixelFormatError;
+
+    fn try_from(code: FourCharCode) -> Result<Self, Self::Error> {
+        PIXEL_FORMATS
+            .iter()
+            .copied()
+            .find(|format| format.get_code() == code)
+            .ok_or(UnsupportedPixelFormatError(code))
+    }
+}
 impl From<PixelFormat> for FourCharCode {
     fn from(val: PixelFormat) -> Self {
-        match val {
-            PixelFormat::ARGB8888 => FourCharCode::from_chars(*b"BGRA"),
-            PixelFormat::ARGB2101010 => FourCharCode::from_chars(*b"l10r"),
-            PixelFormat::YCbCr420v => FourCharCode::from_chars(*b"420v"),
-            PixelFormat::YCbCr420f => FourCharCode::from_chars(*b"420f"),
-        }
+        val.get_code()
+    }
+}
+
+impl From<PixelFormat> for String {
+    fn from(format: PixelFormat) -> Self {
+        format.get_code().to_string()
+    }
+}
+
+impl TryFrom<String> for PixelFormat {
+    type Error = String;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        let chars: [u8; 4] = code
+            .as_bytes()
+            .try_into()
+            .ok()
+            .filter(|_| code.is_ascii())
+            .ok_or_else(|| format!("{code:?} is not a four character code"))?;
+        Ok(PixelFormat::from_code(FourCharCode::from_chars(chars)))
     }
 }
 
@@ -55,7 +186,7 @@ pub struct Size {
     pub scales_to_fit: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SCStreamConfiguration {
     //   The width of the output.
     pub width: u32,
@@ -63,9 +194,10 @@ pub struct SCStreamConfiguration {
     pub height: u32,
     // A boolean value that indicates whether to scale the output to fit the configured width and height.
     pub scales_to_fit: bool,
-    // A rectangle that specifies the source area to capture.
+    // A rectangle that specifies the source area to capture, the whole content when empty.
     pub source_rect: CGRect,
-    // A rectangle that specifies a destination into which to write the output.
+    // A rectangle that specifies a destination into which to write the output, the whole output
+    // when empty.
     pub destination_rect: CGRect,
     // A boolean value that determines whether the cursor is visible in the stream.
     pub shows_cursor: bool,
@@ -89,13 +221,13 @@ pub struct SCStreamConfiguration {
     // Configuring Colors
     // A pixel format for sample buffers that a stream outputs.
     pub pixel_format: PixelFormat,
-    // A color matrix to apply to the output surface.
-    pub color_matrix: &'static str,
-    // A color space to use for the output buffer.
-    pub color_space_name: &'static str,
+    // A color matrix to apply to the output surface, or the ScreenCaptureKit default.
+    pub color_matrix: Option<ColorMatrix>,
+    // A color space to use for the output buffer, or the ScreenCaptureKit default.
+    pub color_space_name: Option<ColorSpace>,
     // A background color for the output.
     // Controlling Visibility
-    pub background_color: CGColor,
+    pub background_color: Color,
 }
 
 impl Default for SCStreamConfiguration {
@@ -132,6 +264,429 @@ impl SCStreamConfiguration {
             ..Default::default()
         }
     }
+    /// A builder that starts from the ScreenCaptureKit defaults and validates the configuration.
+    pub fn builder() -> SCStreamConfigurationBuilder {
+        SCStreamConfigurationBuilder::default()
+    }
+
+    /// Returns the fields that differ between `self` and `other`, in declaration order.
+    ///
+    /// Frame intervals are compared as instants, so `1/30` and `2/60` are the same interval.
+    /// An empty result means updating a stream from `self` to `other` changes nothing.
+    pub fn diff(&self, other: &SCStreamConfiguration) -> Vec<ConfigurationField> {
+        [
+            (ConfigurationField::Width, self.width == other.width),
+            (ConfigurationField::Height, self.height == other.height),
+            (
+                ConfigurationField::ScalesToFit,
+                self.scales_to_fit == other.scales_to_fit,
+            ),
+            (
+                ConfigurationField::SourceRect,
+                self.source_rect == other.source_rect,
+            ),
+            (
+                ConfigurationField::DestinationRect,
+                self.destination_rect == other.destination_rect,
+            ),
+            (
+                ConfigurationField::ShowsCursor,
+                self.shows_cursor == other.shows_cursor,
+            ),
+            (
+                ConfigurationField::PreservesAspectRatio,
+                self.preserves_aspect_ratio == other.preserves_aspect_ratio,
+            ),
+            (
+                ConfigurationField::QueueDepth,
+                self.queue_depth == other.queue_depth,
+            ),
+            (
+                ConfigurationField::MinimumFrameInterval,
+                self.minimum_frame_interval == other.minimum_frame_interval,
+            ),
+            (
+                ConfigurationField::CapturesAudio,
+                self.captures_audio == other.captures_audio,
+            ),
+            (
+                ConfigurationField::SampleRate,
+                self.sample_rate == other.sample_rate,
+            ),
+            (
+                ConfigurationField::ChannelCount,
+                self.channel_count == other.channel_count,
+            ),
+            (
+                ConfigurationField::ExcludesCurrentProcessAudio,
+                self.excludes_current_process_audio == other.excludes_current_process_audio,
+            ),
+            (
+                ConfigurationField::PixelFormat,
+                self.pixel_format == other.pixel_format,
+            ),
+            (
+                ConfigurationField::ColorMatrix,
+                self.color_matrix == other.color_matrix,
+            ),
+            (
+                ConfigurationField::ColorSpaceName,
+                self.color_space_name == other.color_space_name,
+            ),
+            (
+                ConfigurationField::BackgroundColor,
+                self.background_color == other.background_color,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, same)| !same)
+        .map(|(field, _)| field)
+        .collect()
+    }
+}
+
+/// A field of [`SCStreamConfiguration`], as reported by [`SCStreamConfiguration::diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigurationField {
+    Width,
+    Height,
+    ScalesToFit,
+    SourceRect,
+    DestinationRect,
+    ShowsCursor,
+    PreservesAspectRatio,
+    QueueDepth,
+    MinimumFrameInterval,
+    CapturesAudio,
+    SampleRate,
+    ChannelCount,
+    ExcludesCurrentProcessAudio,
+    PixelFormat,
+    ColorMatrix,
+    ColorSpaceName,
+    BackgroundColor,
+}
+
+/// The audio sample rates ScreenCaptureKit can capture at.
+pub const SUPPORTED_SAMPLE_RATES: [u32; 4] = [8000, 16000, 24000, 48000];
+/// The range of audio channel counts ScreenCaptureKit can capture.
+pub const SUPPORTED_CHANNEL_COUNTS: std::ops::RangeInclusive<u32> = 1..=2;
+/// The range of frame queue depths ScreenCaptureKit accepts.
+pub const SUPPORTED_QUEUE_DEPTHS: std::ops::RangeInclusive<u32> = 1..=8;
+
+/// One reason an [`SCStreamConfiguration`] is invalid.
+#[derive(Debug, Clone)]
+pub enum ConfigurationViolation {
+    /// The width or height of the output is zero.
+    ZeroDimension {
+        width: u32,
+        height: u32,
+    },
+    /// The pixel format subsamples chroma by two, so the width and height must be even.
+    UnalignedDimension {
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    },
+    QueueDepthOutOfRange(u32),
+    UnsupportedSampleRate(u32),
+    UnsupportedChannelCount(u32),
+    /// The pixel format is not one of the [`PIXEL_FORMATS`].
+    UnsupportedPixelFormat(FourCharCode),
+    /// The minimum frame interval is not a finite, non-negative time.
+    InvalidFrameInterval(CMTime),
+    /// The source rectangle is not finite, has a negative origin or has a negative size.
+    InvalidSourceRect(CGRect),
+    /// The destination rectangle is not finite, has a negative origin or size, or does not fit
+    /// in the output.
+    InvalidDestinationRect {
+        rect: CGRect,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl fmt::Display for ConfigurationViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroDimension { width, height } => {
+                write!(f, "output size {width}x{height} must not be zero")
+            }
+            Self::UnalignedDimension {
+                width,
+                height,
+                pixel_format,
+            } => write!(
+                f,
+                "output size {width}x{height} must be even for {pixel_format:?}"
+            ),
+            Self::QueueDepthOutOfRange(depth) => write!(
+                f,
+                "queue depth {depth} is outside {}..={}",
+                SUPPORTED_QUEUE_DEPTHS.start(),
+                SUPPORTED_QUEUE_DEPTHS.end()
+            ),
+            Self::UnsupportedSampleRate(rate) => write!(
+                f,
+                "sample rate {rate} Hz is not one of {SUPPORTED_SAMPLE_RATES:?}"
+            ),
+            Self::UnsupportedChannelCount(count) => write!(
+                f,
+                "channel count {count} is outside {}..={}",
+                SUPPORTED_CHANNEL_COUNTS.start(),
+                SUPPORTED_CHANNEL_COUNTS.end()
+            ),
+            Self::InvalidFrameInterval(interval) => write!(
+                f,
+                "minimum frame interval {interval:?} is not a finite, non-negative time"
+            ),
+            Self::UnsupportedPixelFormat(code) => {
+                write!(f, "pixel format {code} is not supported")
+            }
+            Self::InvalidSourceRect(rect) => write!(f, "source rect {rect:?} is invalid"),
+            Self::InvalidDestinationRect {
+                rect,
+                width,
+                height,
+            } => write!(
+                f,
+                "destination rect {rect:?} does not fit in the {width}x{height} output"
+            ),
+        }
+    }
+}
+
+/// The error of [`SCStreamConfigurationBuilder::build`], listing every violation it found.
+#[derive(Debug, Clone)]
+pub struct ConfigurationError {
+    pub violations: Vec<ConfigurationViolation>,
+}
+
+impl fmt::Display for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid stream configuration: ")?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfigurationError {}
+
+impl From<ConfigurationError> for String {
+    fn from(value: ConfigurationError) -> Self {
+        value.to_string()
+    }
+}
+
+/// Builds an [`SCStreamConfiguration`], checking it before handing it out.
+///
+/// Unlike [`SCStreamConfiguration::default`] it starts from the values ScreenCaptureKit itself
+/// uses: a queue depth of 3, 48 kHz stereo audio and no minimum frame interval. The size has no
+/// usable default and must be set.
+///
+/// ```
+/// use screencapturekit::sc_stream_configuration::SCStreamConfiguration;
+///
+/// let config = SCStreamConfiguration::builder()
+///     .width(1920)
+///     .height(1080)
+///     .queue_depth(5)
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.queue_depth, 5);
+///
+/// let error = SCStreamConfiguration::builder().queue_depth(0).build().unwrap_err();
+/// assert_eq!(error.violations.len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct SCStreamConfigurationBuilder {
+    config: SCStreamConfiguration,
+}
+
+impl Default for SCStreamConfigurationBuilder {
+    fn default() -> Self {
+        Self {
+            config: SCStreamConfiguration {
+                queue_depth: 3,
+                minimum_frame_interval: CMTime::ZERO,
+                sample_rate: 48000,
+                channel_count: 2,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl SCStreamConfigurationBuilder {
+    pub fn width(mut self, width: u32) -> Self {
+        self.config.width = width;
+        self
+    }
+    pub fn height(mut self, height: u32) -> Self {
+        self.config.height = height;
+        self
+    }
+    pub fn scales_to_fit(mut self, scales_to_fit: bool) -> Self {
+        self.config.scales_to_fit = scales_to_fit;
+        self
+    }
+    pub fn source_rect(mut self, source_rect: CGRect) -> Self {
+        self.config.source_rect = source_rect;
+        self
+    }
+    pub fn destination_rect(mut self, destination_rect: CGRect) -> Self {
+        self.config.destination_rect = destination_rect;
+        self
+    }
+    pub fn shows_cursor(mut self, shows_cursor: bool) -> Self {
+        self.config.shows_cursor = shows_cursor;
+        self
+    }
+    pub fn preserves_aspect_ratio(mut self, preserves_aspect_ratio: bool) -> Self {
+        self.config.preserves_aspect_ratio = preserves_aspect_ratio;
+        self
+    }
+    pub fn queue_depth(mut self, queue_depth: u32) -> Self {
+        self.config.queue_depth = queue_depth;
+        self
+    }
+    pub fn minimum_frame_interval(mut self, minimum_frame_interval: CMTime) -> Self {
+        self.config.minimum_frame_interval = minimum_frame_interval;
+        self
+    }
+    pub fn captures_audio(mut self, captures_audio: bool) -> Self {
+        self.config.captures_audio = captures_audio;
+        self
+    }
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.config.sample_rate = sample_rate;
+        self
+    }
+    pub fn channel_count(mut self, channel_count: u32) -> Self {
+        self.config.channel_count = channel_count;
+        self
+    }
+    pub fn excludes_current_process_audio(mut self, excludes_current_process_audio: bool) -> Self {
+        self.config.excludes_current_process_audio = excludes_current_process_audio;
+        self
+    }
+    pub fn pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.config.pixel_format = pixel_format;
+        self
+    }
+    pub fn color_matrix(mut self, color_matrix: ColorMatrix) -> Self {
+        self.config.color_matrix = Some(color_matrix);
+        self
+    }
+    pub fn color_space_name(mut self, color_space_name: ColorSpace) -> Self {
+        self.config.color_space_name = Some(color_space_name);
+        self
+    }
+    pub fn background_color(mut self, background_color: Color) -> Self {
+        self.config.background_color = background_color;
+        self
+    }
+
+    /// Returns every problem with the configuration, in field order.
+    pub fn validate(&self) -> Vec<ConfigurationViolation> {
+        let config = &self.config;
+        let (width, height) = (config.width, config.height);
+        let mut violations = Vec::new();
+        if width == 0 || height == 0 {
+            violations.push(ConfigurationViolation::ZeroDimension { width, height });
+        } else if config
+            .pixel_format
+            .get_info()
+            .is_some_and(|info| info.chroma_subsampling == Some(ChromaSubsampling::Yuv420))
+            && (width % 2 != 0 || height % 2 != 0)
+        {
+            violations.push(ConfigurationViolation::UnalignedDimension {
+                width,
+                height,
+                pixel_format: config.pixel_format,
+            });
+        }
+        if !config.source_rect.is_empty() && !is_valid_rect(&config.source_rect) {
+            violations.push(ConfigurationViolation::InvalidSourceRect(
+                config.source_rect,
+            ));
+        }
+        let destination = &config.destination_rect;
+        if !destination.is_empty()
+            && (!is_valid_rect(destination)
+                || destination.get_max_x() > f64::from(width)
+                || destination.get_max_y() > f64::from(height))
+        {
+            violations.push(ConfigurationViolation::InvalidDestinationRect {
+                rect: *destination,
+                width,
+                height,
+            });
+        }
+        if !SUPPORTED_QUEUE_DEPTHS.contains(&config.queue_depth) {
+            violations.push(ConfigurationViolation::QueueDepthOutOfRange(
+                config.queue_depth,
+            ));
+        }
+        let interval = config.minimum_frame_interval;
+        if !interval.is_numeric() || interval.value < 0 {
+            violations.push(ConfigurationViolation::InvalidFrameInterval(interval));
+        }
+        if !SUPPORTED_SAMPLE_RATES.contains(&config.sample_rate) {
+            violations.push(ConfigurationViolation::UnsupportedSampleRate(
+                config.sample_rate,
+            ));
+        }
+        if !SUPPORTED_CHANNEL_COUNTS.contains(&config.channel_count) {
+            violations.push(ConfigurationViolation::UnsupportedChannelCount(
+                config.channel_count,
+            ));
+        }
+        if let PixelFormat::Other(code) = config.pixel_format {
+            violations.push(ConfigurationViolation::UnsupportedPixelFormat(code));
+        }
+        violations
+    }
+
+    pub fn build(self) -> Result<SCStreamConfiguration, ConfigurationError> {
+        let violations = self.validate();
+        if violations.is_empty() {
+            Ok(self.config)
+        } else {
+            Err(ConfigurationError { violations })
+        }
+    }
+}
+
+// An empty rectangle means the whole content for the source and the whole output for the
+// destination, so only the others are checked.
+fn is_valid_rect(rect: &CGRect) -> bool {
+    [
+        rect.origin.x,
+        rect.origin.y,
+        rect.size.width,
+        rect.size.height,
+    ]
+    .iter()
+    .all(|v| v.is_finite())
+        && rect.origin.x >= 0.0
+        && rect.origin.y >= 0.0
+        && rect.size.width > 0.0
+        && rect.size.height > 0.0
+}
+
+// ScreenCaptureKit only takes an all-zero rectangle as the whole content or output.
+fn whole_if_empty(rect: CGRect) -> CGRect {
+    if rect.is_empty() {
+        CGRect::ZERO
+    } else {
+        rect
+    }
 }
 
 impl From<SCStreamConfiguration> for UnsafeStreamConfiguration {
@@ -140,13 +695,19 @@ impl From<SCStreamConfiguration> for UnsafeStreamConfiguration {
             width: value.width,
             height: value.height,
             scales_to_fit: value.scales_to_fit as i8,
-            source_rect: value.source_rect,
-            destination_rect: value.destination_rect,
+            source_rect: whole_if_empty(value.source_rect),
+            destination_rect: whole_if_empty(value.destination_rect),
             preserves_aspect_ratio: value.preserves_aspect_ratio as i8,
             pixel_format: value.pixel_format.into(),
-            color_matrix: value.color_matrix.into(),
-            color_space_name: value.color_space_name.into(),
-            background_color: value.background_color,
+            color_matrix: value
+                .color_matrix
+                .map_or("", |matrix| matrix.get_name())
+                .to_owned(),
+            color_space_name: value
+                .color_space_name
+                .map_or("", |space| space.get_name())
+                .to_owned(),
+            background_color: value.background_color.into(),
             shows_cursor: value.shows_cursor as i8,
             queue_depth: value.queue_depth,
             minimum_frame_interval: value.minimum_frame_interval,
@@ -158,10 +719,12 @@ impl From<SCStreamConfiguration> for UnsafeStreamConfiguration {
     }
 }
 
-impl From<SCStreamConfiguration> for Id<UnsafeStreamConfigurationRef> {
-    fn from(value: SCStreamConfiguration) -> Self {
-        let unsafe_config: UnsafeStreamConfiguration = value.into();
-        unsafe_config.into()
+#[cfg(target_os = "macos")]
+impl TryFrom<SCStreamConfiguration> for Id<UnsafeStreamConfigurationRef> {
+    type Error = String;
+
+    fn try_from(value: SCStreamConfiguration) -> Result<Self, Self::Error> {
+        UnsafeStreamConfiguration::from(value).try_into()
     }
 }
 
@@ -173,4 +736,365 @@ mod get_configuration {
     fn test_configuration() {
         SCStreamConfiguration::from_size(100, 100, false);
     }
+
+    use crate::sc_types::geometry::{CGPoint, CGSize};
+    use screencapturekit_sys::stream_configuration::{PropertyRecorder, PropertyValue};
+
+    #[test]
+    fn test_every_field_reaches_the_native_object() {
+        let config = SCStreamConfiguration {
+            width: 640,
+            height: 480,
+            scales_to_fit: true,
+            source_rect: rect(1.0, 2.0, 3.0, 4.0),
+            destination_rect: rect(5.0, 6.0, 7.0, 8.0),
+            shows_cursor: true,
+            preserves_aspect_ratio: false,
+            queue_depth: 6,
+            minimum_frame_interval: CMTime::from_fps(30),
+            captures_audio: true,
+            sample_rate: 24000,
+            channel_count: 1,
+            excludes_current_process_audio: true,
+            pixel_format: PixelFormat::YCbCr420f,
+            color_matrix: Some(ColorMatrix::ItuR709),
+            color_space_name: Some(ColorSpace::Srgb),
+            background_color: Color::from_hex("#336699").unwrap(),
+        };
+        let mut recorder = PropertyRecorder::default();
+        UnsafeStreamConfiguration::from(config)
+            .apply(&mut recorder)
+            .unwrap();
+        let bool = PropertyValue::Bool(0).encoding();
+        let mut expected = vec![
+            ("setWidth:", "Q"),
+            ("setHeight:", "Q"),
+            ("setScalesToFit:", bool),
+            ("setSourceRect:", "{CGRect={CGPoint=dd}{CGSize=dd}}"),
+            ("setDestinationRect:", "{CGRect={CGPoint=dd}{CGSize=dd}}"),
+            ("setShowsCursor:", bool),
+            ("setPreservesAspectRatio:", bool),
+            ("setQueueDepth:", "q"),
+            ("setMinimumFrameInterval:", "{CMTime=qiIq}"),
+            ("setCapturesAudio:", bool),
+            ("setSampleRate:", "q"),
+            ("setChannelCount:", "q"),
+            ("setExcludesCurrentProcessAudio:", bool),
+            ("setPixelFormat:", "I"),
+            ("setColorMatrix:", "@"),
+            ("setColorSpaceName:", "@"),
+            ("setBackgroundColor:", "^{CGColor=}"),
+        ];
+        let mut assigned: Vec<_> = recorder
+            .get_assignments()
+            .iter()
+            .map(|(selector, value)| (*selector, value.encoding()))
+            .collect();
+        expected.sort_unstable();
+        assigned.sort_unstable();
+        assert_eq!(assigned, expected);
+
+        let get_size = |selector| match recorder.get(selector) {
+            Some(PropertyValue::SizeT(v)) => *v,
+            other => panic!("{selector}: {other:?}"),
+        };
+        let get_integer = |selector| match recorder.get(selector) {
+            Some(PropertyValue::NSInteger(v)) => *v,
+            other => panic!("{selector}: {other:?}"),
+        };
+        let get_bool = |selector| match recorder.get(selector) {
+            Some(PropertyValue::Bool(v)) => *v,
+            other => panic!("{selector}: {other:?}"),
+        };
+        assert_eq!((get_size("setWidth:"), get_size("setHeight:")), (640, 480));
+        assert_eq!(get_integer("setQueueDepth:"), 6);
+        assert_eq!(
+            (
+                get_integer("setSampleRate:"),
+                get_integer("setChannelCount:")
+            ),
+            (24000, 1)
+        );
+        assert_eq!(get_bool("setScalesToFit:"), 1);
+        assert_eq!(get_bool("setShowsCursor:"), 1);
+        assert_eq!(get_bool("setPreservesAspectRatio:"), 0);
+        assert_eq!(get_bool("setCapturesAudio:"), 1);
+        assert_eq!(get_bool("setExcludesCurrentProcessAudio:"), 1);
+        assert!(matches!(
+            recorder.get("setSourceRect:"),
+            Some(PropertyValue::Rect(r)) if r.origin.x == 1.0 && r.size.height == 4.0
+        ));
+        assert!(matches!(
+            recorder.get("setDestinationRect:"),
+            Some(PropertyValue::Rect(r)) if r.origin.y == 6.0 && r.size.width == 7.0
+        ));
+        assert!(matches!(
+            recorder.get("setMinimumFrameInterval:"),
+            Some(PropertyValue::Time(t)) if *t == CMTime::from_fps(30)
+        ));
+        assert!(matches!(
+            recorder.get("setPixelFormat:"),
+            Some(PropertyValue::OSType(code)) if code.to_string() == "420f"
+        ));
+        assert!(matches!(
+            recorder.get("setColorMatrix:"),
+            Some(PropertyValue::String(s)) if s == "ITU_R_709_2"
+        ));
+        assert!(matches!(
+            recorder.get("setColorSpaceName:"),
+            Some(PropertyValue::String(s)) if s == "kCGColorSpaceSRGB"
+        ));
+        assert!(matches!(
+            recorder.get("setBackgroundColor:"),
+            Some(PropertyValue::Color([r, g, b, a]))
+                if (*r, *g, *b, *a) == (0.2, 0.4, 0.6, 1.0)
+        ));
+    }
+
+    #[test]
+    fn test_default_leaves_the_queue_depth_unset() {
+        let mut recorder = PropertyRecorder::default();
+        UnsafeStreamConfiguration::from(SCStreamConfiguration::default())
+            .apply(&mut recorder)
+            .unwrap();
+        assert!(recorder.get("setQueueDepth:").is_none());
+        assert!(!recorder
+            .get_assignments()
+            .iter()
+            .any(|(_, value)| matches!(value, PropertyValue::NSInteger(0))));
+    }
+
+    #[test]
+    fn test_empty_rects_are_sent_as_zero() {
+        let config = SCStreamConfiguration {
+            source_rect: rect(10.0, 10.0, 0.0, 0.0),
+            destination_rect: rect(20.0, 30.0, 100.0, 0.0),
+            ..Default::default()
+        };
+        let mut recorder = PropertyRecorder::default();
+        UnsafeStreamConfiguration::from(config)
+            .apply(&mut recorder)
+            .unwrap();
+        for selector in ["setSourceRect:", "setDestinationRect:"] {
+            assert!(matches!(
+                recorder.get(selector),
+                Some(PropertyValue::Rect(rect)) if *rect == CGRect::ZERO
+            ));
+        }
+    }
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> CGRect {
+        CGRect::new(&CGPoint::new(x, y), &CGSize::new(width, height))
+    }
+
+    #[test]
+    fn test_pixel_format_codes() {
+        for format in PIXEL_FORMATS {
+            let code = FourCharCode::from(format);
+            assert_eq!(PixelFormat::try_from(code), Ok(format));
+            assert_eq!(PixelFormat::from_code(code), format);
+            let info = format.get_info().unwrap();
+            assert_eq!(info.bytes_per_pixel.len(), info.plane_count);
+        }
+        let yuvs = FourCharCode::from_chars(*b"yuvs");
+        assert_eq!(
+            PixelFormat::try_from(yuvs),
+            Err(UnsupportedPixelFormatError(yuvs))
+        );
+        assert_eq!(PixelFormat::from_code(yuvs), PixelFormat::Other(yuvs));
+        assert_eq!(FourCharCode::from(PixelFormat::Other(yuvs)), yuvs);
+        assert_eq!(PixelFormat::Other(yuvs).get_info(), None);
+        assert_eq!(
+            String::from(UnsupportedPixelFormatError(yuvs)),
+            "Unsupported pixel format yuvs"
+        );
+
+        let x420 = PixelFormat::from_code(FourCharCode::from_chars(*b"x420"));
+        assert_eq!(x420, PixelFormat::YCbCr10Bit420v);
+        let info = x420.get_info().unwrap();
+        assert_eq!(info.bits_per_component, 10);
+        assert_eq!(info.chroma_subsampling, Some(ChromaSubsampling::Yuv420));
+        assert_eq!(info.bytes_per_pixel, [2, 4]);
+        assert_eq!(
+            PixelFormat::RGBAHalf.get_info().unwrap().bytes_per_pixel,
+            [8]
+        );
+        assert!(SCStreamConfiguration::builder()
+            .width(64)
+            .height(64)
+            .pixel_format(PixelFormat::Other(yuvs))
+            .build()
+            .is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_pixel_format_serde() {
+        assert_eq!(
+            serde_json::to_string(&PixelFormat::RGBAHalf).unwrap(),
+            "\"RGhA\""
+        );
+        assert_eq!(
+            serde_json::from_str::<PixelFormat>("\"yuvs\"").unwrap(),
+            PixelFormat::Other(FourCharCode::from_chars(*b"yuvs"))
+        );
+        assert!(serde_json::from_str::<PixelFormat>("\"yuv\"").is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let config = settings::StreamPreset::ScreenShare1080p30.get_configuration();
+        assert!(config.diff(&config.clone()).is_empty());
+
+        let other = SCStreamConfiguration {
+            width: 1280,
+            height: 720,
+            minimum_frame_interval: CMTime::new(2, 60),
+            source_rect: rect(0.0, 0.0, 100.0, 100.0),
+            color_matrix: Some(ColorMatrix::ItuR601),
+            background_color: Color::WHITE,
+            ..config.clone()
+        };
+        assert_eq!(
+            config.diff(&other),
+            [
+                ConfigurationField::Width,
+                ConfigurationField::Height,
+                ConfigurationField::SourceRect,
+                ConfigurationField::ColorMatrix,
+                ConfigurationField::BackgroundColor,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_builder_defaults() {
+        let config = SCStreamConfiguration::builder()
+            .width(1280)
+            .height(720)
+            .captures_audio(true)
+            .build()
+            .unwrap();
+        assert_eq!((config.width, config.height), (1280, 720));
+        assert_eq!(config.queue_depth, 3);
+        assert_eq!((config.sample_rate, config.channel_count), (48000, 2));
+        assert_eq!(config.minimum_frame_interval, CMTime::ZERO);
+        assert!(config.captures_audio);
+    }
+
+    #[test]
+    fn test_builder_lists_every_violation() {
+        let error = SCStreamConfiguration::builder()
+            .queue_depth(9)
+            .minimum_frame_interval(CMTime::new(-1, 60))
+            .sample_rate(44100)
+            .channel_count(0)
+            .source_rect(rect(0.0, 0.0, f64::NAN, 10.0))
+            .build()
+            .unwrap_err();
+        let violations = &error.violations;
+        assert_eq!(violations.len(), 6, "{error}");
+        assert!(matches!(
+            violations[0],
+            ConfigurationViolation::ZeroDimension {
+                width: 0,
+                height: 0
+            }
+        ));
+        assert!(matches!(
+            violations[1],
+            ConfigurationViolation::InvalidSourceRect(_)
+        ));
+        assert!(matches!(
+            violations[2],
+            ConfigurationViolation::QueueDepthOutOfRange(9)
+        ));
+        assert!(matches!(
+            violations[3],
+            ConfigurationViolation::InvalidFrameInterval(_)
+        ));
+        assert!(matches!(
+            violations[4],
+            ConfigurationViolation::UnsupportedSampleRate(44100)
+        ));
+        assert!(matches!(
+            violations[5],
+            ConfigurationViolation::UnsupportedChannelCount(0)
+        ));
+        let message = String::from(error);
+        assert!(message.contains("queue depth 9"));
+        assert!(message.contains("sample rate 44100 Hz"));
+    }
+
+    #[test]
+    fn test_builder_alignment() {
+        let odd = SCStreamConfiguration::builder().width(101).height(100);
+        assert!(matches!(
+            odd.pixel_format(PixelFormat::YCbCr420v).validate()[..],
+            [ConfigurationViolation::UnalignedDimension { width: 101, .. }]
+        ));
+        let odd = SCStreamConfiguration::builder().width(101).height(100);
+        assert!(matches!(
+            odd.pixel_format(PixelFormat::YCbCr10Bit420f).validate()[..],
+            [ConfigurationViolation::UnalignedDimension { .. }]
+        ));
+        let odd = SCStreamConfiguration::builder().width(101).height(100);
+        assert!(odd
+            .pixel_format(PixelFormat::YCbCr10Bit444f)
+            .build()
+            .is_ok());
+        let odd = SCStreamConfiguration::builder().width(101).height(100);
+        assert!(odd.pixel_format(PixelFormat::ARGB8888).build().is_ok());
+    }
+
+    #[test]
+    fn test_builder_frame_interval() {
+        let builder = || SCStreamConfiguration::builder().width(64).height(64);
+        assert!(builder()
+            .minimum_frame_interval(CMTime::from_fps(30))
+            .build()
+            .is_ok());
+        for interval in [
+            CMTime::INVALID,
+            CMTime::INDEFINITE,
+            CMTime::POSITIVE_INFINITY,
+        ] {
+            assert!(builder().minimum_frame_interval(interval).build().is_err());
+        }
+    }
+
+    #[test]
+    fn test_builder_rects() {
+        let builder = || SCStreamConfiguration::builder().width(200).height(100);
+        assert!(builder()
+            .source_rect(rect(10.0, 10.0, 400.0, 200.0))
+            .destination_rect(rect(0.0, 0.0, 200.0, 100.0))
+            .build()
+            .is_ok());
+        assert!(matches!(
+            builder()
+                .destination_rect(rect(50.0, 0.0, 200.0, 100.0))
+                .validate()[..],
+            [ConfigurationViolation::InvalidDestinationRect {
+                width: 200,
+                height: 100,
+                ..
+            }]
+        ));
+        assert!(matches!(
+            builder()
+                .source_rect(rect(-1.0, 0.0, 10.0, 10.0))
+                .validate()[..],
+            [ConfigurationViolation::InvalidSourceRect(_)]
+        ));
+        // An empty rectangle is the whole output, a negative one is invalid.
+        assert!(builder()
+            .destination_rect(rect(10.0, 10.0, 0.0, 50.0))
+            .build()
+            .is_ok());
+        assert!(builder()
+            .destination_rect(rect(10.0, 10.0, -10.0, 50.0))
+            .build()
+            .is_err());
+    }
 }
@@ -0,0 +1,340 @@
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The matrices ScreenCaptureKit can use to convert RGB to YCbCr output, serialized with their
+/// CoreVideo names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ColorMatrix {
+    #[cfg_attr(feature = "serde", serde(rename = "ITU_R_601_4"))]
+    ItuR601,
+    #[cfg_attr(feature = "serde", serde(rename = "ITU_R_709_2"))]
+    ItuR709,
+    #[cfg_attr(feature = "serde", serde(rename = "ITU_R_2020"))]
+    ItuR2020,
+    #[cfg_attr(feature = "serde", serde(rename = "SMPTE_240M_1995"))]
+    Smpte240M,
+}
+
+impl ColorMatrix {
+    pub const ALL: [ColorMatrix; 4] = [
+        ColorMatrix::ItuR601,
+        ColorMatrix::ItuR709,
+        ColorMatrix::ItuR2020,
+        ColorMatrix::Smpte240M,
+    ];
+
+    /// The value of the `kCVImageBufferYCbCrMatrix` constant.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ColorMatrix::ItuR601 => "ITU_R_601_4",
+            ColorMatrix::ItuR709 => "ITU_R_709_2",
+            ColorMatrix::ItuR2020 => "ITU_R_2020",
+            ColorMatrix::Smpte240M => "SMPTE_240M_1995",
+        }
+    }
+}
+
+impl FromStr for ColorMatrix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|matrix| matrix.get_name() == s)
+            .ok_or_else(|| format!("Unknown color matrix {s:?}"))
+    }
+}
+
+/// The color spaces of ScreenCaptureKit output, serialized with their CoreGraphics names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ColorSpace {
+    #[cfg_attr(feature = "serde", serde(rename = "kCGColorSpaceSRGB"))]
+    Srgb,
+    #[cfg_attr(feature = "serde", serde(rename = "kCGColorSpaceLinearSRGB"))]
+    LinearSrgb,
+    #[cfg_attr(feature = "serde", serde(rename = "kCGColorSpaceExtendedSRGB"))]
+    ExtendedSrgb,
+    #[cfg_attr(feature = "serde", serde(rename = "kCGColorSpaceExtendedLinearSRGB"))]
+    ExtendedLinearSrgb,
+    #[cfg_attr(feature = "serde", serde(rename = "kCGColorSpaceDisplayP3"))]
+    DisplayP3,
+    #[cfg_attr(feature = "serde", serde(rename = "kCGColorSpaceLinearDisplayP3"))]
+    LinearDisplayP3,
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "kCGColorSpaceExtendedLinearDisplayP3")
+    )]
+    ExtendedLinearDisplayP3,
+    #[cfg_attr(feature = "serde", serde(rename = "kCGColorSpaceITUR_709"))]
+    Bt709,
+    #[cfg_attr(feature = "serde", serde(rename = "kCGColorSpaceITUR_2020"))]
+    Bt2020,
+    #[cfg_attr(feature = "serde", serde(rename = "kCGColorSpaceLinearITUR_2020"))]
+    LinearBt2020,
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "kCGColorSpaceExtendedLinearITUR_2020")
+    )]
+    ExtendedLinearBt2020,
+}
+
+impl ColorSpace {
+    pub const ALL: [ColorSpace; 11] = [
+        ColorSpace::Srgb,
+        ColorSpace::LinearSrgb,
+        ColorSpace::ExtendedSrgb,
+        ColorSpace::ExtendedLinearSrgb,
+        ColorSpace::DisplayP3,
+        ColorSpace::LinearDisplayP3,
+        ColorSpace::ExtendedLinearDisplayP3,
+        ColorSpace::Bt709,
+        ColorSpace::Bt2020,
+        ColorSpace::LinearBt2020,
+        ColorSpace::ExtendedLinearBt2020,
+    ];
+
+    /// The value of the `kCGColorSpace` constant, which is also its name.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "kCGColorSpaceSRGB",
+            ColorSpace::LinearSrgb => "kCGColorSpaceLinearSRGB",
+            ColorSpace::ExtendedSrgb => "kCGColorSpaceExtendedSRGB",
+            ColorSpace::ExtendedLinearSrgb => "kCGColorSpaceExtendedLinearSRGB",
+            ColorSpace::DisplayP3 => "kCGColorSpaceDisplayP3",
+            ColorSpace::LinearDisplayP3 => "kCGColorSpaceLinearDisplayP3",
+            ColorSpace::ExtendedLinearDisplayP3 => "kCGColorSpaceExtendedLinearDisplayP3",
+            ColorSpace::Bt709 => "kCGColorSpaceITUR_709",
+            ColorSpace::Bt2020 => "kCGColorSpaceITUR_2020",
+            ColorSpace::LinearBt2020 => "kCGColorSpaceLinearITUR_2020",
+            ColorSpace::ExtendedLinearBt2020 => "kCGColorSpaceExtendedLinearITUR_2020",
+        }
+    }
+    pub fn is_linear(&self) -> bool {
+        matches!(
+            self,
+            ColorSpace::LinearSrgb
+                | ColorSpace::ExtendedLinearSrgb
+                | ColorSpace::LinearDisplayP3
+                | ColorSpace::ExtendedLinearDisplayP3
+                | ColorSpace::LinearBt2020
+                | ColorSpace::ExtendedLinearBt2020
+        )
+    }
+}
+
+impl FromStr for ColorSpace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|space| space.get_name() == s)
+            .ok_or_else(|| format!("Unknown color space {s:?}"))
+    }
+}
+
+/// An RGBA color with components from 0 to 1, which becomes a generic RGB `CGColor` when the
+/// configuration is applied.
+///
+/// It deserializes from a hex string like `"#1e90ff"` or from its components, where `alpha`
+/// defaults to 1.
+///
+/// ```
+/// use screencapturekit::sc_stream_configuration::color::Color;
+///
+/// let color: Color = "#ff800080".parse().unwrap();
+/// assert_eq!(color.red, 1.0);
+/// assert_eq!(color.to_hex(), "#ff800080");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "ColorRepr"))]
+pub struct Color {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    pub alpha: f64,
+}
+
+impl Default for Color {
+    /// Opaque black, the ScreenCaptureKit default.
+    fn default() -> Self {
+        Self::BLACK
+    }
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const CLEAR: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+
+    pub const fn new(red: f64, green: f64, blue: f64, alpha: f64) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+    /// An opaque color.
+    pub const fn rgb(red: f64, green: f64, blue: f64) -> Self {
+        Self::new(red, green, blue, 1.0)
+    }
+
+    /// Parses `RGB`, `RGBA`, `RRGGBB` or `RRGGBBAA` hex digits, with or without a leading `#`.
+    pub fn from_hex(hex: &str) -> Result<Self, String> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let invalid = || format!("Invalid hex color {hex:?}");
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let width = match digits.len() {
+            3 | 4 => 1,
+            6 | 8 => 2,
+            _ => return Err(invalid()),
+        };
+        let components = (0..digits.len() / width)
+            .map(|i| {
+                let value = u8::from_str_radix(&digits[i * width..(i + 1) * width], 16)
+                    .map_err(|_| invalid())?;
+                // A single digit repeats, so `f` is `ff`.
+                let value = if width == 1 { value * 17 } else { value };
+                Ok(f64::from(value) / 255.0)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self::new(
+            components[0],
+            components[1],
+            components[2],
+            components.get(3).copied().unwrap_or(1.0),
+        ))
+    }
+
+    /// `#rrggbb` for opaque colors and `#rrggbbaa` otherwise, rounding to 8 bits per component.
+    pub fn to_hex(&self) -> String {
+        let byte = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let rgb = format!(
+            "#{:02x}{:02x}{:02x}",
+            byte(self.red),
+            byte(self.green),
+            byte(self.blue)
+        );
+        if byte(self.alpha) == 255 {
+            rgb
+        } else {
+            format!("{rgb}{:02x}", byte(self.alpha))
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl From<Color> for [f64; 4] {
+    fn from(color: Color) -> Self {
+        [color.red, color.green, color.blue, color.alpha]
+    }
+}
+
+impl From<[f64; 4]> for Color {
+    fn from([red, green, blue, alpha]: [f64; 4]) -> Self {
+        Self::new(red, green, blue, alpha)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_alpha() -> f64 {
+    1.0
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorRepr {
+    Hex(String),
+    Components {
+        red: f64,
+        green: f64,
+        blue: f64,
+        #[serde(default = "default_alpha")]
+        alpha: f64,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ColorRepr> for Color {
+    type Error = String;
+
+    fn try_from(repr: ColorRepr) -> Result<Self, Self::Error> {
+        match repr {
+            ColorRepr::Hex(hex) => Self::from_hex(&hex),
+            ColorRepr::Components {
+                red,
+                green,
+                blue,
+                alpha,
+            } => Ok(Self::new(red, green, blue, alpha)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(Color::from_hex("#000").unwrap(), Color::BLACK);
+        assert_eq!(Color::from_hex("ffffff").unwrap(), Color::WHITE);
+        assert_eq!(Color::from_hex("#0000").unwrap(), Color::CLEAR);
+        let color = Color::from_hex("#1E90FF80").unwrap();
+        assert_eq!(color.red, 30.0 / 255.0);
+        assert_eq!(color.blue, 1.0);
+        assert_eq!(color.alpha, 128.0 / 255.0);
+        assert_eq!(color.to_hex(), "#1e90ff80");
+        assert_eq!(Color::rgb(1.0, 0.5, 0.0).to_string(), "#ff8000");
+        for invalid in ["", "#12", "#12345", "#gggggg", "#ffé", "#+1ffff"] {
+            assert!(Color::from_hex(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let color: Color = serde_json::from_str("\"#ff0000\"").unwrap();
+        assert_eq!(color, Color::rgb(1.0, 0.0, 0.0));
+        let color: Color = serde_json::from_str(r#"{"red": 0, "green": 1, "blue": 0}"#).unwrap();
+        assert_eq!(color, Color::rgb(0.0, 1.0, 0.0));
+        let json = serde_json::to_string(&Color::CLEAR).unwrap();
+        assert_eq!(serde_json::from_str::<Color>(&json).unwrap(), Color::CLEAR);
+        assert!(serde_json::from_str::<Color>("\"red\"").is_err());
+
+        let matrix: ColorMatrix = serde_json::from_str("\"ITU_R_709_2\"").unwrap();
+        assert_eq!(matrix, ColorMatrix::ItuR709);
+        for space in ColorSpace::ALL {
+            let json = serde_json::to_string(&space).unwrap();
+            assert_eq!(json, format!("\"{}\"", space.get_name()));
+            assert_eq!(space.get_name().parse::<ColorSpace>(), Ok(space));
+        }
+        for matrix in ColorMatrix::ALL {
+            assert_eq!(matrix.get_name().parse::<ColorMatrix>(), Ok(matrix));
+        }
+        assert!(ColorSpace::LinearBt2020.is_linear());
+        assert!(!ColorSpace::DisplayP3.is_linear());
+    }
+}
@@ -0,0 +1,397 @@
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use screencapturekit_sys::os_types::geometry::CGRect;
+
+use super::{
+    color::{Color, ColorMatrix, ColorSpace},
+    ConfigurationError, PixelFormat, SCStreamConfiguration,
+};
+use crate::sc_types::base::CMTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct RectSettings {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl From<RectSettings> for CGRect {
+    fn from(rect: RectSettings) -> Self {
+        CGRect::from_xywh(rect.x, rect.y, rect.width, rect.height)
+    }
+}
+
+impl From<CGRect> for RectSettings {
+    fn from(rect: CGRect) -> Self {
+        Self {
+            x: rect.origin.x,
+            y: rect.origin.y,
+            width: rect.size.width,
+            height: rect.size.height,
+        }
+    }
+}
+
+/// `value / timescale` seconds, like a numeric [`CMTime`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct TimeSettings {
+    pub value: i64,
+    pub timescale: i32,
+}
+
+impl Default for TimeSettings {
+    fn default() -> Self {
+        Self {
+            value: 0,
+            timescale: 1,
+        }
+    }
+}
+
+impl TimeSettings {
+    /// The interval between frames at `fps` frames per second.
+    pub fn from_fps(fps: u32) -> Self {
+        Self {
+            value: 1,
+            timescale: fps as i32,
+        }
+    }
+}
+
+impl From<TimeSettings> for CMTime {
+    fn from(time: TimeSettings) -> Self {
+        CMTime::new(time.value, time.timescale)
+    }
+}
+
+impl TryFrom<CMTime> for TimeSettings {
+    type Error = String;
+
+    fn try_from(time: CMTime) -> Result<Self, Self::Error> {
+        if time.is_numeric() {
+            Ok(Self {
+                value: time.value,
+                timescale: time.timescale,
+            })
+        } else {
+            Err(format!("{time:?} is not a numeric time"))
+        }
+    }
+}
+
+/// A plain-data form of [`SCStreamConfiguration`] that can be stored with serde, for example
+/// in an application's TOML configuration:
+///
+/// ```toml
+/// width = 1920
+/// height = 1080
+/// pixel_format = "420v"
+/// minimum_frame_interval = { value = 1, timescale = 30 }
+/// source_rect = { x = 0, y = 0, width = 3840, height = 2160 }
+/// color_matrix = "ITU_R_709_2"
+/// color_space_name = "kCGColorSpaceSRGB"
+/// background_color = "#1e90ff"
+/// ```
+///
+/// Missing fields take the values of [`SCStreamConfiguration::builder`], so the size is the only
+/// field a usable configuration has to set. A missing rectangle is the whole content or output,
+/// and a missing color matrix or color space keeps the ScreenCaptureKit default.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct StreamSettings {
+    pub width: u32,
+    pub height: u32,
+    pub scales_to_fit: bool,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub source_rect: Option<RectSettings>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub destination_rect: Option<RectSettings>,
+    pub shows_cursor: bool,
+    pub preserves_aspect_ratio: bool,
+    pub queue_depth: u32,
+    pub minimum_frame_interval: TimeSettings,
+    pub captures_audio: bool,
+    pub sample_rate: u32,
+    pub channel_count: u32,
+    pub excludes_current_process_audio: bool,
+    pub pixel_format: PixelFormat,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub color_matrix: Option<ColorMatrix>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub color_space_name: Option<ColorSpace>,
+    pub background_color: Color,
+}
+
+impl Default for StreamSettings {
+    fn default() -> Self {
+        SCStreamConfiguration::builder().config.into()
+    }
+}
+
+impl From<SCStreamConfiguration> for StreamSettings {
+    fn from(config: SCStreamConfiguration) -> Self {
+        let rect = |rect: CGRect| (!rect.is_empty()).then(|| rect.into());
+        Self {
+            width: config.width,
+            height: config.height,
+            scales_to_fit: config.scales_to_fit,
+            source_rect: rect(config.source_rect),
+            destination_rect: rect(config.destination_rect),
+            shows_cursor: config.shows_cursor,
+            preserves_aspect_ratio: config.preserves_aspect_ratio,
+            queue_depth: config.queue_depth,
+            // Anything but a numeric interval is rejected by the builder, and no limit is the
+            // closest plain value.
+            minimum_frame_interval: TimeSettings::try_from(config.minimum_frame_interval)
+                .unwrap_or_default(),
+            captures_audio: config.captures_audio,
+            sample_rate: config.sample_rate,
+            channel_count: config.channel_count,
+            excludes_current_process_audio: config.excludes_current_process_audio,
+            pixel_format: config.pixel_format,
+            color_matrix: config.color_matrix,
+            color_space_name: config.color_space_name,
+            background_color: config.background_color,
+        }
+    }
+}
+
+impl TryFrom<&StreamSettings> for SCStreamConfiguration {
+    type Error = ConfigurationError;
+
+    /// Builds and validates the configuration the settings describe.
+    fn try_from(settings: &StreamSettings) -> Result<Self, Self::Error> {
+        let mut builder = SCStreamConfiguration::builder()
+            .width(settings.width)
+            .height(settings.height)
+            .scales_to_fit(settings.scales_to_fit)
+            .shows_cursor(settings.shows_cursor)
+            .preserves_aspect_ratio(settings.preserves_aspect_ratio)
+            .queue_depth(settings.queue_depth)
+            .minimum_frame_interval(settings.minimum_frame_interval.into())
+            .captures_audio(settings.captures_audio)
+            .sample_rate(settings.sample_rate)
+            .channel_count(settings.channel_count)
+            .excludes_current_process_audio(settings.excludes_current_process_audio)
+            .pixel_format(settings.pixel_format)
+            .background_color(settings.background_color);
+        if let Some(matrix) = settings.color_matrix {
+            builder = builder.color_matrix(matrix);
+        }
+        if let Some(space) = settings.color_space_name {
+            builder = builder.color_space_name(space);
+        }
+        if let Some(rect) = settings.source_rect {
+            builder = builder.source_rect(rect.into());
+        }
+        if let Some(rect) = settings.destination_rect {
+            builder = builder.destination_rect(rect.into());
+        }
+        builder.build()
+    }
+}
+
+impl TryFrom<StreamSettings> for SCStreamConfiguration {
+    type Error = ConfigurationError;
+
+    fn try_from(settings: StreamSettings) -> Result<Self, Self::Error> {
+        Self::try_from(&settings)
+    }
+}
+
+/// Settings for common kinds of capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum StreamPreset {
+    /// 1920×1080 at 30 frames per second in `420v` with the cursor and stereo audio, for
+    /// sending to a video encoder.
+    ScreenShare1080p30,
+    /// 3840×2160 at 60 frames per second in 10-bit `l10r` with Display P3 colors and stereo
+    /// audio, for recording at full quality.
+    Archive4k60,
+    /// 320×180 at 2 frames per second in `BGRA` without cursor or audio, for previews.
+    LowBandwidthThumbnail,
+}
+
+impl StreamPreset {
+    pub const ALL: [StreamPreset; 3] = [
+        StreamPreset::ScreenShare1080p30,
+        StreamPreset::Archive4k60,
+        StreamPreset::LowBandwidthThumbnail,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            StreamPreset::ScreenShare1080p30 => "screen-share-1080p30",
+            StreamPreset::Archive4k60 => "archive-4k60",
+            StreamPreset::LowBandwidthThumbnail => "low-bandwidth-thumbnail",
+        }
+    }
+
+    pub fn get_settings(&self) -> StreamSettings {
+        let defaults = StreamSettings::default();
+        match self {
+            StreamPreset::ScreenShare1080p30 => StreamSettings {
+                width: 1920,
+                height: 1080,
+                scales_to_fit: true,
+                shows_cursor: true,
+                minimum_frame_interval: TimeSettings::from_fps(30),
+                captures_audio: true,
+                excludes_current_process_audio: true,
+                pixel_format: PixelFormat::YCbCr420v,
+                color_matrix: Some(ColorMatrix::ItuR709),
+                color_space_name: Some(ColorSpace::Srgb),
+                ..defaults
+            },
+            StreamPreset::Archive4k60 => StreamSettings {
+                width: 3840,
+                height: 2160,
+                scales_to_fit: true,
+                shows_cursor: true,
+                queue_depth: 6,
+                minimum_frame_interval: TimeSettings::from_fps(60),
+                captures_audio: true,
+                pixel_format: PixelFormat::ARGB2101010,
+                color_space_name: Some(ColorSpace::DisplayP3),
+                ..defaults
+            },
+            StreamPreset::LowBandwidthThumbnail => StreamSettings {
+                width: 320,
+                height: 180,
+                scales_to_fit: true,
+                minimum_frame_interval: TimeSettings::from_fps(2),
+                pixel_format: PixelFormat::ARGB8888,
+                color_space_name: Some(ColorSpace::Srgb),
+                ..defaults
+            },
+        }
+    }
+
+    pub fn get_configuration(&self) -> SCStreamConfiguration {
+        SCStreamConfiguration::try_from(self.get_settings())
+            .expect("presets are valid configurations")
+    }
+}
+
+impl fmt::Display for StreamPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.get_name())
+    }
+}
+
+impl FromStr for StreamPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.get_name() == s)
+            .ok_or_else(|| format!("Unknown stream preset {s:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sc_stream_configuration::ConfigurationViolation;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_toml_round_trip() {
+        let settings = StreamSettings {
+            source_rect: Some(RectSettings {
+                x: 10.0,
+                y: 20.0,
+                width: 640.0,
+                height: 360.0,
+            }),
+            background_color: Color::rgb(0.25, 0.5, 1.0),
+            ..StreamPreset::ScreenShare1080p30.get_settings()
+        };
+        let toml = toml::to_string(&settings).unwrap();
+        assert!(toml.contains("pixel_format = \"420v\""), "{toml}");
+        assert_eq!(toml::from_str::<StreamSettings>(&toml).unwrap(), settings);
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(
+            serde_json::from_str::<StreamSettings>(&json).unwrap(),
+            settings
+        );
+
+        let config = SCStreamConfiguration::try_from(&settings).unwrap();
+        assert_eq!(config.source_rect.size.width, 640.0);
+        assert_eq!(config.minimum_frame_interval, CMTime::from_fps(30));
+        assert_eq!(config.color_matrix, Some(ColorMatrix::ItuR709));
+        assert_eq!(config.background_color.to_hex(), "#4080ff");
+        assert_eq!(StreamSettings::from(config), settings);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_missing_fields_use_builder_defaults() {
+        let settings: StreamSettings = toml::from_str(
+            "width = 1280\nheight = 720\nminimum_frame_interval = { value = 1, timescale = 24 }",
+        )
+        .unwrap();
+        let config = SCStreamConfiguration::try_from(settings).unwrap();
+        assert_eq!((config.width, config.height), (1280, 720));
+        assert_eq!(config.queue_depth, 3);
+        assert_eq!((config.sample_rate, config.channel_count), (48000, 2));
+        assert_eq!(config.pixel_format, PixelFormat::ARGB8888);
+        assert_eq!(config.minimum_frame_interval, CMTime::from_fps(24));
+        assert_eq!(config.color_space_name, None);
+        assert_eq!(config.background_color, Color::BLACK);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_invalid_toml() {
+        assert!(toml::from_str::<StreamSettings>("pixel_format = \"yuv\"").is_err());
+        assert!(toml::from_str::<StreamSettings>("frame_rate = 30").is_err());
+        assert!(toml::from_str::<StreamSettings>("color_matrix = \"ITU_R_709\"").is_err());
+        assert!(toml::from_str::<StreamSettings>("background_color = \"#12\"").is_err());
+        assert_eq!(
+            toml::from_str::<std::collections::HashMap<String, StreamPreset>>(
+                "preset = \"low-bandwidth-thumbnail\""
+            )
+            .unwrap()["preset"],
+            StreamPreset::LowBandwidthThumbnail
+        );
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let error = SCStreamConfiguration::try_from(StreamSettings::default()).unwrap_err();
+        assert!(matches!(
+            error.violations[..],
+            [ConfigurationViolation::ZeroDimension {
+                width: 0,
+                height: 0
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_presets() {
+        for preset in StreamPreset::ALL {
+            assert_eq!(preset.get_name().parse::<StreamPreset>(), Ok(preset));
+            let config = preset.get_configuration();
+            assert!(config.width > 0 && config.height > 0);
+        }
+        let config = StreamPreset::Archive4k60.get_configuration();
+        assert_eq!((config.width, config.height), (3840, 2160));
+        assert_eq!(config.minimum_frame_interval.get_seconds(), 1.0 / 60.0);
+        assert_eq!(config.pixel_format, PixelFormat::ARGB2101010);
+        assert!("8k120".parse::<StreamPreset>().is_err());
+    }
+}
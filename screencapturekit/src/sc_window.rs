@@ -1,10 +1,14 @@
+use screencapturekit_sys::os_types::geometry::CGRect;
+#[cfg(target_os = "macos")]
 use screencapturekit_sys::{os_types::rc::ShareId, shareable_content::UnsafeSCWindow};
 
 use crate::sc_running_application::SCRunningApplication;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SCWindow {
-    pub(crate) _unsafe_ref: ShareId<UnsafeSCWindow>,
+    #[cfg(target_os = "macos")]
+    pub(crate) _unsafe_ref: Option<ShareId<UnsafeSCWindow>>,
+    pub frame: CGRect,
     pub width: u32,
     pub height: u32,
     pub title: Option<String>,
@@ -15,11 +19,13 @@ pub struct SCWindow {
     pub is_on_screen: bool,
 }
 
+#[cfg(target_os = "macos")]
 impl From<ShareId<UnsafeSCWindow>> for SCWindow {
     fn from(unsafe_ref: ShareId<UnsafeSCWindow>) -> Self {
         let frame = unsafe_ref.get_frame();
         SCWindow {
             title: unsafe_ref.get_title(),
+            frame,
             width: frame.size.width as u32,
             height: frame.size.height as u32,
             window_id: unsafe_ref.get_window_id(),
@@ -29,7 +35,7 @@ impl From<ShareId<UnsafeSCWindow>> for SCWindow {
             owning_application: unsafe_ref
                 .get_owning_application()
                 .map(SCRunningApplication::from),
-            _unsafe_ref: unsafe_ref,
+            _unsafe_ref: Some(unsafe_ref),
         }
     }
 }
@@ -0,0 +1,23 @@
+#[cfg(feature = "fixtures")]
+pub mod fixture;
+
+use crate::{platform::CaptureError, sc_shareable_content::SCShareableContent};
+
+/// A source of the displays, windows and applications that are available for capture.
+///
+/// Code that picks content through this trait can be tested against a
+/// [`FixtureShareableContentProvider`](fixture::FixtureShareableContentProvider) instead of
+/// the live system.
+pub trait ShareableContentProvider {
+    fn get_shareable_content(&self) -> Result<SCShareableContent, CaptureError>;
+}
+
+/// Returns the live content from ScreenCaptureKit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NativeShareableContentProvider;
+
+impl ShareableContentProvider for NativeShareableContentProvider {
+    fn get_shareable_content(&self) -> Result<SCShareableContent, CaptureError> {
+        SCShareableContent::try_current()
+    }
+}
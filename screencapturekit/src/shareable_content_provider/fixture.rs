@@ -0,0 +1,355 @@
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+use screencapturekit_sys::os_types::geometry::{CGPoint, CGRect, CGSize};
+
+use super::ShareableContentProvider;
+use crate::{
+    platform::CaptureError, sc_display::SCDisplay, sc_running_application::SCRunningApplication,
+    sc_shareable_content::SCShareableContent, sc_window::SCWindow,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RectFixture {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl From<&RectFixture> for CGRect {
+    fn from(rect: &RectFixture) -> Self {
+        CGRect::new(
+            &CGPoint::new(rect.x, rect.y),
+            &CGSize::new(rect.width, rect.height),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DisplayFixture {
+    display_id: u32,
+    frame: RectFixture,
+    /// Defaults to the width of the frame.
+    width: Option<u32>,
+    /// Defaults to the height of the frame.
+    height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApplicationFixture {
+    process_id: i32,
+    bundle_identifier: Option<String>,
+    application_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WindowFixture {
+    window_id: u32,
+    title: Option<String>,
+    frame: RectFixture,
+    #[serde(default)]
+    window_layer: u32,
+    #[serde(default = "default_is_on_screen")]
+    is_on_screen: bool,
+    #[serde(default)]
+    is_active: bool,
+    /// The process id of one of the applications of the fixture.
+    owning_application: Option<i32>,
+}
+
+fn default_is_on_screen() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContentFixture {
+    #[serde(default)]
+    displays: Vec<DisplayFixture>,
+    #[serde(default)]
+    windows: Vec<WindowFixture>,
+    #[serde(default)]
+    applications: Vec<ApplicationFixture>,
+}
+
+/// Returns the displays, windows and applications described by a JSON or TOML fixture.
+///
+/// A fixture has optional `displays`, `windows` and `applications` lists, for example in TOML:
+///
+/// ```toml
+/// [[displays]]
+/// display_id = 1
+/// frame = { x = 0, y = 0, width = 1920, height = 1080 }
+///
+/// [[applications]]
+/// process_id = 42
+/// bundle_identifier = "com.apple.Safari"
+/// application_name = "Safari"
+///
+/// [[windows]]
+/// window_id = 7
+/// title = "Start Page"
+/// frame = { x = 0, y = 25, width = 1280, height = 800 }
+/// window_layer = 0
+/// is_on_screen = true
+/// is_active = true
+/// owning_application = 42
+/// ```
+///
+/// A display's `width` and `height` default to the size of its frame, a window is on screen
+/// unless `is_on_screen` is false, and `owning_application` refers to the `process_id` of an
+/// application. The content it returns can't be used to create an
+/// [`SCContentFilter`](crate::sc_content_filter::SCContentFilter) on macOS.
+#[derive(Debug, Clone, Default)]
+pub struct FixtureShareableContentProvider {
+    fixture: ContentFixture,
+}
+
+impl FixtureShareableContentProvider {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let fixture = serde_json::from_str(json)
+            .map_err(|e| format!("Could not parse the JSON fixture: {e}"))?;
+        Self::new(fixture)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, String> {
+        let fixture =
+            toml::from_str(toml).map_err(|e| format!("Could not parse the TOML fixture: {e}"))?;
+        Self::new(fixture)
+    }
+
+    /// Loads a fixture file, choosing the format by its `json` or `toml` extension.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            Some("toml") => Self::from_toml(&contents),
+            _ => Err(format!(
+                "Unknown fixture format for {}, expected a .json or .toml file",
+                path.display()
+            )),
+        }
+    }
+
+    fn new(fixture: ContentFixture) -> Result<Self, String> {
+        for window in &fixture.windows {
+            if let Some(process_id) = window.owning_application {
+                if !fixture
+                    .applications
+                    .iter()
+                    .any(|application| application.process_id == process_id)
+                {
+                    return Err(format!(
+                        "Window {} is owned by process {process_id}, which is not an application of the fixture",
+                        window.window_id
+                    ));
+                }
+            }
+        }
+        Ok(Self { fixture })
+    }
+}
+
+fn application(fixture: &ApplicationFixture) -> SCRunningApplication {
+    SCRunningApplication {
+        #[cfg(target_os = "macos")]
+        _unsafe_ref: None,
+        process_id: fixture.process_id,
+        bundle_identifier: fixture.bundle_identifier.clone(),
+        application_name: fixture.application_name.clone(),
+    }
+}
+
+impl ShareableContentProvider for FixtureShareableContentProvider {
+    fn get_shareable_content(&self) -> Result<SCShareableContent, CaptureError> {
+        let applications: Vec<SCRunningApplication> =
+            self.fixture.applications.iter().map(application).collect();
+        let windows = self
+            .fixture
+            .windows
+            .iter()
+            .map(|window| SCWindow {
+                #[cfg(target_os = "macos")]
+                _unsafe_ref: None,
+                frame: CGRect::from(&window.frame),
+                width: window.frame.width as u32,
+                height: window.frame.height as u32,
+                title: window.title.clone(),
+                owning_application: window.owning_application.and_then(|process_id| {
+                    applications
+                        .iter()
+                        .find(|application| application.process_id == process_id)
+                        .cloned()
+                }),
+                window_id: window.window_id,
+                window_layer: window.window_layer,
+                is_active: window.is_active,
+                is_on_screen: window.is_on_screen,
+            })
+            .collect();
+        let displays = self
+            .fixture
+            .displays
+            .iter()
+            .map(|display| SCDisplay {
+                #[cfg(target_os = "macos")]
+                _unsafe_ref: None,
+                display_id: display.display_id,
+                frame: CGRect::from(&display.frame),
+                width: display.width.unwrap_or(display.frame.width as u32),
+                height: display.height.unwrap_or(display.frame.height as u32),
+            })
+            .collect();
+        Ok(SCShareableContent {
+            #[cfg(target_os = "macos")]
+            _unsafe_ref: None,
+            windows,
+            applications,
+            displays,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{
+        "displays": [
+            { "display_id": 1, "frame": { "x": 0, "y": 0, "width": 1512, "height": 982 } },
+            {
+                "display_id": 2,
+                "frame": { "x": 1512, "y": 0, "width": 2560, "height": 1440 },
+                "width": 5120,
+                "height": 2880
+            }
+        ],
+        "applications": [
+            { "process_id": 42, "bundle_identifier": "com.apple.Safari", "application_name": "Safari" }
+        ],
+        "windows": [
+            {
+                "window_id": 7,
+                "title": "Start Page",
+                "frame": { "x": 10.5, "y": 25, "width": 1280, "height": 800 },
+                "window_layer": 0,
+                "is_active": true,
+                "owning_application": 42
+            },
+            {
+                "window_id": 8,
+                "frame": { "x": 0, "y": 0, "width": 1512, "height": 24 },
+                "window_layer": 25,
+                "is_on_screen": false
+            }
+        ]
+    }"#;
+
+    const TOML: &str = r#"
+        [[displays]]
+        display_id = 1
+        frame = { x = 0, y = 0, width = 1512, height = 982 }
+
+        [[displays]]
+        display_id = 2
+        frame = { x = 1512, y = 0, width = 2560, height = 1440 }
+        width = 5120
+        height = 2880
+
+        [[applications]]
+        process_id = 42
+        bundle_identifier = "com.apple.Safari"
+        application_name = "Safari"
+
+        [[windows]]
+        window_id = 7
+        title = "Start Page"
+        frame = { x = 10.5, y = 25, width = 1280, height = 800 }
+        window_layer = 0
+        is_active = true
+        owning_application = 42
+
+        [[windows]]
+        window_id = 8
+        frame = { x = 0, y = 0, width = 1512, height = 24 }
+        window_layer = 25
+        is_on_screen = false
+    "#;
+
+    fn assert_content(content: SCShareableContent) {
+        assert_eq!(content.displays.len(), 2);
+        assert_eq!(content.displays[0].width, 1512);
+        assert_eq!(content.displays[1].frame.origin.x, 1512.0);
+        assert_eq!(content.displays[1].width, 5120);
+
+        let safari = &content.applications[0];
+        assert_eq!(safari.process_id, 42);
+        assert_eq!(safari.application_name.as_deref(), Some("Safari"));
+
+        let window = &content.windows[0];
+        assert_eq!(window.title.as_deref(), Some("Start Page"));
+        assert_eq!(window.frame.origin.x, 10.5);
+        assert_eq!((window.width, window.height), (1280, 800));
+        assert!(window.is_active && window.is_on_screen);
+        let owner = window.owning_application.as_ref().unwrap();
+        assert_eq!(owner.bundle_identifier.as_deref(), Some("com.apple.Safari"));
+
+        let menu_bar = &content.windows[1];
+        assert_eq!(menu_bar.title, None);
+        assert_eq!(menu_bar.window_layer, 25);
+        assert!(!menu_bar.is_active && !menu_bar.is_on_screen);
+        assert!(menu_bar.owning_application.is_none());
+    }
+
+    #[test]
+    fn test_json_fixture() {
+        let provider = FixtureShareableContentProvider::from_json(JSON).unwrap();
+        assert_content(provider.get_shareable_content().unwrap());
+    }
+
+    #[test]
+    fn test_toml_fixture() {
+        let provider = FixtureShareableContentProvider::from_toml(TOML).unwrap();
+        assert_content(provider.get_shareable_content().unwrap());
+    }
+
+    #[test]
+    fn test_fixture_file() {
+        let path = std::env::temp_dir().join(format!(
+            "screencapturekit-fixture-{}.toml",
+            std::process::id()
+        ));
+        fs::write(&path, TOML).unwrap();
+        let provider = FixtureShareableContentProvider::from_file(&path);
+        fs::remove_file(&path).unwrap();
+        assert_content(provider.unwrap().get_shareable_content().unwrap());
+
+        assert!(FixtureShareableContentProvider::from_file("content.yaml").is_err());
+    }
+
+    #[test]
+    fn test_invalid_fixtures() {
+        let unknown_owner = r#"{
+            "windows": [
+                {
+                    "window_id": 1,
+                    "frame": { "x": 0, "y": 0, "width": 1, "height": 1 },
+                    "owning_application": 7
+                }
+            ]
+        }"#;
+        assert!(FixtureShareableContentProvider::from_json(unknown_owner).is_err());
+        assert!(FixtureShareableContentProvider::from_toml("[[displays]]\nid = 1").is_err());
+
+        let empty = FixtureShareableContentProvider::from_json("{}").unwrap();
+        assert!(empty.get_shareable_content().unwrap().displays.is_empty());
+    }
+}
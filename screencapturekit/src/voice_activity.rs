@@ -0,0 +1,558 @@
+//! Silence and voice-activity detection on captured audio.
+//!
+//! A [`VoiceActivityDetector`] splits a stream of audio frames into alternating speech and
+//! silence segments. The audio is mixed down to mono and cut into windows of about 20 ms, and a
+//! window counts as speech when it is loud enough, most of its energy is in the speech band and
+//! its spectrum is not as flat as noise. A hangover keeps speech going through short pauses
+//! between words.
+
+use std::{f64::consts::PI, ops::Range};
+
+use screencapturekit_sys::os_types::base::CMTime;
+
+use crate::audio_frame::AudioFrame;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoiceActivityOptions {
+    /// The length of the analysis windows in seconds, rounded up to a power of two of samples.
+    pub window_duration: f64,
+    /// Windows with a lower RMS level in dBFS are silent.
+    pub energy_threshold: f32,
+    /// The lowest and highest frequency of speech in Hz.
+    pub speech_band: (f32, f32),
+    /// The share of the energy of a window that has to be in the speech band.
+    pub min_speech_band_ratio: f32,
+    /// The largest spectral flatness of the speech band, from 0.0 for a pure tone to 1.0 for
+    /// white noise.
+    pub max_spectral_flatness: f32,
+    /// How long in seconds speech has to go on before a speech segment starts.
+    pub min_speech_duration: f64,
+    /// How long in seconds a speech segment goes on after the last speech.
+    pub hangover: f64,
+}
+
+impl Default for VoiceActivityOptions {
+    fn default() -> Self {
+        Self {
+            window_duration: 0.02,
+            energy_threshold: -45.0,
+            speech_band: (300.0, 3400.0),
+            min_speech_band_ratio: 0.6,
+            max_spectral_flatness: 0.4,
+            min_speech_duration: 0.06,
+            hangover: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    Speech,
+    Silence,
+}
+
+/// A stretch of the stream from `start` up to `end`.
+#[derive(Debug, Clone, Copy)]
+pub struct ActivitySegment {
+    pub activity: Activity,
+    pub start: CMTime,
+    pub end: CMTime,
+}
+
+/// Transforms `re` and `im` in place with a radix-2 FFT, where the length is a power of two.
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f64;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (w_im, w_re) = (angle * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + length / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+/// Splits a stream of audio frames into speech and silence.
+///
+/// Segments are returned once they end, so a silence segment is returned as soon as speech
+/// starts. The timestamps are derived from the timestamp of the first frame and the number of
+/// samples since, like those of [`crate::resampler::Resampler`].
+pub struct VoiceActivityDetector {
+    options: VoiceActivityOptions,
+    sample_rate: f64,
+    window_length: usize,
+    /// The Hann window applied before the FFT.
+    taper: Vec<f64>,
+    /// The bins of the speech band.
+    band: Range<usize>,
+    min_speech_windows: u64,
+    hangover_windows: u64,
+    /// The mono samples of the current window.
+    samples: Vec<f64>,
+    first_timestamp: Option<CMTime>,
+    /// The number of complete windows so far.
+    windows: u64,
+    activity: Activity,
+    /// The window the current segment started at.
+    segment_start: u64,
+    /// The number of windows in a row that disagree with `activity`.
+    run: u64,
+}
+
+impl VoiceActivityDetector {
+    pub fn new(sample_rate: f64, options: VoiceActivityOptions) -> Result<Self, String> {
+        if !(sample_rate > 0.0 && sample_rate.is_finite()) {
+            return Err(format!("Can't detect voice activity at {sample_rate} Hz"));
+        }
+        if !(options.window_duration > 0.0
+            && options.min_speech_duration >= 0.0
+            && options.hangover >= 0.0)
+        {
+            return Err(format!("Invalid durations in {options:?}"));
+        }
+        let (low, high) = options.speech_band;
+        if !(0.0..high).contains(&low) {
+            return Err(format!("Invalid speech band from {low} Hz to {high} Hz"));
+        }
+        let window_length = ((sample_rate * options.window_duration).ceil() as usize)
+            .max(2)
+            .next_power_of_two();
+        let window_seconds = window_length as f64 / sample_rate;
+        let bin = |frequency: f32| {
+            ((f64::from(frequency) * window_length as f64 / sample_rate).round() as usize)
+                .clamp(1, window_length / 2)
+        };
+        Ok(Self {
+            options,
+            sample_rate,
+            window_length,
+            taper: (0..window_length)
+                .map(|index| 0.5 - 0.5 * (2.0 * PI * index as f64 / window_length as f64).cos())
+                .collect(),
+            band: bin(low)..bin(high) + 1,
+            min_speech_windows: ((options.min_speech_duration / window_seconds).ceil() as u64)
+                .max(1),
+            hangover_windows: (options.hangover / window_seconds).ceil() as u64,
+            samples: Vec::with_capacity(window_length),
+            first_timestamp: None,
+            windows: 0,
+            activity: Activity::Silence,
+            segment_start: 0,
+            run: 0,
+        })
+    }
+
+    pub fn get_options(&self) -> &VoiceActivityOptions {
+        &self.options
+    }
+    /// The length of the analysis windows in seconds.
+    pub fn get_window_duration(&self) -> f64 {
+        self.window_length as f64 / self.sample_rate
+    }
+    /// The activity of the current segment.
+    pub fn get_activity(&self) -> Activity {
+        self.activity
+    }
+
+    /// Analyzes the next frame of the stream and returns the segments that ended in it.
+    pub fn process(&mut self, frame: &AudioFrame) -> Result<Vec<ActivitySegment>, String> {
+        if frame.get_sample_rate() != self.sample_rate {
+            return Err(format!(
+                "Expected audio at {} Hz, got {} Hz",
+                self.sample_rate,
+                frame.get_sample_rate()
+            ));
+        }
+        self.first_timestamp
+            .get_or_insert(frame.get_presentation_timestamp());
+        let channel_count = frame.get_channel_count();
+        let mut segments = Vec::new();
+        for index in 0..frame.get_frame_count() {
+            let sum: f64 = (0..channel_count)
+                .filter_map(|channel| frame.get_sample_f64(channel, index))
+                .sum();
+            self.samples.push(sum / channel_count as f64);
+            if self.samples.len() == self.window_length {
+                let is_speech = self.is_speech();
+                self.samples.clear();
+                segments.extend(self.advance(is_speech));
+            }
+        }
+        Ok(segments)
+    }
+
+    /// Ends the current segment at the end of the audio so far and starts over.
+    ///
+    /// Returns `None` if no audio was given since the start or the last flush.
+    pub fn flush(&mut self) -> Option<ActivitySegment> {
+        let end_sample = self.windows * self.window_length as u64 + self.samples.len() as u64;
+        let start_sample = self.segment_start * self.window_length as u64;
+        let segment = (end_sample > start_sample).then(|| ActivitySegment {
+            activity: self.activity,
+            start: self.timestamp(start_sample),
+            end: self.timestamp(end_sample),
+        });
+        self.reset();
+        segment
+    }
+
+    /// Forgets all audio, for example after a gap in the stream.
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.first_timestamp = None;
+        self.windows = 0;
+        self.activity = Activity::Silence;
+        self.segment_start = 0;
+        self.run = 0;
+    }
+
+    /// Moves past a window and returns the segment that ended before it, if any.
+    fn advance(&mut self, is_speech: bool) -> Option<ActivitySegment> {
+        let window = self.windows;
+        self.windows += 1;
+        let agrees = is_speech == (self.activity == Activity::Speech);
+        if agrees {
+            self.run = 0;
+            return None;
+        }
+        self.run += 1;
+        // the first window of the run starts speech, and silence starts after the hangover
+        let (limit, boundary) = match self.activity {
+            Activity::Silence => (self.min_speech_windows, window + 1 - self.run),
+            Activity::Speech => (self.hangover_windows + 1, window),
+        };
+        if self.run < limit {
+            return None;
+        }
+        let ended = ActivitySegment {
+            activity: self.activity,
+            start: self.timestamp(self.segment_start * self.window_length as u64),
+            end: self.timestamp(boundary * self.window_length as u64),
+        };
+        self.activity = match self.activity {
+            Activity::Silence => Activity::Speech,
+            Activity::Speech => Activity::Silence,
+        };
+        self.segment_start = boundary;
+        self.run = 0;
+        (boundary > 0).then_some(ended)
+    }
+
+    /// Decides whether the samples of the current window are speech.
+    fn is_speech(&self) -> bool {
+        let mean_square = self
+            .samples
+            .iter()
+            .map(|sample| sample * sample)
+            .sum::<f64>()
+            / self.samples.len() as f64;
+        if 10.0 * mean_square.log10() < f64::from(self.options.energy_threshold) {
+            return false;
+        }
+        let mut re: Vec<f64> = self
+            .samples
+            .iter()
+            .zip(&self.taper)
+            .map(|(sample, taper)| sample * taper)
+            .collect();
+        let mut im = vec![0.0; self.window_length];
+        fft(&mut re, &mut im);
+        // leave out DC, which carries no sound
+        let power: Vec<f64> = (1..=self.window_length / 2)
+            .map(|bin| re[bin] * re[bin] + im[bin] * im[bin])
+            .collect();
+        let total: f64 = power.iter().sum();
+        let band = &power[self.band.start - 1..self.band.end - 1];
+        let band_total: f64 = band.iter().sum();
+        if total <= 0.0 || band_total / total < f64::from(self.options.min_speech_band_ratio) {
+            return false;
+        }
+        let arithmetic_mean = band_total / band.len() as f64;
+        let geometric_mean =
+            (band.iter().map(|power| (power + 1e-20).ln()).sum::<f64>() / band.len() as f64).exp();
+        geometric_mean / arithmetic_mean <= f64::from(self.options.max_spectral_flatness)
+    }
+
+    /// The timestamp of sample `position` of the stream.
+    fn timestamp(&self, position: u64) -> CMTime {
+        let first = self.first_timestamp.unwrap_or_default();
+        if first.timescale <= 0 {
+            return first;
+        }
+        let offset = position as f64 * f64::from(first.timescale) / self.sample_rate;
+        CMTime {
+            value: first.value + offset.round() as i64,
+            ..first
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use screencapturekit_sys::{
+        audio_buffer::CopiedAudioBuffer,
+        cm_format_description_ref::{
+            kAudioFormatFlagIsPacked, kAudioFormatFlagIsSignedInteger, kAudioFormatLinearPCM,
+            AudioStreamBasicDescription,
+        },
+    };
+
+    use super::*;
+
+    const RATE: f64 = 48000.0;
+
+    /// A vowel-like sound: harmonics of 150 Hz, strongest around 700 Hz, fading in and out at
+    /// 4 Hz like syllables.
+    fn voice(seconds: f64, amplitude: f64) -> Vec<f64> {
+        (0..(seconds * RATE) as usize)
+            .map(|index| {
+                let time = index as f64 / RATE;
+                let harmonics: f64 = (1..=20)
+                    .map(|harmonic| {
+                        let frequency = 150.0 * harmonic as f64;
+                        let formant = (-((frequency - 700.0) / 600.0).powi(2)).exp();
+                        formant * (2.0 * PI * frequency * time).sin()
+                    })
+                    .sum();
+                let syllables = 0.6 + 0.4 * (2.0 * PI * 4.0 * time).sin();
+                amplitude * syllables * harmonics / 3.0
+            })
+            .collect()
+    }
+
+    /// Uniform noise between -`amplitude` and `amplitude` from a fixed seed.
+    fn noise(seconds: f64, amplitude: f64) -> Vec<f64> {
+        let mut state = 0x9e37_79b9_u32;
+        (0..(seconds * RATE) as usize)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                amplitude * (2.0 * f64::from(state) / f64::from(u32::MAX) - 1.0)
+            })
+            .collect()
+    }
+
+    fn tone(seconds: f64, frequency: f64, amplitude: f64) -> Vec<f64> {
+        (0..(seconds * RATE) as usize)
+            .map(|index| amplitude * (2.0 * PI * frequency * index as f64 / RATE).sin())
+            .collect()
+    }
+
+    /// Encodes mono samples as interleaved 16-bit stereo PCM, as a stream would deliver it,
+    /// in buffers of 10 ms.
+    fn canned_pcm(samples: &[f64]) -> Vec<AudioFrame> {
+        let description = AudioStreamBasicDescription {
+            sample_rate: RATE,
+            format_id: kAudioFormatLinearPCM,
+            format_flags: kAudioFormatFlagIsSignedInteger | kAudioFormatFlagIsPacked,
+            bytes_per_packet: 4,
+            frames_per_packet: 1,
+            bytes_per_frame: 4,
+            channels_per_frame: 2,
+            bits_per_channel: 16,
+            reserved: 0,
+        };
+        samples
+            .chunks(480)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let data = chunk
+                    .iter()
+                    .flat_map(|sample| {
+                        let sample = (sample * 32767.0).round() as i16;
+                        [sample, sample]
+                    })
+                    .flat_map(i16::to_le_bytes)
+                    .collect();
+                let timestamp = CMTime {
+                    value: 1000 + index as i64 * 480,
+                    timescale: 48000,
+                    ..Default::default()
+                };
+                let buffers = [CopiedAudioBuffer {
+                    number_channels: 2,
+                    data,
+                }];
+                AudioFrame::from_buffers(&description, &buffers, timestamp).unwrap()
+            })
+            .collect()
+    }
+
+    fn seconds(time: CMTime) -> f64 {
+        (time.value - 1000) as f64 / f64::from(time.timescale)
+    }
+
+    fn detect(samples: &[f64], options: VoiceActivityOptions) -> Vec<(Activity, f64, f64)> {
+        let mut detector = VoiceActivityDetector::new(RATE, options).unwrap();
+        let mut segments = Vec::new();
+        for frame in canned_pcm(samples) {
+            segments.extend(detector.process(&frame).unwrap());
+        }
+        segments.extend(detector.flush());
+        segments
+            .into_iter()
+            .map(|segment| {
+                (
+                    segment.activity,
+                    seconds(segment.start),
+                    seconds(segment.end),
+                )
+            })
+            .collect()
+    }
+
+    fn assert_segments(actual: &[(Activity, f64, f64)], expected: &[(Activity, f64, f64)]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?}");
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert_eq!(actual.0, expected.0, "{actual:?}");
+            assert!(
+                (actual.1 - expected.1).abs() < 0.03 && (actual.2 - expected.2).abs() < 0.03,
+                "{actual:?} is not {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_speech_between_silence() {
+        let mut samples = noise(0.5, 0.0005);
+        samples.extend(voice(1.0, 0.3));
+        samples.extend(noise(1.0, 0.0005));
+        let segments = detect(&samples, VoiceActivityOptions::default());
+        assert_segments(
+            &segments,
+            &[
+                (Activity::Silence, 0.0, 0.5),
+                (Activity::Speech, 0.5, 1.5 + 0.32),
+                (Activity::Silence, 1.5 + 0.32, 2.5),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_hangover_bridges_pauses() {
+        let mut samples = voice(0.5, 0.3);
+        samples.extend(vec![0.0; 9600]);
+        samples.extend(voice(0.5, 0.3));
+        samples.extend(vec![0.0; 48000]);
+        assert_segments(
+            &detect(&samples, VoiceActivityOptions::default()),
+            &[
+                (Activity::Speech, 0.0, 1.2 + 0.32),
+                (Activity::Silence, 1.52, 2.2),
+            ],
+        );
+
+        let options = VoiceActivityOptions {
+            hangover: 0.1,
+            ..Default::default()
+        };
+        assert_segments(
+            &detect(&samples, options),
+            &[
+                (Activity::Speech, 0.0, 0.5 + 0.107),
+                (Activity::Silence, 0.607, 0.7),
+                (Activity::Speech, 0.7, 1.2 + 0.107),
+                (Activity::Silence, 1.307, 2.2),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_non_speech_is_silence() {
+        let options = VoiceActivityOptions::default();
+        // loud white noise is too flat, a hum and a whistle are outside the speech band
+        for signal in [
+            noise(1.0, 0.5),
+            tone(1.0, 50.0, 0.5),
+            tone(1.0, 8000.0, 0.5),
+            voice(1.0, 0.002),
+        ] {
+            assert_segments(&detect(&signal, options), &[(Activity::Silence, 0.0, 1.0)]);
+        }
+    }
+
+    #[test]
+    fn test_short_sounds_are_ignored() {
+        let mut samples = vec![0.0; 24000];
+        samples.extend(voice(0.03, 0.3));
+        samples.extend(vec![0.0; 24000]);
+        assert_segments(
+            &detect(&samples, VoiceActivityOptions::default()),
+            &[(Activity::Silence, 0.0, 1.03)],
+        );
+    }
+
+    #[test]
+    fn test_state_and_flush() {
+        let mut detector =
+            VoiceActivityDetector::new(RATE, VoiceActivityOptions::default()).unwrap();
+        assert_eq!(detector.get_window_duration(), 1024.0 / RATE);
+        assert!(detector.flush().is_none());
+        for frame in canned_pcm(&voice(0.2, 0.3)) {
+            assert!(detector.process(&frame).unwrap().is_empty());
+        }
+        assert_eq!(detector.get_activity(), Activity::Speech);
+        let segment = detector.flush().unwrap();
+        assert_eq!(segment.activity, Activity::Speech);
+        assert_eq!(segment.start.value, 1000);
+        assert_eq!(segment.end.value, 1000 + 9600);
+        assert_eq!(detector.get_activity(), Activity::Silence);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(VoiceActivityDetector::new(0.0, VoiceActivityOptions::default()).is_err());
+        let options = VoiceActivityOptions {
+            speech_band: (3400.0, 300.0),
+            ..Default::default()
+        };
+        assert!(VoiceActivityDetector::new(RATE, options).is_err());
+        let mut detector =
+            VoiceActivityDetector::new(RATE, VoiceActivityOptions::default()).unwrap();
+        let frame =
+            AudioFrame::from_planar(44100.0, vec![vec![0.0f32]], CMTime::default()).unwrap();
+        assert!(detector.process(&frame).is_err());
+    }
+
+    #[test]
+    fn test_fft() {
+        let mut re = vec![0.0; 8];
+        let mut im = vec![0.0; 8];
+        for (index, sample) in re.iter_mut().enumerate() {
+            *sample = (2.0 * PI * index as f64 / 4.0).cos();
+        }
+        fft(&mut re, &mut im);
+        let magnitudes: Vec<f64> = re
+            .iter()
+            .zip(&im)
+            .map(|(re, im)| (re * re + im * im).sqrt())
+            .collect();
+        for (bin, magnitude) in magnitudes.iter().enumerate() {
+            let expected = if bin == 2 || bin == 6 { 4.0 } else { 0.0 };
+            assert!((magnitude - expected).abs() < 1e-9, "{magnitudes:?}");
+        }
+    }
+}
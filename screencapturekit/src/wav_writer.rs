@@ -0,0 +1,623 @@
+//! Writing of captured audio to WAV files.
+//!
+//! A file starts as a RIFF WAVE file with a `JUNK` chunk reserved after the header. When
+//! [`WavWriter::finish`] finds that the file has outgrown the 4 GiB of RIFF, it turns the file
+//! into RF64 by replacing the `JUNK` chunk with a `ds64` chunk holding the 64-bit sizes, as
+//! EBU Tech 3306 describes.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, ErrorKind, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use screencapturekit_sys::cm_format_description_ref::AudioStreamBasicDescription;
+
+use crate::{
+    audio_frame::{AudioFrame, SampleFormat, I24},
+    cm_sample_buffer::CMSampleBuffer,
+    sc_output_handler::{SCStreamOutputType, StreamOutput},
+};
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+/// The tail of the `KSDATAFORMAT_SUBTYPE_PCM` and `KSDATAFORMAT_SUBTYPE_IEEE_FLOAT` GUIDs,
+/// which start with the format tag.
+const SUBFORMAT_GUID_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+];
+/// The size of the `ds64` chunk without the table of other chunk sizes.
+const DS64_SIZE: u32 = 28;
+
+/// The format of the samples of a WAV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavFormat {
+    pub sample_format: SampleFormat,
+    pub channel_count: u16,
+    pub sample_rate: u32,
+}
+
+impl WavFormat {
+    pub fn from_description(description: &AudioStreamBasicDescription) -> Result<Self, String> {
+        Self::new(
+            SampleFormat::from_description(description)?,
+            description.channels_per_frame as usize,
+            description.sample_rate,
+        )
+    }
+
+    pub fn from_audio_frame(frame: &AudioFrame) -> Result<Self, String> {
+        Self::new(
+            frame.get_sample_format(),
+            frame.get_channel_count(),
+            frame.get_sample_rate(),
+        )
+    }
+
+    fn new(
+        sample_format: SampleFormat,
+        channel_count: usize,
+        sample_rate: f64,
+    ) -> Result<Self, String> {
+        let channel_count = u16::try_from(channel_count)
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or_else(|| format!("A WAV file can't hold {channel_count} channels"))?;
+        if sample_rate.fract() != 0.0 || !(1.0..=f64::from(u32::MAX)).contains(&sample_rate) {
+            return Err(format!(
+                "A WAV file can't have a sample rate of {sample_rate}"
+            ));
+        }
+        Ok(Self {
+            sample_format,
+            channel_count,
+            sample_rate: sample_rate as u32,
+        })
+    }
+
+    fn block_align(&self) -> u16 {
+        self.channel_count * self.sample_format.bytes_per_sample() as u16
+    }
+}
+
+/// Where the sizes that [`WavWriter::finish`] patches are in the file.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    fact_offset: Option<u64>,
+    data_size_offset: u64,
+    data_offset: u64,
+}
+
+fn write_header(writer: &mut impl Write, format: &WavFormat) -> io::Result<Layout> {
+    let bits = format.sample_format.bytes_per_sample() as u16 * 8;
+    let tag = match format.sample_format {
+        SampleFormat::F32 => WAVE_FORMAT_IEEE_FLOAT,
+        SampleFormat::I16 | SampleFormat::I24 | SampleFormat::I32 => WAVE_FORMAT_PCM,
+    };
+    // more than two channels need the extensible format
+    let extensible = format.channel_count > 2;
+    let mut fmt = Vec::with_capacity(40);
+    fmt.extend(
+        if extensible {
+            WAVE_FORMAT_EXTENSIBLE
+        } else {
+            tag
+        }
+        .to_le_bytes(),
+    );
+    fmt.extend(format.channel_count.to_le_bytes());
+    fmt.extend(format.sample_rate.to_le_bytes());
+    fmt.extend((format.sample_rate * u32::from(format.block_align())).to_le_bytes());
+    fmt.extend(format.block_align().to_le_bytes());
+    fmt.extend(bits.to_le_bytes());
+    if extensible {
+        fmt.extend(22u16.to_le_bytes());
+        fmt.extend(bits.to_le_bytes());
+        // no speaker positions
+        fmt.extend(0u32.to_le_bytes());
+        fmt.extend(tag.to_le_bytes());
+        fmt.extend(SUBFORMAT_GUID_TAIL);
+    } else if tag != WAVE_FORMAT_PCM {
+        fmt.extend(0u16.to_le_bytes());
+    }
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&0u32.to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"JUNK")?;
+    writer.write_all(&DS64_SIZE.to_le_bytes())?;
+    writer.write_all(&[0; DS64_SIZE as usize])?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&(fmt.len() as u32).to_le_bytes())?;
+    writer.write_all(&fmt)?;
+    let mut offset = 12 + 8 + u64::from(DS64_SIZE) + 8 + fmt.len() as u64;
+    // formats other than integer PCM have a `fact` chunk with the number of frames
+    let fact_offset = if tag == WAVE_FORMAT_PCM {
+        None
+    } else {
+        writer.write_all(b"fact")?;
+        writer.write_all(&4u32.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        offset += 12;
+        Some(offset - 4)
+    };
+    writer.write_all(b"data")?;
+    writer.write_all(&0u32.to_le_bytes())?;
+    Ok(Layout {
+        fact_offset,
+        data_size_offset: offset + 4,
+        data_offset: offset + 8,
+    })
+}
+
+fn invalid_input(message: impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidInput, message.into())
+}
+
+struct WavState<W: Write + Seek> {
+    writer: W,
+    /// The position of the writer when the writer was created.
+    start: u64,
+    header: Option<(WavFormat, Layout)>,
+    data_size: u64,
+    frame_count: u64,
+    error: Option<io::Error>,
+    /// The largest RIFF size before the file turns into RF64.
+    riff_limit: u64,
+}
+
+impl<W: Write + Seek> WavState<W> {
+    fn write_header(&mut self, format: WavFormat) -> io::Result<()> {
+        let layout = write_header(&mut self.writer, &format)?;
+        self.header = Some((format, layout));
+        Ok(())
+    }
+
+    fn write_frame(&mut self, frame: &AudioFrame) -> io::Result<()> {
+        let format = WavFormat::from_audio_frame(frame).map_err(invalid_input)?;
+        match self.header {
+            None => self.write_header(format)?,
+            Some((header_format, _)) if header_format != format => {
+                return Err(invalid_input(format!(
+                    "Audio in {format:?} can't be added to a WAV file of {header_format:?}"
+                )))
+            }
+            Some(_) => {}
+        }
+        let bytes: Vec<u8> = match format.sample_format {
+            SampleFormat::F32 => frame
+                .to_interleaved::<f32>()
+                .into_iter()
+                .flatten()
+                .flat_map(f32::to_le_bytes)
+                .collect(),
+            SampleFormat::I16 => frame
+                .to_interleaved::<i16>()
+                .into_iter()
+                .flatten()
+                .flat_map(i16::to_le_bytes)
+                .collect(),
+            SampleFormat::I24 => frame
+                .to_interleaved::<I24>()
+                .into_iter()
+                .flatten()
+                .flat_map(I24::to_le_bytes)
+                .collect(),
+            SampleFormat::I32 => frame
+                .to_interleaved::<i32>()
+                .into_iter()
+                .flatten()
+                .flat_map(i32::to_le_bytes)
+                .collect(),
+        };
+        self.writer.write_all(&bytes)?;
+        self.data_size += bytes.len() as u64;
+        self.frame_count += frame.get_frame_count() as u64;
+        Ok(())
+    }
+
+    fn patch_sizes(&mut self) -> io::Result<()> {
+        let Some((_, layout)) = self.header else {
+            return Err(invalid_input(
+                "No audio was written, so the format of the WAV file is unknown",
+            ));
+        };
+        // chunks have an even size, which 24-bit samples need padding for
+        let padding = self.data_size % 2;
+        let riff_size = layout.data_offset - 8 + self.data_size + padding;
+        let is_rf64 = riff_size > self.riff_limit;
+        let start = self.start;
+        let writer = &mut self.writer;
+        let mut patch = |offset: u64, bytes: &[u8]| -> io::Result<()> {
+            writer.seek(SeekFrom::Start(start + offset))?;
+            writer.write_all(bytes)
+        };
+        if is_rf64 {
+            let mut ds64 = Vec::with_capacity(8 + DS64_SIZE as usize);
+            ds64.extend(b"ds64");
+            ds64.extend(DS64_SIZE.to_le_bytes());
+            ds64.extend(riff_size.to_le_bytes());
+            ds64.extend(self.data_size.to_le_bytes());
+            ds64.extend(self.frame_count.to_le_bytes());
+            // no table of other chunk sizes
+            ds64.extend(0u32.to_le_bytes());
+            patch(0, b"RF64")?;
+            patch(4, &u32::MAX.to_le_bytes())?;
+            patch(12, &ds64)?;
+            patch(layout.data_size_offset, &u32::MAX.to_le_bytes())?;
+        } else {
+            patch(4, &(riff_size as u32).to_le_bytes())?;
+            patch(
+                layout.data_size_offset,
+                &(self.data_size as u32).to_le_bytes(),
+            )?;
+        }
+        if let Some(fact_offset) = layout.fact_offset {
+            let frame_count = u32::try_from(self.frame_count).unwrap_or(u32::MAX);
+            patch(fact_offset, &frame_count.to_le_bytes())?;
+        }
+        let data_end = start + layout.data_offset + self.data_size;
+        if padding != 0 {
+            self.writer.seek(SeekFrom::Start(data_end))?;
+            self.writer.write_all(&[0])?;
+        }
+        // more audio overwrites the padding
+        self.writer.seek(SeekFrom::Start(data_end))?;
+        self.writer.flush()
+    }
+}
+
+/// Writes audio to a WAV file, interleaving planar audio.
+///
+/// The samples keep the format the stream delivers, 32-bit float for ScreenCaptureKit. Add a
+/// clone of the writer to a stream as an audio output, and call [`WavWriter::finish`] on
+/// another clone after stopping the capture to write the sizes into the header.
+pub struct WavWriter<W: Write + Seek> {
+    state: Arc<Mutex<WavState<W>>>,
+}
+
+impl<W: Write + Seek> Clone for WavWriter<W> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Creates a writer that takes the format of the file from the first audio it writes.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        let start = writer.stream_position()?;
+        Ok(Self {
+            state: Arc::new(Mutex::new(WavState {
+                writer,
+                start,
+                header: None,
+                data_size: 0,
+                frame_count: 0,
+                error: None,
+                riff_limit: u64::from(u32::MAX),
+            })),
+        })
+    }
+
+    /// Creates a writer for audio in the format of `description` and writes the header.
+    pub fn with_description(
+        writer: W,
+        description: &AudioStreamBasicDescription,
+    ) -> io::Result<Self> {
+        let format = WavFormat::from_description(description).map_err(invalid_input)?;
+        let wav_writer = Self::new(writer)?;
+        wav_writer.state.lock().unwrap().write_header(format)?;
+        Ok(wav_writer)
+    }
+
+    /// Appends the samples of an audio sample buffer.
+    pub fn record(&self, sample: &CMSampleBuffer) -> io::Result<()> {
+        let frame = AudioFrame::try_from(sample).map_err(invalid_input)?;
+        self.write_frame(&frame)
+    }
+
+    pub fn write_frame(&self, frame: &AudioFrame) -> io::Result<()> {
+        self.state.lock().unwrap().write_frame(frame)
+    }
+
+    pub fn get_format(&self) -> Option<WavFormat> {
+        self.state.lock().unwrap().header.map(|(format, _)| format)
+    }
+
+    /// The number of samples written for each channel.
+    pub fn get_frame_count(&self) -> u64 {
+        self.state.lock().unwrap().frame_count
+    }
+
+    /// Writes the sizes into the header and returns the first error that occurred while
+    /// writing from a stream callback.
+    ///
+    /// Audio written after finishing is kept, as long as `finish` is called again.
+    pub fn finish(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(error) = state.error.take() {
+            return Err(error);
+        }
+        state.patch_sizes()
+    }
+}
+
+impl<W: Write + Seek + Send + 'static> StreamOutput for WavWriter<W> {
+    fn did_output_sample_buffer(&self, sample_buffer: CMSampleBuffer, of_type: SCStreamOutputType) {
+        if of_type != SCStreamOutputType::Audio {
+            return;
+        }
+        let frame = AudioFrame::try_from(&sample_buffer);
+        let mut state = self.state.lock().unwrap();
+        if state.error.is_none() {
+            let written = frame
+                .map_err(invalid_input)
+                .and_then(|frame| state.write_frame(&frame));
+            state.error = written.err();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use screencapturekit_sys::{
+        audio_buffer::CopiedAudioBuffer,
+        cm_format_description_ref::{
+            kAudioFormatFlagIsFloat, kAudioFormatFlagIsNonInterleaved, kAudioFormatFlagIsPacked,
+            kAudioFormatLinearPCM,
+        },
+        os_types::base::CMTime,
+        sc_stream_frame_info::SCFrameStatus,
+    };
+
+    use super::*;
+
+    fn written(writer: &WavWriter<Cursor<Vec<u8>>>) -> Vec<u8> {
+        writer.state.lock().unwrap().writer.get_ref().clone()
+    }
+
+    fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    /// Returns the offset of the body of the first chunk with `id`.
+    fn chunk(bytes: &[u8], id: &[u8; 4]) -> Option<usize> {
+        let mut offset = 12;
+        while offset + 8 <= bytes.len() {
+            if &bytes[offset..offset + 4] == id {
+                return Some(offset + 8);
+            }
+            let size = u32_at(bytes, offset + 4) as usize;
+            offset += 8 + size + size % 2;
+        }
+        None
+    }
+
+    fn stereo_f32() -> AudioFrame {
+        AudioFrame::from_planar(
+            48000.0,
+            vec![vec![0.5f32, -0.5, 0.25], vec![1.0, -1.0, 0.0]],
+            CMTime::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_float_wav() {
+        let writer = WavWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.write_frame(&stereo_f32()).unwrap();
+        writer.write_frame(&stereo_f32()).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(writer.get_frame_count(), 6);
+
+        let bytes = written(&writer);
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(u32_at(&bytes, 4) as usize, bytes.len() - 8);
+        assert_eq!(&bytes[8..12], b"WAVE");
+        assert!(chunk(&bytes, b"JUNK").is_some());
+
+        let fmt = chunk(&bytes, b"fmt ").unwrap();
+        assert_eq!(u32_at(&bytes, fmt - 4), 18);
+        assert_eq!(u16_at(&bytes, fmt), WAVE_FORMAT_IEEE_FLOAT);
+        assert_eq!(u16_at(&bytes, fmt + 2), 2);
+        assert_eq!(u32_at(&bytes, fmt + 4), 48000);
+        assert_eq!(u32_at(&bytes, fmt + 8), 48000 * 8);
+        assert_eq!(u16_at(&bytes, fmt + 12), 8);
+        assert_eq!(u16_at(&bytes, fmt + 14), 32);
+        assert_eq!(u32_at(&bytes, chunk(&bytes, b"fact").unwrap()), 6);
+
+        let data = chunk(&bytes, b"data").unwrap();
+        assert_eq!(u32_at(&bytes, data - 4), 6 * 8);
+        let samples: Vec<f32> = bytes[data..]
+            .chunks_exact(4)
+            .map(|sample| f32::from_le_bytes(sample.try_into().unwrap()))
+            .collect();
+        assert_eq!(samples[..6], [0.5, 1.0, -0.5, -1.0, 0.25, 0.0]);
+        assert_eq!(samples.len(), 12);
+    }
+
+    #[test]
+    fn test_integer_and_multichannel_wav() {
+        let mono =
+            AudioFrame::from_interleaved(8000.0, 1, vec![1i16, -2, 3], CMTime::default()).unwrap();
+        let writer = WavWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.write_frame(&mono).unwrap();
+        writer.finish().unwrap();
+        let bytes = written(&writer);
+        let fmt = chunk(&bytes, b"fmt ").unwrap();
+        assert_eq!(u32_at(&bytes, fmt - 4), 16);
+        assert_eq!(u16_at(&bytes, fmt), WAVE_FORMAT_PCM);
+        assert_eq!(u16_at(&bytes, fmt + 14), 16);
+        assert!(chunk(&bytes, b"fact").is_none());
+        let data = chunk(&bytes, b"data").unwrap();
+        assert_eq!(bytes[data..], [1, 0, 0xfe, 0xff, 3, 0]);
+
+        let surround =
+            AudioFrame::from_planar(48000.0, vec![vec![7i32; 2]; 6], CMTime::default()).unwrap();
+        let writer = WavWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.write_frame(&surround).unwrap();
+        writer.finish().unwrap();
+        let bytes = written(&writer);
+        let fmt = chunk(&bytes, b"fmt ").unwrap();
+        assert_eq!(u32_at(&bytes, fmt - 4), 40);
+        assert_eq!(u16_at(&bytes, fmt), WAVE_FORMAT_EXTENSIBLE);
+        assert_eq!(u16_at(&bytes, fmt + 2), 6);
+        assert_eq!(u16_at(&bytes, fmt + 12), 24);
+        assert_eq!(u16_at(&bytes, fmt + 24), WAVE_FORMAT_PCM);
+        assert_eq!(bytes[fmt + 26..fmt + 40], SUBFORMAT_GUID_TAIL);
+        assert_eq!(u32_at(&bytes, chunk(&bytes, b"data").unwrap() - 4), 48);
+
+        let packed = AudioFrame::from_interleaved(
+            48000.0,
+            1,
+            vec![I24::MIN, I24::new(-2).unwrap(), I24::MAX],
+            CMTime::default(),
+        )
+        .unwrap();
+        let writer = WavWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.write_frame(&packed).unwrap();
+        writer.finish().unwrap();
+        let bytes = written(&writer);
+        let fmt = chunk(&bytes, b"fmt ").unwrap();
+        assert_eq!(u16_at(&bytes, fmt), WAVE_FORMAT_PCM);
+        assert_eq!(u16_at(&bytes, fmt + 12), 3);
+        assert_eq!(u16_at(&bytes, fmt + 14), 24);
+        let data = chunk(&bytes, b"data").unwrap();
+        assert_eq!(u32_at(&bytes, data - 4), 9);
+        assert_eq!(
+            bytes[data..],
+            [0, 0, 0x80, 0xfe, 0xff, 0xff, 0xff, 0xff, 0x7f, 0]
+        );
+        assert_eq!(u32_at(&bytes, 4) as usize, bytes.len() - 8);
+
+        // audio written after finishing replaces the padding
+        writer.write_frame(&packed).unwrap();
+        writer.finish().unwrap();
+        let bytes = written(&writer);
+        assert_eq!(u32_at(&bytes, data - 4), 18);
+        assert_eq!(bytes.len(), data + 18);
+        assert_eq!(bytes[data + 9..data + 12], [0, 0, 0x80]);
+    }
+
+    #[test]
+    fn test_rf64() {
+        let writer = WavWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.state.lock().unwrap().riff_limit = 150;
+        writer.write_frame(&stereo_f32()).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(&written(&writer)[..4], b"RIFF");
+
+        for _ in 0..3 {
+            writer.write_frame(&stereo_f32()).unwrap();
+        }
+        writer.finish().unwrap();
+        let bytes = written(&writer);
+        assert_eq!(&bytes[..4], b"RF64");
+        assert_eq!(u32_at(&bytes, 4), u32::MAX);
+        let ds64 = chunk(&bytes, b"ds64").unwrap();
+        assert_eq!(u64_at(&bytes, ds64) as usize, bytes.len() - 8);
+        assert_eq!(u64_at(&bytes, ds64 + 8), 12 * 8);
+        assert_eq!(u64_at(&bytes, ds64 + 16), 12);
+        assert_eq!(u32_at(&bytes, ds64 + 24), 0);
+        // the data chunk can't be found by its 32-bit size, but starts where it did
+        let data_size = bytes.len() - 12 * 8 - 4;
+        assert_eq!(u32_at(&bytes, data_size), u32::MAX);
+        assert_eq!(&bytes[data_size - 4..data_size], b"data");
+    }
+
+    #[test]
+    fn test_stream_output() {
+        let description = AudioStreamBasicDescription {
+            sample_rate: 48000.0,
+            format_id: kAudioFormatLinearPCM,
+            format_flags: kAudioFormatFlagIsFloat
+                | kAudioFormatFlagIsPacked
+                | kAudioFormatFlagIsNonInterleaved,
+            bytes_per_packet: 4,
+            frames_per_packet: 1,
+            bytes_per_frame: 4,
+            channels_per_frame: 2,
+            bits_per_channel: 32,
+            reserved: 0,
+        };
+        let writer = WavWriter::with_description(Cursor::new(Vec::new()), &description).unwrap();
+        assert_eq!(
+            writer.get_format(),
+            Some(WavFormat {
+                sample_format: SampleFormat::F32,
+                channel_count: 2,
+                sample_rate: 48000,
+            })
+        );
+        let channel = CopiedAudioBuffer {
+            number_channels: 1,
+            data: 0.5f32.to_le_bytes().repeat(1024),
+        };
+        for _ in 0..2 {
+            writer.did_output_sample_buffer(
+                CMSampleBuffer::from_audio_buffers(
+                    description,
+                    vec![channel.clone(), channel.clone()],
+                    CMTime::default(),
+                ),
+                SCStreamOutputType::Audio,
+            );
+        }
+        writer.did_output_sample_buffer(
+            CMSampleBuffer::from_pixel_buffer(None, SCFrameStatus::Idle, CMTime::default()),
+            SCStreamOutputType::Screen,
+        );
+        writer.finish().unwrap();
+        assert_eq!(writer.get_frame_count(), 2048);
+
+        let mono = AudioStreamBasicDescription {
+            channels_per_frame: 1,
+            ..description
+        };
+        writer.did_output_sample_buffer(
+            CMSampleBuffer::from_audio_buffers(mono, vec![channel], CMTime::default()),
+            SCStreamOutputType::Audio,
+        );
+        assert!(writer.finish().is_err());
+    }
+
+    #[test]
+    fn test_invalid_audio() {
+        let writer = WavWriter::new(Cursor::new(Vec::new())).unwrap();
+        assert!(writer.finish().is_err());
+        writer.write_frame(&stereo_f32()).unwrap();
+        let other_rate =
+            AudioFrame::from_planar(44100.0, vec![vec![0f32]; 2], CMTime::default()).unwrap();
+        assert!(writer.write_frame(&other_rate).is_err());
+        let integers =
+            AudioFrame::from_planar(48000.0, vec![vec![0i16]; 2], CMTime::default()).unwrap();
+        assert!(writer.write_frame(&integers).is_err());
+
+        let odd_rate =
+            AudioFrame::from_planar(44100.5, vec![vec![0f32]; 2], CMTime::default()).unwrap();
+        assert!(WavWriter::new(Cursor::new(Vec::new()))
+            .unwrap()
+            .write_frame(&odd_rate)
+            .is_err());
+    }
+}